@@ -0,0 +1,9 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.9" tiledversion="1.9.2" name="dungeon" tilewidth="16" tileheight="16" tilecount="2" columns="2">
+ <image source="dungeon.png" width="32" height="16"/>
+ <tile id="1">
+  <properties>
+   <property name="walkable" type="bool" value="false"/>
+  </properties>
+ </tile>
+</tileset>
@@ -0,0 +1,668 @@
+//! Command line tool to process navmeshes in mesh 2, mesh 3 or OBJ format,
+//! or built from glTF scenes.
+//!
+//! Exit codes: 0 on success, 1 if the mesh is invalid or no path was found,
+//! 2 on invalid arguments, 3 if a file couldn't be read, parsed or written.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use glam::Vec2;
+use meshquisse::{
+    binary::{self, BinaryMesh, BinaryOptions},
+    gltf_import::{load_gltf_navmesh, GltfImportOptions},
+    islands::Islands,
+    mesh_data::{merge_triangles::ConvexPolygonsMeshData, IntoPAMesh},
+    navmesh::NavMesh,
+    obj::{read_obj, write_obj, ObjImportOptions},
+    serialization,
+    svg::Svg,
+    tilemap::{ldtk::read_ldtk, tiled::read_tmx, TileMapImportOptions},
+    trianglemerger::{AreaType, MeshMerger},
+    validation::MeshIssue,
+};
+use serde::Serialize;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// print reports as JSON
+    #[arg(long, global = true)]
+    json: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// merge triangles into convex polygons
+    Merge(MergeArgs),
+    /// check the mesh is usable by polyanya, fails if it isn't
+    Validate(InputArgs),
+    /// fix what validate reports, fails if issues remain
+    Repair(OutputArgs),
+    /// print counts and areas
+    Stats(InputArgs),
+    /// write the mesh in another format
+    Convert(OutputArgs),
+    /// search a path, fails if there is none
+    Path(PathArgs),
+    /// time reading, merging and path queries
+    Bench(BenchArgs),
+    /// draw the mesh, and optionally a path, as SVG
+    Svg(SvgArgs),
+}
+
+#[derive(Args, Debug)]
+struct InputArgs {
+    /// path to the mesh
+    input: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct OutputArgs {
+    #[command(flatten)]
+    input: InputArgs,
+    /// where to write the mesh, stdout if not given
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    #[arg(short, long, value_enum, default_value_t = MeshFormat::Mesh3)]
+    format: MeshFormat,
+}
+
+#[derive(Args, Debug)]
+struct MergeArgs {
+    #[command(flatten)]
+    output: OutputArgs,
+    /// islands with an area below this value are removed from the merged mesh
+    #[arg(long)]
+    min_island_area: Option<f32>,
+}
+
+#[derive(Args, Debug)]
+struct PathArgs {
+    #[command(flatten)]
+    input: InputArgs,
+    /// start of the path, as `x,y`
+    #[arg(long, value_parser = parse_point, allow_hyphen_values = true)]
+    from: Vec2,
+    /// end of the path, as `x,y`
+    #[arg(long, value_parser = parse_point, allow_hyphen_values = true)]
+    to: Vec2,
+}
+
+#[derive(Args, Debug)]
+struct BenchArgs {
+    #[command(flatten)]
+    input: InputArgs,
+    /// number of runs of each step
+    #[arg(long, default_value_t = 10)]
+    iterations: u32,
+    /// number of path queries per run, between polygon centers
+    #[arg(long, default_value_t = 100)]
+    queries: u32,
+}
+
+#[derive(Args, Debug)]
+struct SvgArgs {
+    #[command(flatten)]
+    input: InputArgs,
+    /// where to write the SVG, stdout if not given
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// merge the mesh before drawing it
+    #[arg(long)]
+    merge: bool,
+    /// write polygon ids
+    #[arg(long)]
+    ids: bool,
+    /// start of a path to draw, as `x,y`
+    #[arg(long, value_parser = parse_point, allow_hyphen_values = true, requires = "to")]
+    from: Option<Vec2>,
+    /// end of a path to draw, as `x,y`
+    #[arg(long, value_parser = parse_point, allow_hyphen_values = true, requires = "from")]
+    to: Option<Vec2>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum MeshFormat {
+    Mesh2,
+    /// mesh 2 with area types
+    Mesh3,
+    /// Wavefront OBJ, Y up
+    Obj,
+    /// `ConvexPolygonsMeshData` as RON
+    Ron,
+    /// `ConvexPolygonsMeshData` as JSON
+    Json,
+    /// `ConvexPolygonsMeshData` as bincode
+    Bincode,
+    /// binary navmesh with islands and spatial index, see `doc/binary_format.txt`
+    Binary,
+}
+
+fn parse_point(value: &str) -> Result<Vec2, String> {
+    let (x, y) = value
+        .split_once(',')
+        .ok_or_else(|| format!("expected `x,y`, got `{value}`"))?;
+    let parse = |v: &str| v.trim().parse::<f32>().map_err(|error| error.to_string());
+    Ok(Vec2::new(parse(x)?, parse(y)?))
+}
+
+enum CliError {
+    /// The command ran but its check failed.
+    Failed(String),
+    Io(String),
+}
+
+impl From<io::Error> for CliError {
+    fn from(error: io::Error) -> Self {
+        CliError::Io(error.to_string())
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        Command::Merge(args) => merge(args, cli.json),
+        Command::Validate(args) => validate(args, cli.json),
+        Command::Repair(args) => repair(args, cli.json),
+        Command::Stats(args) => stats(args, cli.json),
+        Command::Convert(args) => convert(args, cli.json),
+        Command::Path(args) => path(args, cli.json),
+        Command::Bench(args) => bench(args, cli.json),
+        Command::Svg(args) => svg(args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::Failed(message)) => {
+            eprintln!("{message}");
+            ExitCode::from(1)
+        }
+        Err(CliError::Io(message)) => {
+            eprintln!("error: {message}");
+            ExitCode::from(3)
+        }
+    }
+}
+
+fn read(args: &InputArgs) -> Result<Vec<u8>, CliError> {
+    fs::read(&args.input)
+        .map_err(|error| CliError::Io(format!("{}: {error}", args.input.display())))
+}
+
+/// Reads a mesh, binary meshes are recognized by their magic,
+/// other files than mesh 2 or 3 by their extension.
+fn load(args: &InputArgs) -> Result<MeshMerger, CliError> {
+    parse(args, &read(args)?)
+}
+
+fn parse(args: &InputArgs, bytes: &[u8]) -> Result<MeshMerger, CliError> {
+    let error =
+        |error: &dyn std::fmt::Display| CliError::Io(format!("{}: {error}", args.input.display()));
+    let extension = args.input.extension().and_then(|e| e.to_str());
+    if bytes.starts_with(&binary::MAGIC) {
+        MeshMerger::from_binary(bytes).map_err(|e| error(&e))
+    } else if matches!(extension, Some("gltf" | "glb")) {
+        let options = GltfImportOptions {
+            merge: false,
+            ..Default::default()
+        };
+        load_gltf_navmesh(&args.input, &options)
+            .map(|mesh_data| MeshMerger::from(&mesh_data))
+            .map_err(|e| error(&e))
+    } else if extension == Some("obj") {
+        let options = ObjImportOptions {
+            merge: false,
+            ..Default::default()
+        };
+        read_obj(&String::from_utf8_lossy(bytes), &options)
+            .map(|mesh_data| MeshMerger::from(&mesh_data))
+            .map_err(|e| error(&e))
+    } else if let Some(extension @ ("tmx" | "ldtk")) = extension {
+        let options = TileMapImportOptions {
+            merge: false,
+            ..Default::default()
+        };
+        match extension {
+            "tmx" => read_tmx(&args.input, &options),
+            _ => read_ldtk(&args.input, &options),
+        }
+        .map(|mesh_data| MeshMerger::from(&mesh_data))
+        .map_err(|e| error(&e))
+    } else if let Some(extension @ ("ron" | "json" | "bincode")) = extension {
+        let text = || String::from_utf8_lossy(bytes);
+        let mesh_data: ConvexPolygonsMeshData = match extension {
+            "ron" => serialization::from_ron(&text()),
+            "json" => serialization::from_json(&text()),
+            _ => serialization::from_bincode(bytes),
+        }
+        .map_err(|e| error(&e))?;
+        mesh_data.check_ids().map_err(|e| error(&e))?;
+        Ok(MeshMerger::from(&mesh_data))
+    } else {
+        MeshMerger::try_from_bytes(bytes).map_err(|e| error(&e))
+    }
+}
+
+/// Writes the mesh, then the report: on stdout if the mesh went to a file, on stderr otherwise.
+fn write_mesh(
+    mesh_merger: &MeshMerger,
+    args: &OutputArgs,
+    json: bool,
+    report: &impl Report,
+) -> Result<(), CliError> {
+    let mesh_data = || ConvexPolygonsMeshData::from(mesh_merger);
+    let content = match args.format {
+        MeshFormat::Mesh2 => mesh_merger.to_mesh2_format().into_bytes(),
+        MeshFormat::Mesh3 => mesh_merger.to_mesh3_format().into_bytes(),
+        MeshFormat::Obj => write_obj(&mesh_data().to_pa_mesh()).into_bytes(),
+        MeshFormat::Ron => serialization::to_ron(&mesh_data())
+            .map_err(|error| CliError::Io(error.to_string()))?
+            .into_bytes(),
+        MeshFormat::Json => serialization::to_json(&mesh_data())
+            .map_err(|error| CliError::Io(error.to_string()))?
+            .into_bytes(),
+        MeshFormat::Bincode => serialization::to_bincode(&mesh_data())
+            .map_err(|error| CliError::Io(error.to_string()))?,
+        MeshFormat::Binary => mesh_merger.to_binary(&BinaryOptions::default()),
+    };
+    match &args.output {
+        Some(output) => {
+            fs::write(output, content)
+                .map_err(|error| CliError::Io(format!("{}: {error}", output.display())))?;
+            print_report(&mut io::stdout(), json, report)
+        }
+        None => {
+            io::stdout().write_all(&content)?;
+            print_report(&mut io::stderr(), json, report)
+        }
+    }
+}
+
+trait Report: Serialize {
+    fn text(&self) -> String;
+}
+
+fn print_report(out: &mut impl Write, json: bool, report: &impl Report) -> Result<(), CliError> {
+    if json {
+        serde_json::to_writer_pretty(&mut *out, report)
+            .map_err(|error| CliError::Io(error.to_string()))?;
+        writeln!(out)?;
+    } else {
+        writeln!(out, "{}", report.text())?;
+    }
+    Ok(())
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[derive(Serialize)]
+struct MergeReport {
+    polygons_before: usize,
+    polygons_after: usize,
+    pruned_polygons: usize,
+    merge_ms: f64,
+}
+
+impl Report for MergeReport {
+    fn text(&self) -> String {
+        format!(
+            "merged {} polygons into {} ({} pruned) in {:.2}ms",
+            self.polygons_before, self.polygons_after, self.pruned_polygons, self.merge_ms
+        )
+    }
+}
+
+fn merge(args: &MergeArgs, json: bool) -> Result<(), CliError> {
+    let mut mesh_merger = load(&args.output.input)?;
+    let polygons_before = mesh_merger.mesh_polygons.len();
+    let start = Instant::now();
+    mesh_merger.my_merge();
+    let pruned_polygons = args.min_island_area.map_or(0, |min_island_area| {
+        mesh_merger.prune_small_islands(min_island_area)
+    });
+    mesh_merger.remove_unused();
+    let report = MergeReport {
+        polygons_before,
+        polygons_after: mesh_merger.mesh_polygons.len(),
+        pruned_polygons,
+        merge_ms: millis(start.elapsed()),
+    };
+    write_mesh(&mesh_merger, &args.output, json, &report)
+}
+
+#[derive(Serialize)]
+struct ValidationReport {
+    valid: bool,
+    issues: Vec<String>,
+}
+
+impl ValidationReport {
+    fn new(issues: &[MeshIssue]) -> Self {
+        ValidationReport {
+            valid: issues.is_empty(),
+            issues: issues.iter().map(|issue| issue.to_string()).collect(),
+        }
+    }
+}
+
+impl Report for ValidationReport {
+    fn text(&self) -> String {
+        if self.valid {
+            "mesh is valid".to_string()
+        } else {
+            format!("{} issues:\n{}", self.issues.len(), self.issues.join("\n"))
+        }
+    }
+}
+
+fn validate(args: &InputArgs, json: bool) -> Result<(), CliError> {
+    let issues = load(args)?.validate();
+    print_report(&mut io::stdout(), json, &ValidationReport::new(&issues))?;
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::Failed(format!("{} issues found", issues.len())))
+    }
+}
+
+#[derive(Serialize)]
+struct RepairReport {
+    fixes: usize,
+    remaining: ValidationReport,
+}
+
+impl Report for RepairReport {
+    fn text(&self) -> String {
+        format!("{} fixes, {}", self.fixes, self.remaining.text())
+    }
+}
+
+fn repair(args: &OutputArgs, json: bool) -> Result<(), CliError> {
+    let mut mesh_data = ConvexPolygonsMeshData::from(&load(&args.input)?);
+    let fixes = mesh_data.repair();
+    let mesh_merger = MeshMerger::from(&mesh_data);
+    let issues = mesh_merger.validate();
+    let report = RepairReport {
+        fixes,
+        remaining: ValidationReport::new(&issues),
+    };
+    write_mesh(&mesh_merger, args, json, &report)?;
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::Failed(format!("{} issues remain", issues.len())))
+    }
+}
+
+#[derive(Serialize)]
+struct StatsReport {
+    vertices: usize,
+    polygons: usize,
+    islands: usize,
+    area: f32,
+    min: [f32; 2],
+    max: [f32; 2],
+    /// Number of polygons of each area type.
+    area_types: Vec<(u8, usize)>,
+}
+
+impl Report for StatsReport {
+    fn text(&self) -> String {
+        format!(
+            "vertices: {}\npolygons: {}\nislands: {}\narea: {}\nbounds: {:?} to {:?}\narea types: {}",
+            self.vertices,
+            self.polygons,
+            self.islands,
+            self.area,
+            self.min,
+            self.max,
+            self.area_types
+                .iter()
+                .map(|(area_type, count)| format!("{area_type}: {count}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+fn stats(args: &InputArgs, json: bool) -> Result<(), CliError> {
+    let mesh_merger = load(args)?;
+    let (min, max) = mesh_merger.mesh_vertices.iter().fold(
+        (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+        |(min, max), vertex| (min.min(vertex.p), max.max(vertex.p)),
+    );
+    let mut area_types: Vec<(u8, usize)> = Vec::new();
+    for polygon in mesh_merger.mesh_polygons.iter() {
+        let AreaType(area_type) = polygon.area_type;
+        match area_types.iter_mut().find(|(t, _)| *t == area_type) {
+            Some((_, count)) => *count += 1,
+            None => area_types.push((area_type, 1)),
+        }
+    }
+    area_types.sort_unstable();
+    let islands = Islands::from_mesh_merger(&mesh_merger);
+    let report = StatsReport {
+        vertices: mesh_merger.mesh_vertices.len(),
+        polygons: mesh_merger.mesh_polygons.len(),
+        islands: islands.island_count(),
+        area: islands.island_areas.iter().sum(),
+        min: min.to_array(),
+        max: max.to_array(),
+        area_types,
+    };
+    print_report(&mut io::stdout(), json, &report)
+}
+
+#[derive(Serialize)]
+struct ConvertReport {
+    vertices: usize,
+    polygons: usize,
+}
+
+impl Report for ConvertReport {
+    fn text(&self) -> String {
+        format!(
+            "wrote {} vertices and {} polygons",
+            self.vertices, self.polygons
+        )
+    }
+}
+
+fn convert(args: &OutputArgs, json: bool) -> Result<(), CliError> {
+    let mesh_merger = load(&args.input)?;
+    let report = ConvertReport {
+        vertices: mesh_merger.mesh_vertices.len(),
+        polygons: mesh_merger.mesh_polygons.len(),
+    };
+    write_mesh(&mesh_merger, args, json, &report)
+}
+
+fn navmesh(mesh_merger: &MeshMerger) -> NavMesh {
+    let mesh_data = ConvexPolygonsMeshData::from(mesh_merger);
+    NavMesh::new(mesh_data.to_pa_mesh()).with_area_types(mesh_data.area_types())
+}
+
+/// Binary meshes are read in place, with their stored islands and spatial index.
+fn load_navmesh(args: &InputArgs) -> Result<NavMesh, CliError> {
+    let bytes = read(args)?;
+    if bytes.starts_with(&binary::MAGIC) {
+        BinaryMesh::parse(&bytes)
+            .map(|mesh| NavMesh::from(&mesh))
+            .map_err(|error| CliError::Io(format!("{}: {error}", args.input.display())))
+    } else {
+        parse(args, &bytes).map(|mesh_merger| navmesh(&mesh_merger))
+    }
+}
+
+#[derive(Serialize)]
+struct PathReport {
+    length: f32,
+    points: Vec<[f32; 2]>,
+}
+
+impl Report for PathReport {
+    fn text(&self) -> String {
+        format!(
+            "length: {}\n{}",
+            self.length,
+            self.points
+                .iter()
+                .map(|[x, y]| format!("{x} {y}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }
+}
+
+fn path(args: &PathArgs, json: bool) -> Result<(), CliError> {
+    let navmesh = load_navmesh(&args.input)?;
+    let path = navmesh
+        .path(args.from, args.to)
+        .ok_or_else(|| CliError::Failed(format!("no path from {} to {}", args.from, args.to)))?;
+    let report = PathReport {
+        length: path.length,
+        points: std::iter::once(args.from)
+            .chain(path.path.iter().copied())
+            .map(|p| p.to_array())
+            .collect(),
+    };
+    print_report(&mut io::stdout(), json, &report)
+}
+
+/// Durations of a step over all iterations, in milliseconds.
+#[derive(Serialize)]
+struct Timing {
+    min: f64,
+    mean: f64,
+    max: f64,
+}
+
+impl Timing {
+    fn new(durations: &[Duration]) -> Self {
+        let millis: Vec<f64> = durations.iter().copied().map(millis).collect();
+        Timing {
+            min: millis.iter().copied().fold(f64::MAX, f64::min),
+            mean: millis.iter().sum::<f64>() / millis.len().max(1) as f64,
+            max: millis.iter().copied().fold(0.0, f64::max),
+        }
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "{:.3}ms (min {:.3}ms, max {:.3}ms)",
+            self.mean, self.min, self.max
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct BenchReport {
+    iterations: u32,
+    read: Timing,
+    merge: Timing,
+    navmesh: Timing,
+    /// All queries of a run.
+    queries: Timing,
+    paths_found: usize,
+}
+
+impl Report for BenchReport {
+    fn text(&self) -> String {
+        format!(
+            "read: {}\nmerge: {}\nnavmesh: {}\nqueries: {} ({} paths found per run)",
+            self.read.text(),
+            self.merge.text(),
+            self.navmesh.text(),
+            self.queries.text(),
+            self.paths_found
+        )
+    }
+}
+
+fn bench(args: &BenchArgs, json: bool) -> Result<(), CliError> {
+    let bytes = read(&args.input)?;
+    let (mut reads, mut merge, mut build, mut queries) = (vec![], vec![], vec![], vec![]);
+    let mut paths_found = 0;
+    for _ in 0..args.iterations {
+        let start = Instant::now();
+        let mut mesh_merger = parse(&args.input, &bytes)?;
+        reads.push(start.elapsed());
+
+        let start = Instant::now();
+        mesh_merger.my_merge();
+        mesh_merger.remove_unused();
+        merge.push(start.elapsed());
+
+        let start = Instant::now();
+        let navmesh = navmesh(&mesh_merger);
+        build.push(start.elapsed());
+
+        let centers: Vec<Vec2> = navmesh
+            .navmesh
+            .polygons
+            .iter()
+            .map(|polygon| {
+                polygon
+                    .vertices
+                    .iter()
+                    .map(|v| navmesh.navmesh.vertices[*v as usize].coords)
+                    .sum::<Vec2>()
+                    / polygon.vertices.len() as f32
+            })
+            .collect();
+        paths_found = 0;
+        let start = Instant::now();
+        if !centers.is_empty() {
+            for i in 0..args.queries as usize {
+                // Spread queries over the mesh, the same way on every run.
+                let from = centers[i % centers.len()];
+                let to = centers[(i * 7 + 3) % centers.len()];
+                if navmesh.path(from, to).is_some() {
+                    paths_found += 1;
+                }
+            }
+        }
+        queries.push(start.elapsed());
+    }
+    let report = BenchReport {
+        iterations: args.iterations,
+        read: Timing::new(&reads),
+        merge: Timing::new(&merge),
+        navmesh: Timing::new(&build),
+        queries: Timing::new(&queries),
+        paths_found,
+    };
+    print_report(&mut io::stdout(), json, &report)
+}
+
+fn svg(args: &SvgArgs) -> Result<(), CliError> {
+    let mut mesh_merger = load(&args.input)?;
+    if args.merge {
+        mesh_merger.my_merge();
+        mesh_merger.remove_unused();
+    }
+    let mut svg = Svg::from(&mesh_merger);
+    svg.polygon_ids = args.ids;
+    if let (Some(from), Some(to)) = (args.from, args.to) {
+        let path = navmesh(&mesh_merger)
+            .path(from, to)
+            .ok_or_else(|| CliError::Failed(format!("no path from {from} to {to}")))?;
+        svg = svg.with_path(std::iter::once(from).chain(path.path).collect());
+    }
+    let content = svg.to_svg_string();
+    match &args.output {
+        Some(output) => fs::write(output, content)
+            .map_err(|error| CliError::Io(format!("{}: {error}", output.display()))),
+        None => Ok(io::stdout().write_all(content.as_bytes())?),
+    }
+}
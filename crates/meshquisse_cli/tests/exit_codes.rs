@@ -0,0 +1,74 @@
+use std::{path::PathBuf, process::Command};
+
+fn run(args: &[&str]) -> Option<i32> {
+    Command::new(env!("CARGO_BIN_EXE_meshquisse"))
+        .args(args)
+        .output()
+        .unwrap()
+        .status
+        .code()
+}
+
+/// Writes `content` to a file of the temporary directory, named after the test.
+fn write_mesh(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("meshquisse_cli_{name}.mesh"));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+const QUAD: &str = "mesh\n2\n4 2\n0.0 0.0 2 0 -1\n1.5 0.0 3 0 1 -1\n1.5 1.5 2 1 -1\n0.0 1.5 3 -1 1 0\n3 0 1 3 -1 -1 1\n3 1 2 3 0 -1 -1\n";
+
+#[test]
+fn valid_mesh() {
+    let path = write_mesh("valid_mesh", QUAD);
+    assert_eq!(run(&["validate", path.to_str().unwrap()]), Some(0));
+}
+
+#[test]
+fn invalid_arguments() {
+    assert_eq!(run(&["validate"]), Some(2));
+}
+
+#[test]
+fn missing_file() {
+    assert_eq!(run(&["stats", "does/not/exist.mesh"]), Some(3));
+}
+
+#[test]
+fn negative_neighbour() {
+    let path = write_mesh(
+        "negative_neighbour",
+        &QUAD.replace("0.0 0.0 2 0 -1", "0.0 0.0 2 0 -2"),
+    );
+    assert_eq!(run(&["merge", path.to_str().unwrap()]), Some(3));
+}
+
+#[test]
+fn header_larger_than_input() {
+    let path = write_mesh(
+        "header_larger_than_input",
+        &QUAD.replace("4 2", "4000000000000 2"),
+    );
+    assert_eq!(run(&["stats", path.to_str().unwrap()]), Some(3));
+}
+
+#[test]
+fn bench_binary_mesh() {
+    let path = write_mesh("bench_binary_mesh", QUAD);
+    let binary = std::env::temp_dir().join("meshquisse_cli_bench_binary_mesh.bin");
+    assert_eq!(
+        run(&[
+            "convert",
+            path.to_str().unwrap(),
+            "--format",
+            "binary",
+            "--output",
+            binary.to_str().unwrap(),
+        ]),
+        Some(0)
+    );
+    assert_eq!(
+        run(&["bench", binary.to_str().unwrap(), "--iterations", "1"]),
+        Some(0)
+    );
+}
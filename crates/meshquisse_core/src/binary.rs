@@ -0,0 +1,661 @@
+//! Compact binary navmesh format, read in place from its bytes.
+//! See `doc/binary_format.txt`.
+
+use std::fmt;
+
+use glam::Vec2;
+
+use crate::{
+    islands::{Islands, NO_ISLAND},
+    trianglemerger::{AreaType, MeshMerger, Polygon, UnionFind, Vertex},
+};
+
+pub const MAGIC: [u8; 4] = *b"MQNM";
+pub const VERSION: u16 = 1;
+const HEADER_SIZE: usize = 24;
+const FLAG_ISLANDS: u16 = 1;
+const FLAG_SPATIAL_INDEX: u16 = 1 << 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryOptions {
+    /// Stores islands, so they don't have to be computed on load.
+    pub islands: bool,
+    /// Stores a grid of polygons, to find the polygon at a point without going through all of them.
+    pub spatial_index: bool,
+}
+
+impl Default for BinaryOptions {
+    fn default() -> Self {
+        BinaryOptions {
+            islands: true,
+            spatial_index: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinaryFormatError {
+    NotBinaryMesh,
+    UnsupportedVersion(u16),
+    /// The data ends before the section of that name.
+    Truncated(&'static str),
+    ChecksumMismatch,
+    /// Offsets or indices point outside of their arrays.
+    InvalidIndex(&'static str),
+}
+
+impl fmt::Display for BinaryFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryFormatError::NotBinaryMesh => write!(f, "not a binary navmesh"),
+            BinaryFormatError::UnsupportedVersion(version) => {
+                write!(f, "unsupported binary navmesh version {version}")
+            }
+            BinaryFormatError::Truncated(section) => write!(f, "data ends in {section}"),
+            BinaryFormatError::ChecksumMismatch => write!(f, "checksum mismatch"),
+            BinaryFormatError::InvalidIndex(section) => write!(f, "invalid index in {section}"),
+        }
+    }
+}
+
+impl std::error::Error for BinaryFormatError {}
+
+/// FNV-1a, 32 bits.
+fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ *byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+fn read_u32(bytes: &[u8], index: usize) -> u32 {
+    u32::from_le_bytes(bytes[index * 4..index * 4 + 4].try_into().unwrap())
+}
+
+fn read_i32(bytes: &[u8], index: usize) -> i32 {
+    read_u32(bytes, index) as i32
+}
+
+fn read_f32(bytes: &[u8], index: usize) -> f32 {
+    f32::from_bits(read_u32(bytes, index))
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend(value.to_le_bytes());
+}
+
+/// Polygons overlapping each cell of a uniform grid,
+/// to find the polygon at a point without going through all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpatialIndex {
+    cell_size: f32,
+    origin: Vec2,
+    columns: u32,
+    rows: u32,
+    /// Start of the polygons of each cell in `polygons`, followed by the end of the last cell.
+    offsets: Vec<u32>,
+    polygons: Vec<u32>,
+}
+
+impl SpatialIndex {
+    pub fn new(mesh_merger: &MeshMerger) -> Self {
+        let positions = || mesh_merger.mesh_vertices.iter().map(|v| v.p);
+        let min = positions().fold(Vec2::splat(f32::MAX), Vec2::min);
+        let max = positions().fold(Vec2::splat(f32::MIN), Vec2::max);
+        let size = (max - min).max(Vec2::splat(f32::EPSILON));
+        // Around one polygon per cell.
+        let cell_size = (size.x * size.y / mesh_merger.mesh_polygons.len().max(1) as f32)
+            .sqrt()
+            .max(size.max_element() / 1024.0);
+        let columns = (size.x / cell_size).floor() as u32 + 1;
+        let rows = (size.y / cell_size).floor() as u32 + 1;
+        let mut cells = vec![Vec::new(); (columns * rows) as usize];
+        for (polygon_index, polygon) in mesh_merger.mesh_polygons.iter().enumerate() {
+            let points = || {
+                polygon
+                    .vertices
+                    .iter()
+                    .map(|v| mesh_merger.mesh_vertices[*v as usize].p)
+            };
+            let cell = |p: Vec2| ((p - min) / cell_size).floor();
+            let from = cell(points().fold(Vec2::splat(f32::MAX), Vec2::min));
+            let to = cell(points().fold(Vec2::splat(f32::MIN), Vec2::max));
+            for y in from.y as u32..=(to.y as u32).min(rows - 1) {
+                for x in from.x as u32..=(to.x as u32).min(columns - 1) {
+                    cells[(y * columns + x) as usize].push(polygon_index as u32);
+                }
+            }
+        }
+        let mut offsets = Vec::with_capacity(cells.len() + 1);
+        let mut offset = 0;
+        for cell in cells.iter() {
+            offsets.push(offset);
+            offset += cell.len() as u32;
+        }
+        offsets.push(offset);
+        SpatialIndex {
+            cell_size,
+            origin: min,
+            columns,
+            rows,
+            offsets,
+            polygons: cells.into_iter().flatten().collect(),
+        }
+    }
+
+    /// Polygons overlapping the cell of `point`, none outside of the grid.
+    pub fn candidates(&self, point: Vec2) -> impl Iterator<Item = usize> + '_ {
+        let range = match grid_cell(self.cell_size, self.origin, self.columns, self.rows, point) {
+            Some(cell) => self.offsets[cell] as usize..self.offsets[cell + 1] as usize,
+            None => 0..0,
+        };
+        self.polygons[range].iter().map(|polygon| *polygon as usize)
+    }
+}
+
+/// Index of the grid cell containing `point`, if it is in the grid.
+fn grid_cell(cell_size: f32, origin: Vec2, columns: u32, rows: u32, point: Vec2) -> Option<usize> {
+    let cell = ((point - origin) / cell_size).floor();
+    if cell.x < 0.0 || cell.y < 0.0 || cell.x as u32 >= columns || cell.y as u32 >= rows {
+        return None;
+    }
+    Some(cell.y as usize * columns as usize + cell.x as usize)
+}
+
+impl MeshMerger {
+    /// Encodes the mesh in the binary format, polygons merged into others are dropped.
+    pub fn to_binary(&self, options: &BinaryOptions) -> Vec<u8> {
+        let has_merged =
+            (0..self.mesh_polygons.len()).any(|p| self.is_polygon_merged_into_other(p as u32));
+        if has_merged {
+            let mut mesh_merger = self.clone();
+            mesh_merger.remove_unused();
+            return mesh_merger.to_binary(options);
+        }
+        let mut body = Vec::new();
+        for vertex in self.mesh_vertices.iter() {
+            body.extend(vertex.p.x.to_le_bytes());
+            body.extend(vertex.p.y.to_le_bytes());
+        }
+        let mut offset = 0;
+        for vertex in self.mesh_vertices.iter() {
+            push_u32(&mut body, offset);
+            offset += vertex.polygons.len() as u32;
+        }
+        push_u32(&mut body, offset);
+        for vertex in self.mesh_vertices.iter() {
+            for polygon in vertex.polygons.iter() {
+                body.extend(polygon.to_le_bytes());
+            }
+        }
+        let mut index_count = 0;
+        for polygon in self.mesh_polygons.iter() {
+            push_u32(&mut body, index_count);
+            index_count += polygon.vertices.len() as u32;
+        }
+        push_u32(&mut body, index_count);
+        for polygon in self.mesh_polygons.iter() {
+            for vertex in polygon.vertices.iter() {
+                push_u32(&mut body, *vertex);
+            }
+        }
+        for polygon in self.mesh_polygons.iter() {
+            for neighbour in polygon.polygons.iter() {
+                body.extend(neighbour.to_le_bytes());
+            }
+        }
+        body.extend(self.mesh_polygons.iter().map(|p| p.area_type.0));
+        body.resize((body.len() + 3) / 4 * 4, 0);
+
+        let mut flags = 0;
+        if options.islands {
+            flags |= FLAG_ISLANDS;
+            let islands = Islands::from_mesh_merger(self);
+            push_u32(&mut body, islands.island_count() as u32);
+            for area in islands.island_areas.iter() {
+                body.extend(area.to_le_bytes());
+            }
+            for island in islands.polygon_islands.iter() {
+                push_u32(&mut body, *island);
+            }
+        }
+        if options.spatial_index {
+            flags |= FLAG_SPATIAL_INDEX;
+            let grid = SpatialIndex::new(self);
+            body.extend(grid.cell_size.to_le_bytes());
+            body.extend(grid.origin.x.to_le_bytes());
+            body.extend(grid.origin.y.to_le_bytes());
+            push_u32(&mut body, grid.columns);
+            push_u32(&mut body, grid.rows);
+            for value in grid.offsets.iter().chain(grid.polygons.iter()) {
+                push_u32(&mut body, *value);
+            }
+        }
+
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + body.len());
+        bytes.extend(MAGIC);
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(flags.to_le_bytes());
+        bytes.extend((self.mesh_vertices.len() as u32).to_le_bytes());
+        bytes.extend((self.mesh_polygons.len() as u32).to_le_bytes());
+        bytes.extend(index_count.to_le_bytes());
+        bytes.extend(checksum(&body).to_le_bytes());
+        bytes.extend(body);
+        bytes
+    }
+}
+
+/// Sections of a binary mesh, borrowed from its bytes.
+///
+/// Checks are done once in `parse`, accessors then read values in place.
+#[derive(Debug, Clone, Copy)]
+pub struct BinaryMesh<'a> {
+    vertex_count: usize,
+    polygon_count: usize,
+    vertices: &'a [u8],
+    vertex_polygon_offsets: &'a [u8],
+    vertex_polygons: &'a [u8],
+    polygon_offsets: &'a [u8],
+    polygon_vertices: &'a [u8],
+    polygon_neighbours: &'a [u8],
+    area_types: &'a [u8],
+    island_areas: Option<&'a [u8]>,
+    polygon_islands: Option<&'a [u8]>,
+    grid: Option<BinaryGrid<'a>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BinaryGrid<'a> {
+    cell_size: f32,
+    origin: Vec2,
+    columns: u32,
+    rows: u32,
+    offsets: &'a [u8],
+    polygons: &'a [u8],
+}
+
+struct Sections<'a> {
+    bytes: &'a [u8],
+}
+
+/// Size of `count` values of `size` bytes, too large to be in the data if it overflows.
+fn array_len(count: usize, size: usize, section: &'static str) -> Result<usize, BinaryFormatError> {
+    count
+        .checked_mul(size)
+        .ok_or(BinaryFormatError::Truncated(section))
+}
+
+impl<'a> Sections<'a> {
+    fn take(&mut self, len: usize, section: &'static str) -> Result<&'a [u8], BinaryFormatError> {
+        if self.bytes.len() < len {
+            return Err(BinaryFormatError::Truncated(section));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// Offsets array of `count + 1` values, checked to be increasing, with the values it indexes.
+    fn take_indexed(
+        &mut self,
+        count: usize,
+        section: &'static str,
+    ) -> Result<(&'a [u8], &'a [u8]), BinaryFormatError> {
+        let len = count
+            .checked_add(1)
+            .ok_or(BinaryFormatError::Truncated(section))
+            .and_then(|count| array_len(count, 4, section))?;
+        let offsets = self.take(len, section)?;
+        let mut previous = 0;
+        for i in 0..=count {
+            let offset = read_u32(offsets, i);
+            if offset < previous {
+                return Err(BinaryFormatError::InvalidIndex(section));
+            }
+            previous = offset;
+        }
+        let values = self.take(previous as usize * 4, section)?;
+        Ok((offsets, values))
+    }
+}
+
+impl<'a> BinaryMesh<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, BinaryFormatError> {
+        if bytes.len() < HEADER_SIZE || bytes[0..4] != MAGIC {
+            return Err(BinaryFormatError::NotBinaryMesh);
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != VERSION {
+            return Err(BinaryFormatError::UnsupportedVersion(version));
+        }
+        let flags = u16::from_le_bytes([bytes[6], bytes[7]]);
+        let header = &bytes[8..HEADER_SIZE];
+        let vertex_count = read_u32(header, 0) as usize;
+        let polygon_count = read_u32(header, 1) as usize;
+        let index_count = read_u32(header, 2) as usize;
+        let body = &bytes[HEADER_SIZE..];
+        if checksum(body) != read_u32(header, 3) {
+            return Err(BinaryFormatError::ChecksumMismatch);
+        }
+
+        let mut sections = Sections { bytes: body };
+        let vertices = sections.take(array_len(vertex_count, 8, "vertices")?, "vertices")?;
+        let (vertex_polygon_offsets, vertex_polygons) =
+            sections.take_indexed(vertex_count, "vertex polygons")?;
+        let (polygon_offsets, polygon_vertices) =
+            sections.take_indexed(polygon_count, "polygon vertices")?;
+        if polygon_vertices.len() / 4 != index_count {
+            return Err(BinaryFormatError::InvalidIndex("polygon vertices"));
+        }
+        let polygon_neighbours = sections.take(polygon_vertices.len(), "polygon neighbours")?;
+        let area_types = sections.take(polygon_count, "area types")?;
+        sections.take((4 - polygon_count % 4) % 4, "area types")?;
+
+        let (mut island_areas, mut polygon_islands) = (None, None);
+        if flags & FLAG_ISLANDS != 0 {
+            let island_count = read_u32(sections.take(4, "islands")?, 0) as usize;
+            island_areas = Some(sections.take(array_len(island_count, 4, "islands")?, "islands")?);
+            polygon_islands =
+                Some(sections.take(array_len(polygon_count, 4, "islands")?, "islands")?);
+        }
+        let mut grid = None;
+        if flags & FLAG_SPATIAL_INDEX != 0 {
+            let header = sections.take(20, "spatial index")?;
+            let (columns, rows) = (read_u32(header, 3), read_u32(header, 4));
+            let cell_count = (columns as usize)
+                .checked_mul(rows as usize)
+                .ok_or(BinaryFormatError::Truncated("spatial index"))?;
+            let (offsets, polygons) = sections.take_indexed(cell_count, "spatial index")?;
+            grid = Some(BinaryGrid {
+                cell_size: read_f32(header, 0),
+                origin: Vec2::new(read_f32(header, 1), read_f32(header, 2)),
+                columns,
+                rows,
+                offsets,
+                polygons,
+            });
+        }
+
+        let in_range = |values: &[u8], count: usize, allow_none: bool| {
+            (0..values.len() / 4).all(|i| {
+                let value = read_i32(values, i);
+                (allow_none && value == -1) || (0..count as i32).contains(&value)
+            })
+        };
+        if !in_range(vertex_polygons, polygon_count, true) {
+            return Err(BinaryFormatError::InvalidIndex("vertex polygons"));
+        }
+        if !in_range(polygon_vertices, vertex_count, false) {
+            return Err(BinaryFormatError::InvalidIndex("polygon vertices"));
+        }
+        if !in_range(polygon_neighbours, polygon_count, true) {
+            return Err(BinaryFormatError::InvalidIndex("polygon neighbours"));
+        }
+        if grid.map_or(false, |grid| !in_range(grid.polygons, polygon_count, false)) {
+            return Err(BinaryFormatError::InvalidIndex("spatial index"));
+        }
+        if let (Some(island_areas), Some(polygon_islands)) = (island_areas, polygon_islands) {
+            let island_count = island_areas.len() / 4;
+            if (0..polygon_count).any(|p| {
+                let island = read_u32(polygon_islands, p);
+                island != NO_ISLAND && island as usize >= island_count
+            }) {
+                return Err(BinaryFormatError::InvalidIndex("islands"));
+            }
+        }
+
+        Ok(BinaryMesh {
+            vertex_count,
+            polygon_count,
+            vertices,
+            vertex_polygon_offsets,
+            vertex_polygons,
+            polygon_offsets,
+            polygon_vertices,
+            polygon_neighbours,
+            area_types,
+            island_areas,
+            polygon_islands,
+            grid,
+        })
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    pub fn polygon_count(&self) -> usize {
+        self.polygon_count
+    }
+
+    pub fn vertex(&self, vertex: usize) -> Vec2 {
+        Vec2::new(
+            read_f32(self.vertices, vertex * 2),
+            read_f32(self.vertices, vertex * 2 + 1),
+        )
+    }
+
+    pub fn vertex_polygons(&self, vertex: usize) -> impl Iterator<Item = i32> + 'a {
+        let values = self.vertex_polygons;
+        let (start, end) = (
+            read_u32(self.vertex_polygon_offsets, vertex) as usize,
+            read_u32(self.vertex_polygon_offsets, vertex + 1) as usize,
+        );
+        (start..end).map(move |i| read_i32(values, i))
+    }
+
+    fn polygon_range(&self, polygon: usize) -> std::ops::Range<usize> {
+        read_u32(self.polygon_offsets, polygon) as usize
+            ..read_u32(self.polygon_offsets, polygon + 1) as usize
+    }
+
+    pub fn polygon_vertices(&self, polygon: usize) -> impl Iterator<Item = u32> + 'a {
+        let values = self.polygon_vertices;
+        self.polygon_range(polygon)
+            .map(move |i| read_u32(values, i))
+    }
+
+    /// Neighbour across each edge, -1 for none.
+    pub fn polygon_neighbours(&self, polygon: usize) -> impl Iterator<Item = i32> + 'a {
+        let values = self.polygon_neighbours;
+        self.polygon_range(polygon)
+            .map(move |i| read_i32(values, i))
+    }
+
+    pub fn area_type(&self, polygon: usize) -> AreaType {
+        AreaType(self.area_types[polygon])
+    }
+
+    /// Stored islands, if any.
+    pub fn islands(&self) -> Option<Islands> {
+        let (areas, polygon_islands) = (self.island_areas?, self.polygon_islands?);
+        Some(Islands {
+            polygon_islands: (0..self.polygon_count)
+                .map(|i| read_u32(polygon_islands, i))
+                .collect(),
+            island_areas: (0..areas.len() / 4).map(|i| read_f32(areas, i)).collect(),
+        })
+    }
+
+    /// Stored spatial index, if any.
+    pub fn spatial_index(&self) -> Option<SpatialIndex> {
+        let grid = self.grid?;
+        let read = |values: &[u8]| (0..values.len() / 4).map(|i| read_u32(values, i)).collect();
+        Some(SpatialIndex {
+            cell_size: grid.cell_size,
+            origin: grid.origin,
+            columns: grid.columns,
+            rows: grid.rows,
+            offsets: read(grid.offsets),
+            polygons: read(grid.polygons),
+        })
+    }
+
+    fn contains(&self, polygon: usize, point: Vec2) -> bool {
+        let vertices: Vec<Vec2> = self
+            .polygon_vertices(polygon)
+            .map(|v| self.vertex(v as usize))
+            .collect();
+        (0..vertices.len()).all(|i| {
+            let (a, b) = (vertices[i], vertices[(i + 1) % vertices.len()]);
+            (b - a).perp_dot(point - a) >= 0f32
+        })
+    }
+
+    /// Polygon containing `point`, through the spatial index if there is one.
+    pub fn polygon_at(&self, point: Vec2) -> Option<usize> {
+        match self.grid {
+            Some(grid) => {
+                let cell = grid_cell(grid.cell_size, grid.origin, grid.columns, grid.rows, point)?;
+                (read_u32(grid.offsets, cell)..read_u32(grid.offsets, cell + 1))
+                    .map(|i| read_u32(grid.polygons, i as usize) as usize)
+                    .find(|polygon| self.contains(*polygon, point))
+            }
+            None => (0..self.polygon_count).find(|polygon| self.contains(*polygon, point)),
+        }
+    }
+
+    /// Copies the mesh into a `MeshMerger`, to convert it to mesh 2 or to edit it.
+    pub fn to_mesh_merger(&self) -> MeshMerger {
+        let mesh_vertices: Vec<Vertex> = (0..self.vertex_count)
+            .map(|v| Vertex {
+                p: self.vertex(v),
+                polygons: self.vertex_polygons(v).collect(),
+            })
+            .collect();
+        let mesh_polygons = (0..self.polygon_count)
+            .map(|p| {
+                let vertices: Vec<u32> = self.polygon_vertices(p).collect();
+                let polygons: Vec<i32> = self.polygon_neighbours(p).collect();
+                Polygon {
+                    num_traversable: polygons.iter().filter(|n| **n != -1).count() as u32,
+                    area: MeshMerger::get_area(&mesh_vertices, &vertices),
+                    vertices,
+                    polygons,
+                    area_type: self.area_type(p),
+                }
+            })
+            .collect();
+        MeshMerger {
+            mesh_vertices,
+            mesh_polygons,
+            polygon_unions: UnionFind::new(self.polygon_count as i32),
+        }
+    }
+}
+
+impl MeshMerger {
+    /// Reads a mesh in the binary format, see `BinaryMesh` to read it in place.
+    pub fn from_binary(bytes: &[u8]) -> Result<MeshMerger, BinaryFormatError> {
+        BinaryMesh::parse(bytes).map(|mesh| mesh.to_mesh_merger())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+
+    use crate::{islands::Islands, test_fixtures::load, trianglemerger::MeshMerger};
+
+    use super::{
+        checksum, BinaryFormatError, BinaryMesh, BinaryOptions, SpatialIndex, HEADER_SIZE,
+    };
+
+    #[test]
+    fn round_trip_to_mesh3() {
+        let mut mesh_merger = load("../../assets/meshes/arena.mesh");
+        mesh_merger.my_merge();
+        mesh_merger.remove_unused();
+        let bytes = mesh_merger.to_binary(&BinaryOptions::default());
+        let read = MeshMerger::from_binary(&bytes).unwrap();
+        assert_eq!(read.to_mesh3_format(), mesh_merger.to_mesh3_format());
+        assert_eq!(
+            BinaryMesh::parse(&bytes).unwrap().islands(),
+            Some(Islands::from_mesh_merger(&mesh_merger))
+        );
+    }
+
+    #[test]
+    fn polygon_at_with_and_without_index() {
+        let mesh_merger = load("../../assets/meshes/two_quads.mesh");
+        for spatial_index in [true, false] {
+            let bytes = mesh_merger.to_binary(&BinaryOptions {
+                islands: false,
+                spatial_index,
+            });
+            let mesh = BinaryMesh::parse(&bytes).unwrap();
+            assert_eq!(mesh.polygon_at(Vec2::new(1.0, 0.2)), Some(0));
+            assert_eq!(mesh.polygon_at(Vec2::new(4.8, 0.5)), Some(3));
+            assert_eq!(mesh.polygon_at(Vec2::new(3.0, 0.5)), None);
+            assert!(mesh.islands().is_none());
+        }
+    }
+
+    #[test]
+    fn corrupted() {
+        let mesh_merger = load("../../assets/meshes/quad.mesh");
+        let mut bytes = mesh_merger.to_binary(&BinaryOptions::default());
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert_eq!(
+            BinaryMesh::parse(&bytes).unwrap_err(),
+            BinaryFormatError::ChecksumMismatch
+        );
+        assert_eq!(
+            BinaryMesh::parse(b"mesh\n2\n").unwrap_err(),
+            BinaryFormatError::NotBinaryMesh
+        );
+    }
+
+    #[test]
+    fn corrupted_header() {
+        let mesh_merger = load("../../assets/meshes/quad.mesh");
+        let bytes = mesh_merger.to_binary(&BinaryOptions::default());
+        let with_checksum = |mut bytes: Vec<u8>| {
+            let checksum = checksum(&bytes[HEADER_SIZE..]);
+            bytes[20..24].copy_from_slice(&checksum.to_le_bytes());
+            bytes
+        };
+
+        let mut huge_vertex_count = bytes.clone();
+        huge_vertex_count[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            BinaryMesh::parse(&with_checksum(huge_vertex_count)).unwrap_err(),
+            BinaryFormatError::Truncated("vertices")
+        );
+
+        // columns * rows + 1 offsets don't fit in memory.
+        let grid_header = {
+            let offsets = BinaryMesh::parse(&bytes).unwrap().grid.unwrap().offsets;
+            offsets.as_ptr() as usize - bytes.as_ptr() as usize - 20
+        };
+        let mut huge_grid = bytes.clone();
+        huge_grid[grid_header + 12..grid_header + 20].copy_from_slice(&[0xff; 8]);
+        assert_eq!(
+            BinaryMesh::parse(&with_checksum(huge_grid)).unwrap_err(),
+            BinaryFormatError::Truncated("spatial index")
+        );
+
+        let islands = {
+            let polygon_islands = BinaryMesh::parse(&bytes).unwrap().polygon_islands.unwrap();
+            polygon_islands.as_ptr() as usize - bytes.as_ptr() as usize
+        };
+        let mut unknown_island = bytes.clone();
+        unknown_island[islands..islands + 4].copy_from_slice(&7u32.to_le_bytes());
+        assert_eq!(
+            BinaryMesh::parse(&with_checksum(unknown_island)).unwrap_err(),
+            BinaryFormatError::InvalidIndex("islands")
+        );
+    }
+
+    #[test]
+    fn stored_spatial_index() {
+        let mesh_merger = load("../../assets/meshes/two_quads.mesh");
+        let bytes = mesh_merger.to_binary(&BinaryOptions::default());
+        let spatial_index = BinaryMesh::parse(&bytes).unwrap().spatial_index();
+        assert_eq!(spatial_index, Some(SpatialIndex::new(&mesh_merger)));
+        assert!(spatial_index
+            .unwrap()
+            .candidates(Vec2::new(4.8, 0.5))
+            .any(|polygon| polygon == 3));
+    }
+}
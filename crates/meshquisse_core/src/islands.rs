@@ -0,0 +1,228 @@
+use glam::Vec2;
+use polyanya::Mesh as PAMesh;
+
+use crate::{
+    tools,
+    trianglemerger::{MeshMerger, Polygon},
+};
+
+/// Island id given to polygons which are not part of the mesh anymore
+/// (merged into another polygon, or explicitly invalidated).
+pub const NO_ISLAND: u32 = u32::MAX;
+
+/// Connected components of a mesh, computed over polygon adjacency.
+///
+/// Two polygons are in the same island if there is a chain of polygons sharing
+/// edges between them, so a path can only exist between points of the same island.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Islands {
+    /// Island id of each polygon, indexed like the polygons of the source mesh.
+    /// [`NO_ISLAND`] for polygons which are not part of the mesh.
+    pub polygon_islands: Vec<u32>,
+    /// Area of each island, indexed by island id.
+    pub island_areas: Vec<f32>,
+}
+
+impl Islands {
+    /// Flood fills polygons from their neighbours.
+    ///
+    /// - `is_valid` tells if a polygon is part of the mesh.
+    /// - `neighbours` returns the neighbour polygons of a polygon, -1 meaning no neighbour.
+    /// - `area` returns the (real, not doubled) area of a polygon.
+    pub fn compute(
+        nb_polygons: usize,
+        is_valid: impl Fn(usize) -> bool,
+        neighbours: impl Fn(usize) -> Vec<i32>,
+        area: impl Fn(usize) -> f32,
+    ) -> Self {
+        let mut polygon_islands = vec![NO_ISLAND; nb_polygons];
+        let mut island_areas = Vec::new();
+        let mut to_visit = Vec::new();
+        for start in 0..nb_polygons {
+            if polygon_islands[start] != NO_ISLAND || !is_valid(start) {
+                continue;
+            }
+            let island = island_areas.len() as u32;
+            let mut island_area = 0f32;
+            polygon_islands[start] = island;
+            to_visit.push(start);
+            while let Some(polygon) = to_visit.pop() {
+                island_area += area(polygon);
+                for neighbour in neighbours(polygon) {
+                    if neighbour == -1 {
+                        continue;
+                    }
+                    let neighbour = neighbour as usize;
+                    if polygon_islands[neighbour] == NO_ISLAND && is_valid(neighbour) {
+                        polygon_islands[neighbour] = island;
+                        to_visit.push(neighbour);
+                    }
+                }
+            }
+            island_areas.push(island_area);
+        }
+        Islands {
+            polygon_islands,
+            island_areas,
+        }
+    }
+
+    /// Islands of a `MeshMerger`, polygons merged into others are not part of any island.
+    pub fn from_mesh_merger(mesh_merger: &MeshMerger) -> Self {
+        Self::compute(
+            mesh_merger.mesh_polygons.len(),
+            |p| !mesh_merger.is_polygon_merged_into_other(p as u32),
+            |p| {
+                mesh_merger.mesh_polygons[p]
+                    .polygons
+                    .iter()
+                    .map(|neighbour| mesh_merger.polygon_unions.find(*neighbour))
+                    .collect()
+            },
+            |p| mesh_merger.mesh_polygons[p].area / 2f32,
+        )
+    }
+
+    /// Islands of polygons with up to date neighbour data, ignoring `invalid_polygon_ids`.
+    pub fn from_polygons(polygons: &[Polygon], invalid_polygon_ids: &[u32]) -> Self {
+        let invalid = polygon_mask(polygons.len(), invalid_polygon_ids);
+        Self::compute(
+            polygons.len(),
+            |p| !invalid[p],
+            |p| polygons[p].polygons.clone(),
+            |p| polygons[p].area / 2f32,
+        )
+    }
+
+    /// Islands of a polyanya mesh, adjacency is deduced from shared edges.
+    pub fn from_pa_mesh(navmesh: &PAMesh) -> Self {
+        let edges = tools::edge_polygons(navmesh);
+        Self::compute(
+            navmesh.polygons.len(),
+            |_| true,
+            |p| {
+                let vertices = &navmesh.polygons[p].vertices;
+                (0..vertices.len())
+                    .flat_map(|i| {
+                        let a = vertices[i];
+                        let b = vertices[(i + 1) % vertices.len()];
+                        edges[&(a.min(b), a.max(b))].clone()
+                    })
+                    .filter(|neighbour| *neighbour != p as i32)
+                    .collect()
+            },
+            |p| {
+                let positions: Vec<Vec2> = navmesh.polygons[p]
+                    .vertices
+                    .iter()
+                    .map(|v| navmesh.vertices[*v as usize].coords)
+                    .collect();
+                polygon_area(&positions)
+            },
+        )
+    }
+
+    pub fn island_count(&self) -> usize {
+        self.island_areas.len()
+    }
+
+    /// Island of the given polygon, `None` if the polygon is not part of the mesh.
+    pub fn island_of(&self, polygon_index: usize) -> Option<u32> {
+        match self.polygon_islands.get(polygon_index) {
+            Some(island) if *island != NO_ISLAND => Some(*island),
+            _ => None,
+        }
+    }
+
+    /// Polygons belonging to islands with an area strictly below `min_area`.
+    pub fn polygons_in_islands_smaller_than(&self, min_area: f32) -> Vec<u32> {
+        self.polygon_islands
+            .iter()
+            .enumerate()
+            .filter(|(_, island)| {
+                **island != NO_ISLAND && self.island_areas[**island as usize] < min_area
+            })
+            .map(|(polygon_index, _)| polygon_index as u32)
+            .collect()
+    }
+}
+
+/// Area of a simple polygon, positive if counter clockwise.
+pub fn polygon_area(positions: &[Vec2]) -> f32 {
+    (0..positions.len())
+        .map(|i| {
+            let a = positions[i];
+            let b = positions[(i + 1) % positions.len()];
+            a.x * b.y - a.y * b.x
+        })
+        .sum::<f32>()
+        / 2f32
+}
+
+/// `true` for each polygon of `polygon_ids`, to test membership in constant time.
+///
+/// Ids out of `0..nb_polygons` are ignored.
+fn polygon_mask(nb_polygons: usize, polygon_ids: &[u32]) -> Vec<bool> {
+    let mut mask = vec![false; nb_polygons];
+    for polygon_id in polygon_ids {
+        if let Some(masked) = mask.get_mut(*polygon_id as usize) {
+            *masked = true;
+        }
+    }
+    mask
+}
+
+impl MeshMerger {
+    /// Removes islands whose area is strictly below `min_area`, returns the number of removed polygons.
+    ///
+    /// Meant to be called after `my_merge` and before `remove_unused`,
+    /// which takes care of actually dropping the polygons.
+    pub fn prune_small_islands(&mut self, min_area: f32) -> usize {
+        let islands = Islands::from_mesh_merger(self);
+        let pruned = islands.polygons_in_islands_smaller_than(min_area);
+        let pruned_mask = polygon_mask(self.polygon_unions.parent.len(), &pruned);
+        // Pruned polygons are roots of their union, so this also prunes the polygons merged into them.
+        for parent in self.polygon_unions.parent.iter_mut() {
+            if *parent != -1 && pruned_mask[*parent as usize] {
+                *parent = -1;
+            }
+        }
+        pruned.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fixtures::load;
+
+    use super::Islands;
+
+    #[test]
+    fn islands_quad() {
+        let mesh_merger = load("../../assets/meshes/quad.mesh");
+        let islands = Islands::from_mesh_merger(&mesh_merger);
+        assert_eq!(islands.island_count(), 1);
+        assert_eq!(islands.polygon_islands, vec![0, 0]);
+        assert_eq!(islands.island_areas, vec![2.25]);
+    }
+
+    #[test]
+    fn islands_two_quads() {
+        let mesh_merger = load("../../assets/meshes/two_quads.mesh");
+        let islands = Islands::from_mesh_merger(&mesh_merger);
+        assert_eq!(islands.island_count(), 2);
+        assert_eq!(islands.polygon_islands, vec![0, 0, 1, 1]);
+        assert_eq!(islands.island_areas, vec![2.25, 1.0]);
+    }
+
+    #[test]
+    fn prune_small_island() {
+        let mut mesh_merger = load("../../assets/meshes/two_quads.mesh");
+        mesh_merger.my_merge();
+        assert_eq!(mesh_merger.prune_small_islands(2.0), 1);
+        mesh_merger.remove_unused();
+        assert_eq!(mesh_merger.mesh_polygons.len(), 1);
+        let islands = Islands::from_mesh_merger(&mesh_merger);
+        assert_eq!(islands.island_areas, vec![2.25]);
+    }
+}
@@ -0,0 +1,17 @@
+//! Mesh processing of meshquisse without bevy: mesh v2 and binary I/O, merging into convex polygons,
+//! validation, islands, SVG rendering and triangle mesh tools.
+//!
+//! The `serde` feature derives serde traits on mesh types and adds RON, JSON and bincode helpers.
+//!
+//! `meshquisse` re-exports these modules.
+
+pub mod binary;
+pub mod islands;
+#[cfg(feature = "serde")]
+pub mod serialization;
+pub mod svg;
+#[cfg(test)]
+mod test_fixtures;
+pub mod tools;
+pub mod trianglemerger;
+pub mod validation;
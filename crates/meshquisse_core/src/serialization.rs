@@ -0,0 +1,88 @@
+//! RON, JSON and bincode encodings of any serializable mesh data.
+//!
+//! RON is meant to be diffed by humans, bincode to be small and fast to load.
+
+use std::fmt;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+#[derive(Debug)]
+pub enum SerializationError {
+    /// Keeps the position of the error in the text.
+    Ron(ron::error::SpannedError),
+    Json(serde_json::Error),
+    Bincode(bincode::Error),
+}
+
+impl fmt::Display for SerializationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializationError::Ron(error) => write!(f, "ron: {error}"),
+            SerializationError::Json(error) => write!(f, "json: {error}"),
+            SerializationError::Bincode(error) => write!(f, "bincode: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SerializationError {}
+
+pub fn to_ron<T: Serialize>(value: &T) -> Result<String, SerializationError> {
+    ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(SerializationError::Ron)
+}
+
+pub fn from_ron<T: DeserializeOwned>(text: &str) -> Result<T, SerializationError> {
+    ron::from_str(text).map_err(SerializationError::Ron)
+}
+
+pub fn to_json<T: Serialize>(value: &T) -> Result<String, SerializationError> {
+    serde_json::to_string_pretty(value).map_err(SerializationError::Json)
+}
+
+pub fn from_json<T: DeserializeOwned>(text: &str) -> Result<T, SerializationError> {
+    serde_json::from_str(text).map_err(SerializationError::Json)
+}
+
+pub fn to_bincode<T: Serialize>(value: &T) -> Result<Vec<u8>, SerializationError> {
+    bincode::serialize(value).map_err(SerializationError::Bincode)
+}
+
+pub fn from_bincode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, SerializationError> {
+    bincode::deserialize(bytes).map_err(SerializationError::Bincode)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        test_fixtures::load,
+        trianglemerger::{AreaType, Polygon},
+    };
+
+    use super::{
+        from_bincode, from_json, from_ron, to_bincode, to_json, to_ron, SerializationError,
+    };
+
+    #[test]
+    fn round_trips() {
+        let mut polygons = load("../../assets/meshes/two_quads.mesh").mesh_polygons;
+        polygons[1].area_type = AreaType::WATER;
+
+        let ron: Vec<Polygon> = from_ron(&to_ron(&polygons).unwrap()).unwrap();
+        assert_eq!(ron, polygons);
+        let json: Vec<Polygon> = from_json(&to_json(&polygons).unwrap()).unwrap();
+        assert_eq!(json, polygons);
+        let bincode: Vec<Polygon> = from_bincode(&to_bincode(&polygons).unwrap()).unwrap();
+        assert_eq!(bincode, polygons);
+    }
+
+    #[test]
+    fn ron_error_position() {
+        let error = from_ron::<Vec<Polygon>>("[\n  (\n    vertices: oops,\n").unwrap_err();
+        let error = match error {
+            SerializationError::Ron(error) => error,
+            error => panic!("expected a ron error, got {error}"),
+        };
+        assert_eq!(error.position.line, 3);
+        assert!(error.to_string().starts_with("3:"));
+    }
+}
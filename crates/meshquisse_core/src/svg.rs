@@ -0,0 +1,223 @@
+//! SVG rendering of meshes and paths, to review merges without a GPU.
+
+use std::{collections::HashSet, fmt::Write};
+
+use glam::Vec2;
+use polyanya::Mesh as PAMesh;
+
+use crate::trianglemerger::{AreaType, MeshMerger};
+
+/// Fill of each area type, cycled through for types above 3.
+const AREA_COLORS: [&str; 4] = ["#c8e6c9", "#dcedc8", "#bbdefb", "#ffcdd2"];
+const PATH_COLORS: [&str; 3] = ["#d32f2f", "#7b1fa2", "#f57c00"];
+
+/// Polygons and paths to draw, x to the right and y downwards.
+///
+/// ```ignore
+/// let svg = Svg::from(&mesh_merger).with_polygon_ids().with_path(points).to_svg_string();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Svg {
+    pub vertices: Vec<Vec2>,
+    /// Vertex indices of each polygon, with its id and area type.
+    pub polygons: Vec<(u32, Vec<u32>, AreaType)>,
+    pub paths: Vec<Vec<Vec2>>,
+    pub polygon_ids: bool,
+    /// Width of the image in pixels, the height follows the mesh proportions.
+    pub width: f32,
+}
+
+impl Svg {
+    pub fn new(vertices: Vec<Vec2>, polygons: Vec<(u32, Vec<u32>, AreaType)>) -> Self {
+        Svg {
+            vertices,
+            polygons,
+            paths: Vec::new(),
+            polygon_ids: false,
+            width: 1024.0,
+        }
+    }
+
+    pub fn with_polygon_ids(mut self) -> Self {
+        self.polygon_ids = true;
+        self
+    }
+
+    pub fn with_path(mut self, path: Vec<Vec2>) -> Self {
+        self.paths.push(path);
+        self
+    }
+
+    /// Edges used by two polygons, in both directions.
+    fn portals(&self) -> HashSet<[u32; 2]> {
+        let edges: HashSet<[u32; 2]> = self.polygon_edges().collect();
+        edges
+            .iter()
+            .filter(|[a, b]| edges.contains(&[*b, *a]))
+            .copied()
+            .collect()
+    }
+
+    fn polygon_edges(&self) -> impl Iterator<Item = [u32; 2]> + '_ {
+        self.polygons.iter().flat_map(|(_, vertices, _)| {
+            (0..vertices.len()).map(|i| [vertices[i], vertices[(i + 1) % vertices.len()]])
+        })
+    }
+
+    pub fn to_svg_string(&self) -> String {
+        let (min, max) = self
+            .vertices
+            .iter()
+            .chain(self.paths.iter().flatten())
+            .fold(
+                (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+                |(min, max), p| (min.min(*p), max.max(*p)),
+            );
+        let (min, max) = if min.x > max.x {
+            (Vec2::ZERO, Vec2::ONE)
+        } else {
+            (min, max)
+        };
+        let size = (max - min).max(Vec2::splat(f32::EPSILON));
+        let margin = size.max_element() * 0.02;
+        let scale = self.width / (size.x + 2.0 * margin);
+        let height = (size.y + 2.0 * margin) * scale;
+        let to_image = |p: Vec2| (p - min + margin) * scale;
+        let point = |p: Vec2| {
+            let p = to_image(p);
+            format!("{:.2},{:.2}", p.x, p.y)
+        };
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}">"#,
+            self.width, height
+        );
+        let _ = writeln!(svg, r#"<g stroke="none">"#);
+        for (_, vertices, AreaType(area_type)) in self.polygons.iter() {
+            let _ = writeln!(
+                svg,
+                r#"<polygon points="{}" fill="{}"/>"#,
+                vertices
+                    .iter()
+                    .map(|v| point(self.vertices[*v as usize]))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                AREA_COLORS[*area_type as usize % AREA_COLORS.len()]
+            );
+        }
+        let _ = writeln!(svg, "</g>");
+
+        let portals = self.portals();
+        let mut drawn = HashSet::new();
+        let _ = writeln!(svg, r#"<g stroke-linecap="round">"#);
+        for [a, b] in self.polygon_edges() {
+            if !drawn.insert([a.min(b), a.max(b)]) {
+                continue;
+            }
+            let style = if portals.contains(&[a, b]) {
+                r##"stroke="#9e9e9e" stroke-width="0.5""##
+            } else {
+                r##"stroke="#212121" stroke-width="1.5""##
+            };
+            let (a, b) = (
+                to_image(self.vertices[a as usize]),
+                to_image(self.vertices[b as usize]),
+            );
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" {style}/>"#,
+                a.x, a.y, b.x, b.y
+            );
+        }
+        let _ = writeln!(svg, "</g>");
+
+        for (i, path) in self.paths.iter().enumerate() {
+            let _ = writeln!(
+                svg,
+                r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+                path.iter().map(|p| point(*p)).collect::<Vec<_>>().join(" "),
+                PATH_COLORS[i % PATH_COLORS.len()]
+            );
+        }
+
+        if self.polygon_ids {
+            let _ = writeln!(
+                svg,
+                r#"<g font-family="sans-serif" font-size="10" text-anchor="middle">"#
+            );
+            for (id, vertices, _) in self.polygons.iter() {
+                let center = vertices
+                    .iter()
+                    .map(|v| self.vertices[*v as usize])
+                    .sum::<Vec2>()
+                    / vertices.len().max(1) as f32;
+                let center = to_image(center);
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="{:.2}" y="{:.2}">{id}</text>"#,
+                    center.x, center.y
+                );
+            }
+            let _ = writeln!(svg, "</g>");
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+impl From<&MeshMerger> for Svg {
+    /// Polygons merged into others are skipped, ids are indices in `mesh_polygons`.
+    fn from(mesh_merger: &MeshMerger) -> Self {
+        Svg::new(
+            mesh_merger.mesh_vertices.iter().map(|v| v.p).collect(),
+            mesh_merger
+                .mesh_polygons
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !mesh_merger.is_polygon_merged_into_other(*i as u32))
+                .map(|(i, p)| (i as u32, p.vertices.clone(), p.area_type))
+                .collect(),
+        )
+    }
+}
+
+impl From<&PAMesh> for Svg {
+    fn from(mesh: &PAMesh) -> Self {
+        Svg::new(
+            mesh.vertices.iter().map(|v| v.coords).collect(),
+            mesh.polygons
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (i as u32, p.vertices.clone(), AreaType::default()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+
+    use crate::test_fixtures::load;
+
+    use super::Svg;
+
+    #[test]
+    fn two_quads() {
+        let mesh_merger = load("../../assets/meshes/two_quads.mesh");
+        let polygon_count = mesh_merger.mesh_polygons.len();
+
+        let svg = Svg::from(&mesh_merger)
+            .with_polygon_ids()
+            .with_path(vec![Vec2::new(0.5, 0.5), Vec2::new(1.5, 0.5)])
+            .to_svg_string();
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<polygon ").count(), polygon_count);
+        assert_eq!(svg.matches("<text ").count(), polygon_count);
+        assert_eq!(svg.matches("<polyline ").count(), 1);
+        // Each quad is two triangles sharing their diagonal.
+        assert_eq!(svg.matches(r##"stroke="#9e9e9e""##).count(), 2);
+    }
+}
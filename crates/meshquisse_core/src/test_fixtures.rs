@@ -0,0 +1,14 @@
+//! Meshes shared by the tests of several modules.
+
+use std::io::Read;
+
+use crate::trianglemerger::MeshMerger;
+
+/// Reads a mesh 2 or mesh 3 file, `path` is relative to the crate root,
+/// so assets are in `../../assets`.
+pub(crate) fn load(path: &str) -> MeshMerger {
+    let mut file = std::fs::File::open(path).unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+    MeshMerger::from_bytes(&buffer)
+}
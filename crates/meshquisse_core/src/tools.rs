@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use glam::Vec2;
+use polyanya::{Mesh as PAMesh, Polygon, Vertex};
+
+use crate::trianglemerger::AreaType;
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TriangleMesh {
+    pub indices: Vec<u32>,
+    pub positions: Vec<Vec2>,
+    /// Area type of each triangle, an empty vec means all triangles have the default area type.
+    pub area_types: Vec<AreaType>,
+}
+
+impl TriangleMesh {
+    /// Area type of each triangle.
+    pub fn triangle_area_types(&self) -> Vec<AreaType> {
+        (0..self.indices.len() / 3)
+            .map(|i| self.area_types.get(i).copied().unwrap_or_default())
+            .collect()
+    }
+}
+
+/// Triangulates convex polygons, complexity is O(n)
+pub fn triangulate(navmesh: &PAMesh) -> Vec<u32> {
+    navmesh
+        .polygons
+        .iter()
+        .flat_map(|p| {
+            (2..p.vertices.len()).flat_map(|i| [p.vertices[0], p.vertices[i], p.vertices[i - 1]])
+        })
+        .map(|v| v as u32)
+        .collect()
+}
+
+/// Triangulates a simple polygon by ear clipping, complexity is O(n²).
+///
+/// `points` can be clockwise or counter clockwise, returned triangles are counter clockwise
+/// and index into `points`.
+pub fn triangulate_simple_polygon(points: &[Vec2]) -> Vec<[usize; 3]> {
+    let cross = |a: Vec2, b: Vec2, c: Vec2| (b - a).perp_dot(c - a);
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let double_area: f32 = (0..points.len())
+        .map(|i| points[i].perp_dot(points[(i + 1) % points.len()]))
+        .sum();
+    if double_area < 0.0 {
+        remaining.reverse();
+    }
+    let mut triangles = Vec::new();
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let ear = (0..len).find(|i| {
+            let (a, b, c) = (
+                remaining[(i + len - 1) % len],
+                remaining[*i],
+                remaining[(i + 1) % len],
+            );
+            cross(points[a], points[b], points[c]) > 0.0
+                && remaining.iter().all(|other| {
+                    [a, b, c].contains(other)
+                        || cross(points[a], points[b], points[*other]) < 0.0
+                        || cross(points[b], points[c], points[*other]) < 0.0
+                        || cross(points[c], points[a], points[*other]) < 0.0
+                })
+        });
+        // Degenerate polygons have no strict ear, clipping any vertex keeps the loop finite.
+        let ear = ear.unwrap_or(0);
+        triangles.push([
+            remaining[(ear + len - 1) % len],
+            remaining[ear],
+            remaining[(ear + 1) % len],
+        ]);
+        remaining.remove(ear);
+    }
+    if remaining.len() == 3 {
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
+    }
+    triangles
+}
+
+/// Convex hull of `points`, counter clockwise without collinear points, complexity is O(n log n).
+pub fn convex_hull(points: &[Vec2]) -> Vec<Vec2> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let mut hull: Vec<Vec2> = Vec::new();
+    // Lower hull then upper hull, the first point of each is the last of the other.
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let start = hull.len();
+        for p in pass {
+            while hull.len() >= start + 2
+                && (hull[hull.len() - 1] - hull[hull.len() - 2]).perp_dot(p - hull[hull.len() - 1])
+                    <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// Part of the convex `polygon` on the left of the line from `a` to `b`.
+fn clip_left(polygon: &[Vec2], a: Vec2, b: Vec2) -> Vec<Vec2> {
+    let side = |p: Vec2| (b - a).perp_dot(p - a);
+    let mut clipped = Vec::new();
+    for i in 0..polygon.len() {
+        let (p, q) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        let (side_p, side_q) = (side(p), side(q));
+        if side_p >= 0.0 {
+            clipped.push(p);
+        }
+        if (side_p > 0.0 && side_q < 0.0) || (side_p < 0.0 && side_q > 0.0) {
+            clipped.push(p + (q - p) * (side_p / (side_p - side_q)));
+        }
+    }
+    clipped
+}
+
+fn double_area(polygon: &[Vec2]) -> f32 {
+    (0..polygon.len())
+        .map(|i| polygon[i].perp_dot(polygon[(i + 1) % polygon.len()]))
+        .sum()
+}
+
+/// Convex pieces of the convex `polygon` outside of the convex `hole`, both counter clockwise.
+///
+/// Returns `polygon` unchanged if they don't overlap, and nothing if `hole` covers it.
+pub fn subtract_convex(polygon: &[Vec2], hole: &[Vec2]) -> Vec<Vec<Vec2>> {
+    const MIN_DOUBLE_AREA: f32 = 1e-6;
+    let edges = || (0..hole.len()).map(|i| (hole[i], hole[(i + 1) % hole.len()]));
+    let overlap = edges().fold(polygon.to_vec(), |part, (a, b)| clip_left(&part, a, b));
+    if double_area(&overlap) <= MIN_DOUBLE_AREA {
+        return vec![polygon.to_vec()];
+    }
+    // Each piece is outside of one edge of the hole and inside of the previous ones.
+    let mut pieces = Vec::new();
+    let mut remaining = polygon.to_vec();
+    for (a, b) in edges() {
+        let outside = clip_left(&remaining, b, a);
+        if double_area(&outside) > MIN_DOUBLE_AREA {
+            pieces.push(outside);
+        }
+        remaining = clip_left(&remaining, a, b);
+    }
+    pieces
+}
+
+/// Polygons sharing each edge of a navmesh, edges being keyed by `(min vertex, max vertex)`.
+///
+/// Edges with only one polygon are on the boundary, others are portals.
+pub fn edge_polygons(navmesh: &PAMesh) -> HashMap<(u32, u32), Vec<i32>> {
+    let mut edges: HashMap<(u32, u32), Vec<i32>> = HashMap::new();
+    for (polygon_index, polygon) in navmesh.polygons.iter().enumerate() {
+        for i in 0..polygon.vertices.len() {
+            let a = polygon.vertices[i];
+            let b = polygon.vertices[(i + 1) % polygon.vertices.len()];
+            edges
+                .entry((a.min(b), a.max(b)))
+                .or_default()
+                .push(polygon_index as i32);
+        }
+    }
+    edges
+}
+
+pub fn create_grid_trimesh(width: u32, height: u32, spacing: f32) -> TriangleMesh {
+    let to_index = |x: u32, y: u32| y * width + x;
+    let positions: Vec<Vec2> = (0..height)
+        .flat_map(|y| {
+            (0..width).map(move |x| {
+                let position: Vec2 = [x as f32, y as f32].into();
+                position * spacing
+            })
+        })
+        .collect();
+    let indices: Vec<u32> = (1..height)
+        .flat_map(|y| {
+            (1..width).flat_map(move |x| {
+                let mut triangles = [
+                    // bottom left triangle
+                    to_index(x - 1, y - 1),
+                    to_index(x, y - 1),
+                    to_index(x - 1, y),
+                    // top right triangle
+                    to_index(x, y),
+                    to_index(x - 1, y),
+                    to_index(x, y - 1),
+                ];
+                triangles
+            })
+        })
+        .collect();
+    TriangleMesh {
+        indices,
+        positions,
+        area_types: Vec::new(),
+    }
+}
+
+/// Returns an polyanya::Mesh, without any complex transformations,
+/// polygons are kept as triangles.
+/// (not implemented) For a more optimal solution, consider calling trimesh_to_convex_polygon_mesh()
+pub fn navmesh_from_trimesh(triangles_mesh: &TriangleMesh) -> PAMesh {
+    let mut vertices: Vec<Vertex> = triangles_mesh
+        .positions
+        .iter()
+        .map(|position| Vertex::new(*position, vec![]))
+        .collect();
+    let polygons: Vec<_> = (0..triangles_mesh.indices.len() / 3)
+        .map(|i| {
+            let i = i * 3;
+            let indexes = vec![
+                triangles_mesh.indices[i],
+                triangles_mesh.indices[i + 1],
+                triangles_mesh.indices[i + 2],
+            ];
+            Polygon::new(indexes, false)
+        })
+        .collect();
+    for (vertex_index, mut vertex) in vertices.iter_mut().enumerate() {
+        vertex.polygons = polygons
+            .iter()
+            .enumerate()
+            .filter_map(|(polygon_index, p)| {
+                p.vertices
+                    .contains(&(vertex_index as u32))
+                    .then_some(polygon_index as isize)
+            })
+            .collect::<Vec<isize>>();
+    }
+    PAMesh::new(vertices, polygons)
+}
+
+#[cfg(test)]
+mod test {
+    use glam::Vec2;
+    use polyanya::{Polygon, Vertex};
+
+    use super::{
+        convex_hull, create_grid_trimesh, double_area, navmesh_from_trimesh, subtract_convex,
+        TriangleMesh,
+    };
+
+    fn trimesh_3_3_10() -> TriangleMesh {
+        TriangleMesh {
+            indices: vec![
+                0, 3, 1, 4, 1, 3, 1, 4, 2, 5, 2, 4, 3, 6, 4, 7, 4, 6, 4, 7, 5, 8, 5, 7,
+            ],
+            positions: vec![
+                Vec2 { x: 0.0, y: 0.0 },
+                Vec2 { x: 10.0, y: 0.0 },
+                Vec2 { x: 20.0, y: 0.0 },
+                Vec2 { x: 0.0, y: 10.0 },
+                Vec2 { x: 10.0, y: 10. },
+                Vec2 { x: 20.0, y: 10. },
+                Vec2 { x: 0.0, y: 20.0 },
+                Vec2 { x: 10.0, y: 20. },
+                Vec2 { x: 20.0, y: 20. },
+            ],
+            area_types: vec![],
+        }
+    }
+
+    #[test]
+    fn test_trimesh_3_3_10() {
+        let trimesh = create_grid_trimesh(3, 3, 10f32);
+        assert_eq!(trimesh, trimesh_3_3_10())
+    }
+    #[test]
+    fn test_navmesh_from_trimesh_3_3_10() {
+        let trimesh = trimesh_3_3_10();
+        let navmesh = navmesh_from_trimesh(&trimesh);
+        assert_eq!(
+            navmesh.vertices,
+            vec![
+                Vertex::new(Vec2::new(0.0, 0.0,), vec![0,]),
+                Vertex::new(Vec2::new(10.0, 0.0,), vec![0, 1, 2,]),
+                Vertex::new(Vec2::new(20.0, 0.0,), vec![2, 3,],),
+                Vertex::new(Vec2::new(0.0, 10.0,), vec![0, 1, 4,],),
+                Vertex::new(Vec2::new(10.0, 10.0,), vec![1, 2, 3, 4, 5, 6,],),
+                Vertex::new(Vec2::new(20.0, 10.0,), vec![3, 6, 7,],),
+                Vertex::new(Vec2::new(0.0, 20.0,), vec![4, 5,],),
+                Vertex::new(Vec2::new(10.0, 20.0,), vec![5, 6, 7,],),
+                Vertex::new(Vec2::new(20.0, 20.0,), vec![7,],),
+            ]
+        );
+        assert_eq!(
+            navmesh
+                .polygons
+                .into_iter()
+                .map(|v| (v.vertices, v.is_one_way))
+                .collect::<Vec<_>>(),
+            [
+                (vec![0, 3, 1,], false,),
+                (vec![4, 1, 3,], false,),
+                (vec![1, 4, 2,], false,),
+                (vec![5, 2, 4,], false,),
+                (vec![3, 6, 4,], false,),
+                (vec![7, 4, 6,], false,),
+                (vec![4, 7, 5,], false,),
+                (vec![8, 5, 7,], false,),
+            ]
+        )
+    }
+
+    #[test]
+    fn subtract_hole_from_square() {
+        let square = |min: f32, max: f32| {
+            vec![
+                Vec2::new(min, min),
+                Vec2::new(max, min),
+                Vec2::new(max, max),
+                Vec2::new(min, max),
+            ]
+        };
+        // Unordered points with one inside the hull.
+        let hole = convex_hull(&[
+            Vec2::new(3.0, 3.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(1.0, 3.0),
+            Vec2::new(3.0, 1.0),
+        ]);
+        assert_eq!(hole, square(1.0, 3.0));
+        let pieces = subtract_convex(&square(0.0, 4.0), &hole);
+        assert_eq!(pieces.len(), 4);
+        let area: f32 = pieces.iter().map(|piece| double_area(piece) / 2.0).sum();
+        assert!((area - 12.0).abs() < 1e-4);
+        assert_eq!(
+            subtract_convex(&square(0.0, 1.0), &square(2.0, 3.0)),
+            vec![square(0.0, 1.0)]
+        );
+        assert!(subtract_convex(&square(1.5, 2.5), &hole).is_empty());
+    }
+}
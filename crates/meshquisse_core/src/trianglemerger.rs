@@ -3,11 +3,12 @@ use std::{
     io::{self, BufRead},
 };
 
-use bevy::prelude::Vec2;
+use glam::Vec2;
 
 /// Credits to https://bitbucket.org/dharabor/pathfinding/src/master/anyangle/polyanya/utils/meshmerger.cpp
 
 #[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnionFind {
     pub parent: Vec<i32>,
 }
@@ -19,7 +20,7 @@ impl UnionFind {
         }
     }
 
-    fn find(&self, x: i32) -> i32 {
+    pub fn find(&self, x: i32) -> i32 {
         if x == -1 {
             return -1;
         }
@@ -35,12 +36,29 @@ impl UnionFind {
 }
 
 #[derive(Default, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vertex {
     pub p: Vec2,
     pub polygons: Vec<i32>,
 }
 
+/// Kind of terrain a polygon is made of.
+///
+/// Polygons are only merged with polygons of the same type,
+/// and agents can weight or exclude types via `navmesh::AreaCosts`.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AreaType(pub u8);
+
+impl AreaType {
+    pub const ROAD: AreaType = AreaType(0);
+    pub const GRASS: AreaType = AreaType(1);
+    pub const WATER: AreaType = AreaType(2);
+    pub const FORBIDDEN: AreaType = AreaType(3);
+}
+
 #[derive(Default, Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Polygon {
     pub num_traversable: u32,
     pub area: f32,
@@ -48,6 +66,7 @@ pub struct Polygon {
     /// Stores the original polygons.
     /// To get the actual polygon, do polygon_unions.find on the polygon you get.
     pub polygons: Vec<i32>,
+    pub area_type: AreaType,
 }
 
 struct SearchNode {
@@ -83,7 +102,7 @@ fn getc<T: Copy>(vec: &Vec<T>, index: u32) -> T {
     vec[index as usize % vec.len()]
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct MergeInfo {
     pub polygon_to: i32,
     /// index of the start vertex within the polygon we want to merge into
@@ -94,15 +113,41 @@ pub struct MergeInfo {
     pub from_index: u32,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum ImpossibleMergeInfo {
     ToMergedIntoOther,
     NoNeighbour,
+    DifferentAreaType,
     FirstVertexClockwise,
     SecondVertexClockwise,
 }
 
-#[derive(Debug)]
+/// Error reading a mesh file, `line` starts at 1, 0 if the file ended early.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshFormatError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl MeshFormatError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        MeshFormatError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for MeshFormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for MeshFormatError {}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MeshMerger {
     /// We'll keep all vertices,
     /// but we may throw them out in the end if num_polygons is 0.
@@ -112,6 +157,136 @@ pub struct MeshMerger {
     pub polygon_unions: UnionFind,
 }
 
+/// A merge attempt, as done by `MeshMerger::my_merge`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MergeStep {
+    /// Polygon we tried to merge into.
+    pub polygon_to: i32,
+    /// Index of the start vertex of the candidate edge, within `polygon_to`.
+    pub to_index: u32,
+    /// Vertices of the candidate edge.
+    pub edge: [u32; 2],
+    /// Root polygon on the other side of the candidate edge, -1 if there is none.
+    pub polygon_from: i32,
+    /// If `Ok`, the merge has been applied.
+    pub result: Result<MergeInfo, ImpossibleMergeInfo>,
+}
+
+/// State of `MeshMerger::my_merge` between two merge attempts.
+///
+/// It doesn't borrow the `MeshMerger`, so stepping can be spread over time, e.g. across frames in an editor.
+/// Use `MeshMerger::merge_steps` to iterate at once.
+#[derive(Debug, Clone)]
+pub struct MergeStepper {
+    sorted_area_polygon_indexes: Vec<(usize, f32)>,
+    /// Position in `sorted_area_polygon_indexes` of the polygon we try to merge into.
+    polygon_position: usize,
+    /// Next edge to try within that polygon.
+    edge_index: usize,
+    /// Number of polygons visited, used to decide when to sort polygons.
+    progress: u32,
+    finished: bool,
+}
+
+impl MergeStepper {
+    pub fn new(mesh_merger: &MeshMerger) -> Self {
+        // TODO: merge biggest areas first.
+        // 1: Sort polygons by area
+        let mut stepper = MergeStepper {
+            sorted_area_polygon_indexes: mesh_merger
+                .mesh_polygons
+                .iter()
+                .enumerate()
+                .map(|(index, polygon)| (index, polygon.area))
+                .collect(),
+            polygon_position: 0,
+            edge_index: 0,
+            progress: 0,
+            finished: false,
+        };
+        stepper.start_pass();
+        stepper
+    }
+
+    /// Searches for a new merge from the first polygon.
+    fn start_pass(&mut self) {
+        if self.progress % 100 == 10 {
+            self.sorted_area_polygon_indexes
+                .sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        }
+        self.polygon_position = 0;
+        self.edge_index = 0;
+    }
+
+    /// True once a whole pass over polygons found nothing to merge.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Tries the next merge, applying it to `mesh_merger` if possible.
+    /// Returns `None` once finished.
+    pub fn step(&mut self, mesh_merger: &mut MeshMerger) -> Option<MergeStep> {
+        loop {
+            if self.finished {
+                return None;
+            }
+            let polygon_to_index = match self.sorted_area_polygon_indexes.get(self.polygon_position)
+            {
+                Some((polygon_to_index, _)) => *polygon_to_index,
+                None => {
+                    self.finished = true;
+                    return None;
+                }
+            };
+            let polygon = &mesh_merger.mesh_polygons[polygon_to_index];
+            if self.edge_index == 0 {
+                self.progress += 1;
+            }
+            if self.edge_index >= polygon.vertices.len() {
+                self.polygon_position += 1;
+                self.edge_index = 0;
+                continue;
+            }
+            let to_index = self.edge_index as u32;
+            let edge = [
+                polygon.vertices[self.edge_index],
+                getc(&polygon.vertices, to_index + 1),
+            ];
+            let polygon_from = mesh_merger
+                .polygon_unions
+                .find(polygon.polygons[self.edge_index]);
+            let result = mesh_merger.can_merge(polygon_to_index as i32, to_index);
+            self.edge_index += 1;
+            if let Ok(merge_info) = &result {
+                mesh_merger.merge(merge_info);
+                mesh_merger.is_correct();
+                self.start_pass();
+            }
+            return Some(MergeStep {
+                polygon_to: polygon_to_index as i32,
+                to_index,
+                edge,
+                polygon_from,
+                result,
+            });
+        }
+    }
+}
+
+/// Iterator over merge attempts, see `MeshMerger::merge_steps`.
+pub struct MergeSteps<'a> {
+    mesh_merger: &'a mut MeshMerger,
+    stepper: MergeStepper,
+}
+
+impl<'a> Iterator for MergeSteps<'a> {
+    type Item = MergeStep;
+
+    fn next(&mut self) -> Option<MergeStep> {
+        self.stepper.step(self.mesh_merger)
+    }
+}
+
 impl MeshMerger {
     /// Actually returns double the area of the polygon...
     /// Assume that mesh_vertices is populated and is valid.
@@ -126,9 +301,17 @@ impl MeshMerger {
         out
     }
     pub fn to_mesh2_format(&self) -> String {
+        self.to_mesh_format(2)
+    }
+    /// Mesh 2 format, with each polygon line followed by its area type.
+    /// See `doc/mesh_3_format.txt`.
+    pub fn to_mesh3_format(&self) -> String {
+        self.to_mesh_format(3)
+    }
+    fn to_mesh_format(&self, version: u32) -> String {
         use std::fmt::Write as _;
         let mut res = String::new();
-        res.push_str("mesh\n2\n");
+        let _ = writeln!(res, "mesh\n{version}");
         let nb_vertices = self.mesh_vertices.len();
         let nb_polygons = self.mesh_polygons.len();
         let _ = writeln!(res, "{nb_vertices} {nb_polygons}");
@@ -177,79 +360,143 @@ impl MeshMerger {
                     })
                     .collect::<String>()
             );
+            if version >= 3 {
+                res.pop();
+                let _ = writeln!(res, " {}", p.area_type.0);
+            }
         }
         res
     }
+    /// Reads a mesh in mesh 2 or mesh 3 format, see `try_from_bytes` to handle errors.
+    ///
+    /// Panics if the mesh is malformed or has clockwise polygons.
     pub fn from_bytes(bytes: &[u8]) -> MeshMerger {
-        let mut list_data = Vec::<u32>::default();
+        let mesh_merger = Self::try_from_bytes(bytes).unwrap_or_else(|error| panic!("{error}"));
+        for polygon in mesh_merger.mesh_polygons.iter() {
+            assert!(polygon.area > 0f32, "Polygon has an area inferior to 0");
+        }
+        mesh_merger
+    }
+    /// Reads a mesh in mesh 2 or mesh 3 format.
+    ///
+    /// Polygon orientation is not checked, see `validate`.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<MeshMerger, MeshFormatError> {
+        let mut reader = io::BufReader::new(bytes).lines().enumerate();
+        let mut next_line = || -> Result<(usize, String), MeshFormatError> {
+            match reader.next() {
+                Some((index, Ok(line))) => Ok((index + 1, line)),
+                Some((index, Err(error))) => {
+                    Err(MeshFormatError::new(index + 1, error.to_string()))
+                }
+                None => Err(MeshFormatError::new(0, "unexpected end of file")),
+            }
+        };
+        fn parse<T: std::str::FromStr>(
+            line: usize,
+            value: Option<&str>,
+            what: &str,
+        ) -> Result<T, MeshFormatError> {
+            value
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| MeshFormatError::new(line, format!("failed reading {what}")))
+        }
 
+        let (line, content) = next_line()?;
+        if content != "mesh" {
+            return Err(MeshFormatError::new(line, "First line should be 'mesh'"));
+        }
+        let (line, content) = next_line()?;
+        let version: u32 = match content.as_str() {
+            "2" => 2,
+            "3" => 3,
+            _ => {
+                return Err(MeshFormatError::new(
+                    line,
+                    "second line should be '2' or '3'",
+                ))
+            }
+        };
+        let (line, content) = next_line()?;
+        // (V, P) from https://bitbucket.org/dharabor/pathfinding/src/ce5b02e9d051d5f17addb359429104c0293decaf/anyangle/polyanya/utils/meshmerger.cpp#lines-205
+        let mut values = content.split(' ');
         // number of total vertices, called V on reference implementation
-        let mut v_nb_vertices = 0;
+        let v_nb_vertices: usize = parse(line, values.next(), "nb_vertices")?;
         // number of total vertices, called P on reference implementation
-        let mut p_nb_polygons = 0;
-        let mut reader = io::BufReader::new(bytes).lines();
-        let line: String = reader.next().unwrap().unwrap();
-        if line != "mesh" {
-            panic!("First line should be 'mesh'");
+        let p_nb_polygons: usize = parse(line, values.next(), "nb_polygons")?;
+        // Each vertex and polygon takes a line, don't trust the header to size the buffers.
+        let remaining_lines = io::BufReader::new(bytes)
+            .lines()
+            .count()
+            .saturating_sub(line);
+        if v_nb_vertices
+            .checked_add(p_nb_polygons)
+            .map_or(true, |needed| needed > remaining_lines)
+        {
+            return Err(MeshFormatError::new(
+                line,
+                format!(
+                    "header declares {v_nb_vertices} vertices and {p_nb_polygons} polygons but only {remaining_lines} lines follow"
+                ),
+            ));
         }
-        let line: String = reader.next().unwrap().unwrap();
-        if line != "2" {
-            panic!("second line should be '2'");
-        }
-        let line: String = reader.next().unwrap().unwrap();
-        // (V, P) from https://bitbucket.org/dharabor/pathfinding/src/ce5b02e9d051d5f17addb359429104c0293decaf/anyangle/polyanya/utils/meshmerger.cpp#lines-205
-        (v_nb_vertices, p_nb_polygons) = line
-            .split_once(' ')
-            .map(|(a, b)| {
-                (
-                    a.parse().expect("failed reading nb_vertices"),
-                    b.parse().expect("failed reading nb_polygons"),
-                )
-            })
-            .unwrap();
         let mut mesh_vertices = vec![Vertex::default(); v_nb_vertices];
         let mut mesh_polygons = vec![Polygon::default(); p_nb_polygons];
         let polygon_unions = UnionFind::new(p_nb_polygons as i32);
 
-        for mut vertex in mesh_vertices.iter_mut() {
-            let line: String = reader.next().unwrap().unwrap();
-            let mut values = line.split(' ');
+        for vertex in mesh_vertices.iter_mut() {
+            let (line, content) = next_line()?;
+            let mut values = content.split(' ');
             // Step: Read vertex coordinates
-            let x = values.next().unwrap().parse().unwrap();
-            let y = values.next().unwrap().parse().unwrap();
+            let x = parse(line, values.next(), "vertex x")?;
+            let y = parse(line, values.next(), "vertex y")?;
             // Step: Read vertex's neighbour polygons
-            let neigbours: i32 = values.next().unwrap().parse().unwrap();
+            let neigbours: i32 = parse(line, values.next(), "vertex neighbours count")?;
             if neigbours < 2 {
-                panic!("vertex with less than 2 neigbours");
+                return Err(MeshFormatError::new(
+                    line,
+                    "vertex with less than 2 neigbours",
+                ));
             }
             vertex.p = Vec2::new(x, y);
-            let neighbour_values: Vec<i32> = values.map(|v| v.parse().unwrap()).collect();
+            let neighbour_values = values
+                .map(|v| parse(line, Some(v), "vertex neighbour"))
+                .collect::<Result<Vec<i32>, _>>()?;
             if neighbour_values.len() != neigbours as usize {
-                panic!("read more neighbours than defined.");
+                return Err(MeshFormatError::new(
+                    line,
+                    "read more neighbours than defined.",
+                ));
             }
             // Guaranteed to have 2 or more.
             for polygon_index in neighbour_values.into_iter() {
-                if polygon_index >= p_nb_polygons as i32 {
-                    panic!(
+                if polygon_index < -1 || polygon_index >= p_nb_polygons as i32 {
+                    let message = format!(
                         "Got a polygon index of {polygon_index} when nb_polygon is {p_nb_polygons}"
                     );
+                    return Err(MeshFormatError::new(line, message));
                 }
                 vertex.polygons.push(polygon_index);
             }
         }
-        for mut polygon in mesh_polygons.iter_mut() {
-            let line: String = reader.next().unwrap().unwrap();
-            let mut values = line.split(' ');
+        for polygon in mesh_polygons.iter_mut() {
+            let (line, content) = next_line()?;
+            let mut values = content.split(' ');
 
             // Step: Read polygon's vertices (corresponding to neighbouring polygons too)
-            let n = values.next().unwrap().parse().unwrap();
+            let n: usize = parse(line, values.next(), "polygon vertices count")?;
             if n < 3 {
-                panic!("Invalid number of vertices in polygon (Got {n}).");
+                return Err(MeshFormatError::new(
+                    line,
+                    format!("Invalid number of vertices in polygon (Got {n})."),
+                ));
             }
             for _ in 0..n {
-                let vertex_index: u32 = values.next().unwrap().parse().unwrap();
+                let vertex_index: u32 = parse(line, values.next(), "polygon vertex")?;
                 if vertex_index >= v_nb_vertices as u32 {
-                    panic!("Invalid vertex index when getting polygon");
+                    return Err(MeshFormatError::new(
+                        line,
+                        "Invalid vertex index when getting polygon",
+                    ));
                 }
                 polygon.vertices.push(vertex_index);
             }
@@ -257,28 +504,33 @@ impl MeshMerger {
             // Step: Read polygon's neighbour polygons
             polygon.num_traversable = 0;
             for _ in 0..n {
-                let polygon_index: i32 = values.next().unwrap().parse().unwrap();
-                if polygon_index >= p_nb_polygons as i32 {
-                    panic!("Invalid polygon index when getting polygon");
+                let polygon_index: i32 = parse(line, values.next(), "polygon neighbour")?;
+                if polygon_index < -1 || polygon_index >= p_nb_polygons as i32 {
+                    return Err(MeshFormatError::new(
+                        line,
+                        "Invalid polygon index when getting polygon",
+                    ));
                 }
                 if polygon_index != -1 {
                     polygon.num_traversable += 1;
                 }
                 polygon.polygons.push(polygon_index);
             }
+            if version >= 3 {
+                polygon.area_type = AreaType(parse(line, values.next(), "polygon area type")?);
+            }
             // shift data back 1 place, to respect mesh format
             polygon.polygons.push(polygon.polygons[0]);
             polygon.polygons.remove(0);
             //
             polygon.area = MeshMerger::get_area(&mesh_vertices, &polygon.vertices);
-            assert!(polygon.area > 0f32, "Polygon has an area inferior to 0");
         }
         // TODO: check if the file is indeed finished.
-        MeshMerger {
+        Ok(MeshMerger {
             mesh_vertices,
             mesh_polygons,
             polygon_unions,
-        }
+        })
     }
     /// Checks if points are ordered clockwise
     fn cw(a: &Vec2, b: &Vec2, c: &Vec2) -> bool {
@@ -329,6 +581,9 @@ impl MeshMerger {
         }
         // The polygon we want to merge from
         let polygon_from = &self.mesh_polygons[polygon_from_index as usize];
+        if polygon_from.area_type != polygon_to.area_type {
+            return Err(ImpossibleMergeInfo::DifferentAreaType);
+        }
         debug_assert!(
             polygon_from.vertices.len() != 0,
             "Wrong data: a polygon cannot have 0 vertices."
@@ -515,45 +770,14 @@ impl MeshMerger {
     }
 
     pub fn my_merge(&mut self) {
-        // TODO: merge biggest areas first.
-        // 1: Sort polygons by area
+        for _ in self.merge_steps() {}
+    }
 
-        let mut sorted_area_polygon_indexes: Vec<_> = self
-            .mesh_polygons
-            .iter()
-            .enumerate()
-            .map(|(index, polygon)| (index, polygon.area))
-            .collect();
-        let mut check_new_merge = true;
-        let mut merge_count = 0;
-        let mut progress = 0;
-        while check_new_merge {
-            check_new_merge = false;
-            if progress % 100 == 10 {
-                //println!("sorting {} polygons", sorted_area_polygon_indexes.len());
-                sorted_area_polygon_indexes
-                    .sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-            }
-            'search_merge: for (polygon_to_index, _) in sorted_area_polygon_indexes.iter() {
-                progress += 1;
-                if progress % 1000 == 100 {
-                    //println!("polygon {polygon_to_index}/{}",sorted_area_polygon_indexes.len());
-                    //println!("{merge_count} merged so far.");
-                }
-                let polygon = &self.mesh_polygons[*polygon_to_index];
-                for merge_index in 0..polygon.vertices.len() {
-                    if let Ok(merge_info) =
-                        self.can_merge(*polygon_to_index as i32, merge_index as u32)
-                    {
-                        self.merge(&merge_info);
-                        //println!("merged {merge_info:?}, {merge_count} merged so far.");
-                        check_new_merge = true;
-                        self.is_correct();
-                        merge_count += 1;
-                        break 'search_merge;
-                    }
-                }
-            }
+    /// Iterator over each merge attempt of `my_merge`, successful merges being applied as they are yielded.
+    pub fn merge_steps(&mut self) -> MergeSteps<'_> {
+        MergeSteps {
+            stepper: MergeStepper::new(self),
+            mesh_merger: self,
         }
     }
     /// Call that after a merge to remove unused polygons
@@ -615,11 +839,9 @@ impl MeshMerger {
 
 #[cfg(test)]
 mod tests {
-    use std::io::Read;
-
-    use crate::trianglemerger::MergeInfo;
+    use crate::{test_fixtures::load, trianglemerger::MergeInfo};
 
-    use super::{ImpossibleMergeInfo, MeshMerger};
+    use super::{AreaType, ImpossibleMergeInfo, MeshMerger};
 
     // TODO: test read and assert result...
 
@@ -636,10 +858,7 @@ mod tests {
     // 3         2
     #[test]
     fn can_merge_4() {
-        let mut file = std::fs::File::open("assets/meshes/quad.mesh").unwrap();
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
-        let mut mesh_merger = MeshMerger::from_bytes(&buffer);
+        let mut mesh_merger = load("../../assets/meshes/quad.mesh");
         assert_eq!(
             mesh_merger.can_merge(0, 0),
             Err(ImpossibleMergeInfo::NoNeighbour)
@@ -673,10 +892,7 @@ mod tests {
     }
     #[test]
     fn manual_merge_4() {
-        let mut file = std::fs::File::open("assets/meshes/quad.mesh").unwrap();
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
-        let mut mesh_merger = MeshMerger::from_bytes(&buffer);
+        let mut mesh_merger = load("../../assets/meshes/quad.mesh");
         mesh_merger.merge(&MergeInfo {
             polygon_to: 0,
             to_index: 1,
@@ -693,10 +909,7 @@ mod tests {
     }
     #[test]
     fn merge_quad() {
-        let mut file = std::fs::File::open("assets/meshes/quad.mesh").unwrap();
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
-        let mut mesh_merger = MeshMerger::from_bytes(&buffer);
+        let mut mesh_merger = load("../../assets/meshes/quad.mesh");
         mesh_merger.my_merge();
         assert_eq!(
             mesh_merger.mesh_polygons[0],
@@ -705,6 +918,7 @@ mod tests {
                 area: 4.5,
                 vertices: vec![3, 0, 1, 2,],
                 polygons: vec![-1, -1, -1, -1,],
+                area_type: AreaType::ROAD,
             }
         )
     }
@@ -731,10 +945,7 @@ mod tests {
     /// 4
     #[test]
     fn merge_bigger_quad() {
-        let mut file = std::fs::File::open("assets/meshes/quad_plus_one.mesh").unwrap();
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
-        let mut mesh_merger = MeshMerger::from_bytes(&buffer);
+        let mut mesh_merger = load("../../assets/meshes/quad_plus_one.mesh");
         mesh_merger.my_merge();
         assert_eq!(
             mesh_merger.mesh_polygons[2],
@@ -743,15 +954,13 @@ mod tests {
                 area: 5.25,
                 vertices: vec![1, 2, 4, 3, 0,],
                 polygons: vec![-1, -1, -1, -1, -1],
+                area_type: AreaType::ROAD,
             }
         )
     }
     #[test]
     fn manual_merge_5() {
-        let mut file = std::fs::File::open("assets/meshes/quad_plus_one.mesh").unwrap();
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
-        let mut mesh_merger = MeshMerger::from_bytes(&buffer);
+        let mut mesh_merger = load("../../assets/meshes/quad_plus_one.mesh");
         mesh_merger.merge(&MergeInfo {
             polygon_to: 0,
             to_index: 1,
@@ -782,12 +991,55 @@ mod tests {
         });
     }
     #[test]
-    fn merge_arena() {
-        let mut file = std::fs::File::open("assets/meshes/arena.mesh").unwrap();
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
+    fn no_merge_different_area_type() {
+        let mut mesh_merger = load("../../assets/meshes/quad.mesh");
+        mesh_merger.mesh_polygons[1].area_type = AreaType::WATER;
+        assert_eq!(
+            mesh_merger.can_merge(0, 1),
+            Err(ImpossibleMergeInfo::DifferentAreaType)
+        );
+        mesh_merger.my_merge();
+        assert!(!mesh_merger.is_polygon_merged_into_other(1));
+    }
+    #[test]
+    fn mesh3_round_trip() {
+        let mut mesh_merger = load("../../assets/meshes/quad.mesh");
+        mesh_merger.mesh_polygons[1].area_type = AreaType::GRASS;
+        let reloaded = MeshMerger::from_bytes(mesh_merger.to_mesh3_format().as_bytes());
+        assert_eq!(reloaded.mesh_polygons, mesh_merger.mesh_polygons);
+        assert_eq!(
+            MeshMerger::from_bytes(mesh_merger.to_mesh2_format().as_bytes()).mesh_polygons[1]
+                .area_type,
+            AreaType::ROAD
+        );
+    }
+    #[test]
+    fn merge_steps_quad() {
+        let mut mesh_merger = load("../../assets/meshes/quad.mesh");
+        let steps: Vec<_> = mesh_merger.merge_steps().collect();
+        assert_eq!(
+            steps[0].result,
+            Err(ImpossibleMergeInfo::NoNeighbour),
+            "first edge of polygon 0 is on the border"
+        );
+        assert_eq!(
+            steps[1].result,
+            Ok(MergeInfo {
+                polygon_to: 0,
+                to_index: 1,
+                polygon_from: 1,
+                from_index: 0,
+            })
+        );
+        assert!(steps[2..].iter().all(|step| step.result.is_err()));
 
-        let mut mesh_merger = MeshMerger::from_bytes(&buffer);
+        let mut merged = MeshMerger::from_bytes(&buffer);
+        merged.my_merge();
+        assert_eq!(mesh_merger.mesh_polygons, merged.mesh_polygons);
+    }
+    #[test]
+    fn merge_arena() {
+        let mut mesh_merger = load("../../assets/meshes/arena.mesh");
         assert!(
             mesh_merger.is_correct(),
             "source file is incorrect or loading code is not."
@@ -796,11 +1048,7 @@ mod tests {
     }
     #[test]
     fn merge_aurora() {
-        let mut file = std::fs::File::open("assets/meshes/aurora.mesh").unwrap();
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
-
-        let mut mesh_merger = MeshMerger::from_bytes(&buffer);
+        let mut mesh_merger = load("../../assets/meshes/aurora.mesh");
         assert!(
             mesh_merger.is_correct(),
             "source file is incorrect or loading code is not."
@@ -811,15 +1059,44 @@ mod tests {
     /// This test results in wrong behaviour, because the merge() function does 26 merges, but data is supposed to be merged already.
     #[test]
     fn merge_arena_merged() {
-        let mut file = std::fs::File::open("assets/meshes/arena_merged.mesh").unwrap();
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
-
-        let mut mesh_merger = MeshMerger::from_bytes(&buffer);
+        let mut mesh_merger = load("../../assets/meshes/arena_merged.mesh");
         assert!(
             mesh_merger.is_correct(),
             "source file is incorrect or loading code is not."
         );
         mesh_merger.my_merge();
     }
+
+    const QUAD: &str = "mesh\n2\n4 2\n0.0 0.0 2 0 -1\n1.5 0.0 3 0 1 -1\n1.5 1.5 2 1 -1\n0.0 1.5 3 -1 1 0\n3 0 1 3 -1 -1 1\n3 1 2 3 0 -1 -1\n";
+
+    #[test]
+    fn read_quad() {
+        let mesh_merger = MeshMerger::try_from_bytes(QUAD.as_bytes()).unwrap();
+        assert_eq!(mesh_merger.mesh_vertices.len(), 4);
+        assert_eq!(mesh_merger.mesh_polygons.len(), 2);
+    }
+
+    #[test]
+    fn reject_negative_vertex_neighbour() {
+        let mesh = QUAD.replace("0.0 0.0 2 0 -1", "0.0 0.0 2 0 -2");
+        let error = MeshMerger::try_from_bytes(mesh.as_bytes()).unwrap_err();
+        assert_eq!(error.line, 4);
+    }
+
+    #[test]
+    fn reject_negative_polygon_neighbour() {
+        let mesh = QUAD.replace("3 0 1 3 -1 -1 1", "3 0 1 3 -7 -1 1");
+        let error = MeshMerger::try_from_bytes(mesh.as_bytes()).unwrap_err();
+        assert_eq!(error.line, 8);
+    }
+
+    #[test]
+    fn reject_counts_larger_than_input() {
+        let mesh = QUAD.replace("4 2", "4000000000000 2");
+        let error = MeshMerger::try_from_bytes(mesh.as_bytes()).unwrap_err();
+        assert_eq!(error.line, 3);
+        let mesh = QUAD.replace("4 2", &format!("{} 1", usize::MAX));
+        let error = MeshMerger::try_from_bytes(mesh.as_bytes()).unwrap_err();
+        assert_eq!(error.line, 3);
+    }
 }
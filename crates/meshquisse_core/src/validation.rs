@@ -0,0 +1,186 @@
+use std::{collections::HashMap, fmt};
+
+use crate::trianglemerger::MeshMerger;
+
+/// Something polyanya doesn't expect in a mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshIssue {
+    /// The polygon has less than 3 vertices.
+    TooFewVertices(u32),
+    /// The polygon uses a vertex which doesn't exist.
+    InvalidVertex {
+        polygon: u32,
+        vertex: u32,
+    },
+    /// The polygon is clockwise, or flat.
+    NotCounterClockwise(u32),
+    NotConvex(u32),
+    /// The polygon has a neighbour which doesn't exist, or which doesn't share that edge.
+    InvalidNeighbour {
+        polygon: u32,
+        neighbour: i32,
+    },
+    /// The vertex lists a polygon which doesn't exist or doesn't use it.
+    InvalidVertexPolygon {
+        vertex: u32,
+        polygon: i32,
+    },
+    /// No polygon uses that vertex.
+    UnusedVertex(u32),
+}
+
+impl fmt::Display for MeshIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshIssue::TooFewVertices(polygon) => {
+                write!(f, "polygon {polygon} has less than 3 vertices")
+            }
+            MeshIssue::InvalidVertex { polygon, vertex } => {
+                write!(f, "polygon {polygon} uses missing vertex {vertex}")
+            }
+            MeshIssue::NotCounterClockwise(polygon) => {
+                write!(f, "polygon {polygon} is not counter clockwise")
+            }
+            MeshIssue::NotConvex(polygon) => write!(f, "polygon {polygon} is not convex"),
+            MeshIssue::InvalidNeighbour { polygon, neighbour } => {
+                write!(f, "polygon {polygon} has invalid neighbour {neighbour}")
+            }
+            MeshIssue::InvalidVertexPolygon { vertex, polygon } => {
+                write!(
+                    f,
+                    "vertex {vertex} lists polygon {polygon} which doesn't use it"
+                )
+            }
+            MeshIssue::UnusedVertex(vertex) => write!(f, "vertex {vertex} is unused"),
+        }
+    }
+}
+
+impl MeshMerger {
+    /// Issues of polygons and vertices, empty if the mesh is fine for polyanya.
+    ///
+    /// Polygons merged into others are skipped.
+    pub fn validate(&self) -> Vec<MeshIssue> {
+        let mut issues = Vec::new();
+        let nb_vertices = self.mesh_vertices.len() as u32;
+        let valid_polygons = || {
+            self.mesh_polygons
+                .iter()
+                .enumerate()
+                .filter(|(polygon_index, _)| {
+                    !self.is_polygon_merged_into_other(*polygon_index as u32)
+                })
+        };
+        // Polygon owning each directed edge.
+        let mut edges = HashMap::new();
+        for (polygon_index, polygon) in valid_polygons() {
+            let len = polygon.vertices.len();
+            for i in 0..len {
+                edges.insert(
+                    [polygon.vertices[i], polygon.vertices[(i + 1) % len]],
+                    polygon_index as i32,
+                );
+            }
+        }
+        let mut used_vertices = vec![false; nb_vertices as usize];
+        for (polygon_index, polygon) in valid_polygons() {
+            let polygon_index = polygon_index as u32;
+            let len = polygon.vertices.len();
+            if len < 3 {
+                issues.push(MeshIssue::TooFewVertices(polygon_index));
+                continue;
+            }
+            if let Some(vertex) = polygon.vertices.iter().find(|v| **v >= nb_vertices) {
+                issues.push(MeshIssue::InvalidVertex {
+                    polygon: polygon_index,
+                    vertex: *vertex,
+                });
+                continue;
+            }
+            for vertex in polygon.vertices.iter() {
+                used_vertices[*vertex as usize] = true;
+            }
+            let positions: Vec<_> = polygon
+                .vertices
+                .iter()
+                .map(|v| self.mesh_vertices[*v as usize].p)
+                .collect();
+            if MeshMerger::get_area(&self.mesh_vertices, &polygon.vertices) <= 0f32 {
+                issues.push(MeshIssue::NotCounterClockwise(polygon_index));
+            } else if !(0..len).all(|i| {
+                let (a, b, c) = (
+                    positions[i],
+                    positions[(i + 1) % len],
+                    positions[(i + 2) % len],
+                );
+                // Same tolerance as the merge.
+                (b - a).perp_dot(c - b) >= -1e-8
+            }) {
+                issues.push(MeshIssue::NotConvex(polygon_index));
+            }
+            // polygons[i] is the neighbour across the edge (vertices[i], vertices[i + 1]).
+            for (i, neighbour) in polygon.polygons.iter().enumerate() {
+                let neighbour = self.polygon_unions.find(*neighbour);
+                let expected = edges
+                    .get(&[polygon.vertices[(i + 1) % len], polygon.vertices[i]])
+                    .copied()
+                    .unwrap_or(-1);
+                if neighbour != expected {
+                    issues.push(MeshIssue::InvalidNeighbour {
+                        polygon: polygon_index,
+                        neighbour,
+                    });
+                }
+            }
+        }
+        for (vertex_index, vertex) in self.mesh_vertices.iter().enumerate() {
+            let vertex_index = vertex_index as u32;
+            if !used_vertices[vertex_index as usize] {
+                issues.push(MeshIssue::UnusedVertex(vertex_index));
+                continue;
+            }
+            for polygon in vertex.polygons.iter() {
+                let polygon = self.polygon_unions.find(*polygon);
+                if polygon == -1 {
+                    continue;
+                }
+                let uses_vertex = self
+                    .mesh_polygons
+                    .get(polygon as usize)
+                    .map_or(false, |p| p.vertices.contains(&vertex_index));
+                if !uses_vertex {
+                    issues.push(MeshIssue::InvalidVertexPolygon {
+                        vertex: vertex_index,
+                        polygon,
+                    });
+                }
+            }
+        }
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_fixtures::load;
+
+    use super::MeshIssue;
+
+    #[test]
+    fn valid_before_and_after_merge() {
+        let mut mesh_merger = load("../../assets/meshes/two_quads.mesh");
+        assert_eq!(mesh_merger.validate(), vec![]);
+        mesh_merger.my_merge();
+        mesh_merger.remove_unused();
+        assert_eq!(mesh_merger.validate(), vec![]);
+    }
+
+    #[test]
+    fn clockwise_polygon() {
+        let mut mesh_merger = load("../../assets/meshes/quad.mesh");
+        mesh_merger.mesh_polygons[0].vertices.reverse();
+        assert!(mesh_merger
+            .validate()
+            .contains(&MeshIssue::NotCounterClockwise(0)));
+    }
+}
@@ -0,0 +1,138 @@
+use std::io::Read;
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+use clap::Parser;
+use meshquisse::{
+    interact_mesh::{IntoBevyMesh, IntoPAMesh},
+    mesh_data::merge_triangles::ConvexPolygonsMeshData,
+    mesh_diff::MeshDiff,
+    plane::PlaneMapping,
+    trianglemerger::MeshMerger,
+    MeshquissePlugins,
+};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// path to the first mesh, usually before merging
+    #[arg(short)]
+    a: String,
+    /// path to the second mesh, usually after merging
+    #[arg(short)]
+    b: String,
+    /// size of the cells used to compare coverage
+    #[arg(long, default_value_t = 0.5)]
+    cell_size: f32,
+    /// only print the report, without opening a window
+    #[arg(long)]
+    no_render: bool,
+}
+
+struct DiffToShow {
+    a: ConvexPolygonsMeshData,
+    b: ConvexPolygonsMeshData,
+    diff: MeshDiff,
+}
+
+fn main() {
+    let args = Args::parse();
+    let a = load(&args.a);
+    let b = load(&args.b);
+    let diff = MeshDiff::new(&a.to_pa_mesh(), &b.to_pa_mesh(), args.cell_size);
+    println!("{diff}");
+    if args.no_render {
+        return;
+    }
+    App::new()
+        .insert_resource(DiffToShow { a, b, diff })
+        .add_plugins(DefaultPlugins)
+        .add_plugins(MeshquissePlugins)
+        .add_startup_system(setup)
+        .run();
+}
+
+fn load(path: &str) -> ConvexPolygonsMeshData {
+    let mut file = std::fs::File::open(path).unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+    ConvexPolygonsMeshData::from(&MeshMerger::from_bytes(&buffer))
+}
+
+/// Both meshes are overlaid with transparency,
+/// cells only walkable in the first one are red, only walkable in the second one are green.
+fn setup(
+    mut commands: Commands,
+    to_show: Res<DiffToShow>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let layers = [
+        (
+            to_show.a.to_bevy_mesh(&PlaneMapping::XZ),
+            Color::rgba(0.2, 0.4, 1.0, 0.4),
+            0.0,
+        ),
+        (
+            to_show.b.to_bevy_mesh(&PlaneMapping::XZ),
+            Color::rgba(1.0, 0.6, 0.2, 0.4),
+            0.05,
+        ),
+        (
+            cells_mesh(&to_show.diff.only_in_a, to_show.diff.cell_size),
+            Color::RED,
+            0.1,
+        ),
+        (
+            cells_mesh(&to_show.diff.only_in_b, to_show.diff.cell_size),
+            Color::GREEN,
+            0.1,
+        ),
+    ];
+    for (mut mesh, color, height) in layers {
+        // Plain colors make the overlay readable, rather than per polygon colors.
+        let nb_vertices = mesh.count_vertices();
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_COLOR,
+            vec![[1.0, 1.0, 1.0, 1.0]; nb_vertices],
+        );
+        commands.spawn_bundle(PbrBundle {
+            mesh: meshes.add(mesh),
+            material: materials.add(StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            transform: Transform::from_xyz(0.0, height, 0.0),
+            ..default()
+        });
+    }
+}
+
+fn cells_mesh(cell_centers: &[Vec2], cell_size: f32) -> Mesh {
+    let half = cell_size / 2.0;
+    let positions: Vec<[f32; 3]> = cell_centers
+        .iter()
+        .flat_map(|c| {
+            [
+                [c.x - half, 0.0, c.y - half],
+                [c.x - half, 0.0, c.y + half],
+                [c.x + half, 0.0, c.y + half],
+                [c.x + half, 0.0, c.y - half],
+            ]
+        })
+        .collect();
+    let indices = (0..cell_centers.len() as u32)
+        .flat_map(|i| [i * 4, i * 4 + 1, i * 4 + 2, i * 4, i * 4 + 2, i * 4 + 3])
+        .collect();
+    let nb_vertices = positions.len();
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; nb_vertices]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; nb_vertices]);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
@@ -1,10 +1,17 @@
 use std::{io::Read, time::SystemTime};
 
-use bevy::{pbr::wireframe::WireframePlugin, prelude::*};
-use bevy_flycam::{FlyCam, NoCameraPlayerPlugin};
+use bevy::{
+    pbr::wireframe::WireframePlugin,
+    prelude::*,
+    render::settings::{WgpuFeatures, WgpuSettings},
+};
 use meshquisse::{
-    interact_mesh::{EditableMesh, InteractMeshPlugin, ShowAndUpdateMesh, UpdateNavMesh},
-    mesh_data::{merge_triangles::ConvexPolygonsMeshData, only_triangles::TriangleMeshData},
+    interact_mesh::{EditableMesh, MeshEditorPlugins, ShowAndUpdateMesh, UpdateNavMesh},
+    merge_debugger::{MergeDebugger, MergeDebuggerPlugin},
+    mesh_data::{
+        merge_triangles::ConvexPolygonsMeshData, only_triangles::TriangleMeshData,
+        topology::TopologyEditPlugin,
+    },
     tools::create_grid_trimesh,
     trianglemerger::{MeshMerger, UnionFind},
     *,
@@ -18,19 +25,19 @@ struct ToolPlugin;
 
 impl Plugin for ToolPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(MeshquissePlugin)
-            //.add_plugin(NoCameraPlayerPlugin)
-            //.add_plugin(WireframePlugin)
-            .add_plugin(InteractMeshPlugin::<ConvexPolygonsMeshData>::default())
-            .add_startup_system(setup)
-            .add_system(update_camera)
-            .add_system(save_mesh)
-            .add_system(try_merge_1);
-    }
-}
-fn update_camera(mut commands: Commands, cam: Query<Entity, Added<MainCamera>>) {
-    for e in cam.iter() {
-        //commands.entity(e).insert(FlyCam);
+        // Needed by `WireframePlugin`, before `DefaultPlugins` creates the render device.
+        app.insert_resource(WgpuSettings {
+            features: WgpuFeatures::POLYGON_MODE_LINE,
+            ..default()
+        })
+        .add_plugins(DefaultPlugins)
+        .add_plugins(MeshquissePlugins)
+        //.add_plugin(WireframePlugin)
+        .add_plugins(MeshEditorPlugins::<ConvexPolygonsMeshData>::default())
+        .add_plugin(MergeDebuggerPlugin)
+        .add_plugin(TopologyEditPlugin)
+        .add_startup_system(setup)
+        .add_system(save_mesh);
     }
 }
 
@@ -72,7 +79,8 @@ fn setup(mut commands: Commands) {
         .insert(convex_data)
         .insert(ShowAndUpdateMesh::default())
         .insert(UpdateNavMesh)
-        .insert(EditableMesh);
+        .insert(EditableMesh)
+        .insert(MergeDebugger::default());
 }
 
 fn save_mesh(keyboard_input: Res<Input<KeyCode>>) {
@@ -80,29 +88,3 @@ fn save_mesh(keyboard_input: Res<Input<KeyCode>>) {
         // TODO: save mesh into mesh 2 format, to load into it and test different merge stuff.
     }
 }
-fn try_merge_1(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut mesh_convex_data: Query<&mut ConvexPolygonsMeshData>,
-) {
-    if keyboard_input.just_pressed(KeyCode::M) {
-        dbg!("just pressed M");
-        for mut data in mesh_convex_data.iter_mut() {
-            let nb_polygons = data.mesh_polygons.len();
-            let union_find = UnionFind {
-                parent: (0i32..(nb_polygons as i32))
-                    .map(|polygon_index| polygon_index)
-                    .collect(),
-            };
-            let mut mesh_merger = MeshMerger {
-                mesh_vertices: data.mesh_vertices.clone(),
-                mesh_polygons: data.mesh_polygons.clone(),
-                polygon_unions: union_find,
-            };
-            mesh_merger.my_merge();
-            mesh_merger.remove_unused();
-            dbg!(&mesh_merger);
-
-            *data = ConvexPolygonsMeshData::from(&mesh_merger);
-        }
-    }
-}
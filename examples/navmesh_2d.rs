@@ -0,0 +1,45 @@
+use std::io::Read;
+
+use bevy::prelude::*;
+use meshquisse::{
+    mesh_data::{merge_triangles::ConvexPolygonsMeshData, IntoPAMesh},
+    navmesh::NavMesh,
+    plane::PlaneMapping,
+    render_2d::MeshquissePlugins2d,
+    trianglemerger::MeshMerger,
+    MainCamera,
+};
+
+/// The arena navmesh in Bevy 2D: middle click to add path steps, right click to clear them.
+fn main() {
+    App::new()
+        .insert_resource(PlaneMapping::XY)
+        .add_plugins(DefaultPlugins)
+        .add_plugins(MeshquissePlugins2d)
+        .add_startup_system(setup)
+        .run();
+}
+
+fn setup(mut commands: Commands) {
+    let mut file = std::fs::File::open("assets/meshes/arena.mesh").unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+    let mut mesh_merger = MeshMerger::from_bytes(&buffer);
+    mesh_merger.my_merge();
+    mesh_merger.remove_unused();
+    let mesh_data = ConvexPolygonsMeshData::from(&mesh_merger);
+
+    let (min, max) = mesh_data.mesh_vertices.iter().fold(
+        (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+        |(min, max), v| (min.min(v.p), max.max(v.p)),
+    );
+    let center = (min + max) / 2.0;
+    let mut camera = Camera2dBundle::default();
+    camera.transform.translation = center.extend(camera.transform.translation.z);
+    camera.projection.scale = 0.1;
+    commands.spawn_bundle(camera).insert(MainCamera);
+
+    commands
+        .spawn_bundle(SpatialBundle::default())
+        .insert(NavMesh::new(mesh_data.to_pa_mesh()).with_area_types(mesh_data.area_types()));
+}
@@ -0,0 +1,74 @@
+use bevy::{app::PluginGroupBuilder, prelude::*};
+use bevy_polyline::prelude::*;
+
+use crate::{
+    navmesh::NavMeshPlugin, navmesh_debug::NavMeshDebugPlugin, offmesh::OffMeshLinkPlugin,
+    path_query::PathQueryPlugin, MainCamera,
+};
+
+/// Behaviour of the examples, to add after `DefaultPlugins`:
+/// navmesh, debug rendering, demo scene and interactive path queries.
+///
+/// Each plugin can be added on its own to pull only what's needed,
+/// the editor lives in `interact_mesh::MeshEditorPlugins`.
+pub struct MeshquissePlugins;
+
+impl PluginGroup for MeshquissePlugins {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        group
+            .add(PolylinePlugin)
+            .add(NavMeshPlugin)
+            .add(NavMeshDebugPlugin)
+            .add(OffMeshLinkPlugin)
+            .add(DemoScenePlugin)
+            .add(PathQueryPlugin);
+    }
+}
+
+/// Scene of the examples: clear color, a `MainCamera` and a light.
+pub struct DemoScenePlugin;
+
+impl Plugin for DemoScenePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ClearColor(Color::rgb(
+            0xF9 as f32 / 255.0,
+            0xF9 as f32 / 255.0,
+            0xFF as f32 / 255.0,
+        )))
+        .insert_resource(Msaa::default())
+        .add_startup_system(setup_graphics);
+    }
+}
+
+fn setup_graphics(mut commands: Commands) {
+    commands
+        .spawn_bundle(Camera3dBundle {
+            transform: Transform::from_xyz(-30.0, 30.0, 100.0)
+                .looking_at(Vec3::new(0.0, 10.0, 0.0), Vec3::Y),
+            ..Default::default()
+        })
+        .insert(MainCamera);
+    const HALF_SIZE: f32 = 100.0;
+    commands.spawn_bundle(DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            shadow_projection: OrthographicProjection {
+                left: -HALF_SIZE,
+                right: HALF_SIZE,
+                bottom: -HALF_SIZE,
+                top: HALF_SIZE,
+                near: -10.0 * HALF_SIZE,
+                far: 10.0 * HALF_SIZE,
+                ..default()
+            },
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_rotation(Quat::from_euler(
+            EulerRot::ZYX,
+            0.0,
+            3f32 * std::f32::consts::PI / 5.0,
+            -std::f32::consts::PI / 4.,
+        )),
+        ..default()
+    });
+}
@@ -0,0 +1,298 @@
+//! Builds navmeshes from glTF scenes, so they follow the level art without a separate export.
+//!
+//! Nodes are selected by name or by a key in their extras, children inherit the role of
+//! their parent. glTF is Y up: world positions `(x, y, z)` become navmesh points `(x, z)`,
+//! so stacked floors overlap and are not supported.
+
+use std::{collections::HashMap, fmt, path::Path};
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+use gltf::{buffer::Data, mesh::Mode, Document, Node};
+
+use crate::{
+    mesh_data::merge_triangles::ConvexPolygonsMeshData,
+    trianglemerger::{MeshMerger, Polygon, Vertex},
+};
+
+/// Positions closer than this are welded into one vertex.
+pub const GLTF_WELD_DISTANCE: f32 = 1e-3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfImportOptions {
+    /// Nodes named with this prefix, or with this key in their extras, are walkable.
+    pub walkable_tag: String,
+    /// Nodes named with this prefix, or with this key in their extras, are carved out
+    /// of walkable surfaces, along the convex hull of their meshes seen from above.
+    pub obstacle_tag: String,
+    /// Walkable triangles steeper than that, in radians, are dropped.
+    pub max_slope: f32,
+    /// Merges polygons with `MeshMerger` after carving obstacles.
+    pub merge: bool,
+}
+
+impl Default for GltfImportOptions {
+    fn default() -> Self {
+        GltfImportOptions {
+            walkable_tag: "navmesh".to_string(),
+            obstacle_tag: "obstacle".to_string(),
+            max_slope: std::f32::consts::FRAC_PI_4,
+            merge: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GltfImportError {
+    Gltf(gltf::Error),
+    /// No triangle of a walkable node is flat enough.
+    NoWalkableSurface,
+    /// A walkable primitive references a vertex it doesn't have.
+    InvalidIndex {
+        index: u32,
+        nb_positions: usize,
+    },
+}
+
+impl fmt::Display for GltfImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfImportError::Gltf(error) => write!(f, "{error}"),
+            GltfImportError::NoWalkableSurface => write!(f, "no walkable surface in the scene"),
+            GltfImportError::InvalidIndex {
+                index,
+                nb_positions,
+            } => write!(
+                f,
+                "vertex index {index} out of range, primitive has {nb_positions} positions"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GltfImportError {}
+
+impl From<gltf::Error> for GltfImportError {
+    fn from(error: gltf::Error) -> Self {
+        GltfImportError::Gltf(error)
+    }
+}
+
+/// Loads a `.gltf` or `.glb` file and builds its navmesh, see `navmesh_from_gltf`.
+pub fn load_gltf_navmesh(
+    path: impl AsRef<Path>,
+    options: &GltfImportOptions,
+) -> Result<ConvexPolygonsMeshData, GltfImportError> {
+    let (document, buffers, _) = gltf::import(path)?;
+    navmesh_from_gltf(&document, &buffers, options)
+}
+
+/// Builds the navmesh of the default scene, or of the first one if there is no default.
+pub fn navmesh_from_gltf(
+    document: &Document,
+    buffers: &[Data],
+    options: &GltfImportOptions,
+) -> Result<ConvexPolygonsMeshData, GltfImportError> {
+    let mut collector = SceneCollector {
+        buffers,
+        options,
+        mesh_data: ConvexPolygonsMeshData::default(),
+        welded: HashMap::new(),
+        footprints: Vec::new(),
+    };
+    if let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        for node in scene.nodes() {
+            collector.visit(&node, Mat4::IDENTITY, NodeRole::None)?;
+        }
+    }
+    let mut mesh_data = collector.mesh_data;
+    if mesh_data.mesh_polygons.is_empty() {
+        return Err(GltfImportError::NoWalkableSurface);
+    }
+    mesh_data.rebuild_neighbours();
+    for footprint in collector.footprints.iter() {
+        mesh_data.carve(footprint);
+    }
+    if options.merge {
+        let mut mesh_merger = MeshMerger::from(&mesh_data);
+        mesh_merger.my_merge();
+        mesh_merger.remove_unused();
+        mesh_data = ConvexPolygonsMeshData::from(&mesh_merger);
+    }
+    Ok(mesh_data)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NodeRole {
+    None,
+    Walkable,
+    Obstacle,
+}
+
+struct SceneCollector<'a> {
+    buffers: &'a [Data],
+    options: &'a GltfImportOptions,
+    mesh_data: ConvexPolygonsMeshData,
+    /// Vertex index of each welded position.
+    welded: HashMap<[i64; 2], u32>,
+    /// Points of each obstacle mesh, seen from above.
+    footprints: Vec<Vec<Vec2>>,
+}
+
+impl<'a> SceneCollector<'a> {
+    fn visit(
+        &mut self,
+        node: &Node,
+        parent_transform: Mat4,
+        parent_role: NodeRole,
+    ) -> Result<(), GltfImportError> {
+        let transform = parent_transform * Mat4::from_cols_array_2d(&node.transform().matrix());
+        let role = if has_tag(node, &self.options.walkable_tag) {
+            NodeRole::Walkable
+        } else if has_tag(node, &self.options.obstacle_tag) {
+            NodeRole::Obstacle
+        } else {
+            parent_role
+        };
+        if let Some(mesh) = node.mesh() {
+            let buffers = self.buffers;
+            for primitive in mesh.primitives() {
+                let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+                let positions: Vec<Vec3> = match reader.read_positions() {
+                    Some(positions) => positions
+                        .map(|p| transform.transform_point3(Vec3::from(p)))
+                        .collect(),
+                    None => continue,
+                };
+                match role {
+                    NodeRole::Walkable if primitive.mode() == Mode::Triangles => {
+                        let indices: Vec<u32> = match reader.read_indices() {
+                            Some(indices) => indices.into_u32().collect(),
+                            None => (0..positions.len() as u32).collect(),
+                        };
+                        let position = |index: u32| {
+                            positions.get(index as usize).copied().ok_or(
+                                GltfImportError::InvalidIndex {
+                                    index,
+                                    nb_positions: positions.len(),
+                                },
+                            )
+                        };
+                        for triangle in indices.chunks_exact(3) {
+                            self.add_triangle([
+                                position(triangle[0])?,
+                                position(triangle[1])?,
+                                position(triangle[2])?,
+                            ]);
+                        }
+                    }
+                    NodeRole::Obstacle => {
+                        self.footprints
+                            .push(positions.iter().map(|p| p.xz()).collect());
+                    }
+                    _ => (),
+                }
+            }
+        }
+        for child in node.children() {
+            self.visit(&child, transform, role)?;
+        }
+        Ok(())
+    }
+
+    fn add_triangle(&mut self, triangle: [Vec3; 3]) {
+        let normal = (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]);
+        if normal.length_squared() <= f32::EPSILON
+            || normal.normalize().y < self.options.max_slope.cos()
+        {
+            return;
+        }
+        // Facing up is counter clockwise in 3d, which is clockwise seen as (x, z).
+        let vertices: Vec<u32> = [triangle[0], triangle[2], triangle[1]]
+            .iter()
+            .map(|p| self.weld(p.xz()))
+            .collect();
+        if vertices[0] == vertices[1] || vertices[1] == vertices[2] || vertices[0] == vertices[2] {
+            return;
+        }
+        self.mesh_data.mesh_polygons.push(Polygon {
+            vertices,
+            ..default()
+        });
+    }
+
+    fn weld(&mut self, position: Vec2) -> u32 {
+        let key = (position / GLTF_WELD_DISTANCE).round();
+        let mesh_vertices = &mut self.mesh_data.mesh_vertices;
+        *self
+            .welded
+            .entry([key.x as i64, key.y as i64])
+            .or_insert_with(|| {
+                mesh_vertices.push(Vertex {
+                    p: position,
+                    polygons: Vec::new(),
+                });
+                mesh_vertices.len() as u32 - 1
+            })
+    }
+}
+
+/// Whether the node name starts with `tag`, or its extras have a `tag` key which isn't `false`.
+fn has_tag(node: &Node, tag: &str) -> bool {
+    if node.name().map_or(false, |name| name.starts_with(tag)) {
+        return true;
+    }
+    node.extras()
+        .as_ref()
+        .and_then(|extras| serde_json::from_str::<serde_json::Value>(extras.get()).ok())
+        .and_then(|extras| extras.get(tag).cloned())
+        .map_or(false, |value| {
+            !value.is_null() && value != serde_json::Value::Bool(false)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trianglemerger::MeshMerger;
+
+    use super::{load_gltf_navmesh, GltfImportError, GltfImportOptions};
+
+    #[test]
+    fn floor_with_obstacle() {
+        let mesh_data =
+            load_gltf_navmesh("assets/gltf/floor_obstacle.gltf", &Default::default()).unwrap();
+        let area: f32 = mesh_data
+            .mesh_polygons
+            .iter()
+            .map(|p| MeshMerger::get_area(&mesh_data.mesh_vertices, &p.vertices) / 2.0)
+            .sum();
+        // 10 x 10 floor, minus the 2 x 2 footprint of the obstacle.
+        assert!((area - 96.0).abs() < 1e-3);
+        assert_eq!(MeshMerger::from(&mesh_data).validate(), vec![]);
+    }
+
+    #[test]
+    fn no_walkable_node() {
+        let options = GltfImportOptions {
+            walkable_tag: "floor".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            load_gltf_navmesh("assets/gltf/floor_obstacle.gltf", &options),
+            Err(GltfImportError::NoWalkableSurface)
+        ));
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        assert!(matches!(
+            load_gltf_navmesh("assets/gltf/floor_bad_index.gltf", &Default::default()),
+            Err(GltfImportError::InvalidIndex {
+                index: 9,
+                nb_positions: 4
+            })
+        ));
+    }
+}
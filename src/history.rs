@@ -0,0 +1,194 @@
+use bevy::prelude::*;
+
+use crate::mesh_data::UpdateVertex;
+
+/// A vertex moved from a position to another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexMove {
+    pub vertex_id: u32,
+    pub from: Vec2,
+    pub to: Vec2,
+}
+
+/// A reversible edit of a mesh data.
+#[derive(Debug, Clone)]
+pub enum EditCommand<MeshData> {
+    MoveVertices(Vec<VertexMove>),
+    /// Any other edit (merge, split, delete...), stored as the whole mesh data before and after.
+    Replace {
+        before: MeshData,
+        after: MeshData,
+    },
+}
+
+/// Undo and redo stacks of an `EditableMesh`.
+///
+/// Inserted by `InteractMeshPlugin` along editable meshes,
+/// bound to Ctrl+Z for undo and Ctrl+Y (or Ctrl+Shift+Z) for redo.
+#[derive(Component)]
+pub struct EditHistory<MeshData: Send + Sync + 'static> {
+    undo: Vec<EditCommand<MeshData>>,
+    redo: Vec<EditCommand<MeshData>>,
+    /// While a drag is in progress, moves are coalesced into the last `MoveVertices` command.
+    drag_in_progress: bool,
+}
+
+impl<MeshData: Send + Sync + 'static> Default for EditHistory<MeshData> {
+    fn default() -> Self {
+        EditHistory {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            drag_in_progress: false,
+        }
+    }
+}
+
+impl<MeshData: UpdateVertex + Clone + Send + Sync + 'static> EditHistory<MeshData> {
+    pub fn record(&mut self, command: EditCommand<MeshData>) {
+        self.drag_in_progress = false;
+        self.redo.clear();
+        self.undo.push(command);
+    }
+
+    /// Records a move of a vertex which is part of a continuous drag:
+    /// until `end_drag` is called, moves are merged into a single command.
+    pub fn record_drag(&mut self, vertex_id: u32, from: Vec2, to: Vec2) {
+        if self.drag_in_progress {
+            if let Some(EditCommand::MoveVertices(moves)) = self.undo.last_mut() {
+                match moves.iter_mut().find(|m| m.vertex_id == vertex_id) {
+                    Some(vertex_move) => vertex_move.to = to,
+                    None => moves.push(VertexMove {
+                        vertex_id,
+                        from,
+                        to,
+                    }),
+                }
+                return;
+            }
+        }
+        self.record(EditCommand::MoveVertices(vec![VertexMove {
+            vertex_id,
+            from,
+            to,
+        }]));
+        self.drag_in_progress = true;
+    }
+
+    /// Moves of the drag in progress, empty if there is none.
+    pub fn current_drag(&self) -> &[VertexMove] {
+        match (self.drag_in_progress, self.undo.last()) {
+            (true, Some(EditCommand::MoveVertices(moves))) => moves,
+            _ => &[],
+        }
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag_in_progress = false;
+    }
+
+    /// Applies `edit` to `mesh_data`, recording it as a single command.
+    pub fn apply_edit<R>(
+        &mut self,
+        mesh_data: &mut MeshData,
+        edit: impl FnOnce(&mut MeshData) -> R,
+    ) -> R {
+        let before = mesh_data.clone();
+        let result = edit(mesh_data);
+        self.record(EditCommand::Replace {
+            before,
+            after: mesh_data.clone(),
+        });
+        result
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Reverts the last command, returns false if there was nothing to undo.
+    pub fn undo(&mut self, mesh_data: &mut MeshData) -> bool {
+        self.drag_in_progress = false;
+        match self.undo.pop() {
+            Some(command) => {
+                match &command {
+                    EditCommand::MoveVertices(moves) => {
+                        for m in moves.iter().rev() {
+                            mesh_data.update_vertex(m.vertex_id, m.from);
+                        }
+                    }
+                    EditCommand::Replace { before, .. } => *mesh_data = before.clone(),
+                }
+                self.redo.push(command);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies again the last undone command, returns false if there was nothing to redo.
+    pub fn redo(&mut self, mesh_data: &mut MeshData) -> bool {
+        self.drag_in_progress = false;
+        match self.redo.pop() {
+            Some(command) => {
+                match &command {
+                    EditCommand::MoveVertices(moves) => {
+                        for m in moves.iter() {
+                            mesh_data.update_vertex(m.vertex_id, m.to);
+                        }
+                    }
+                    EditCommand::Replace { after, .. } => *mesh_data = after.clone(),
+                }
+                self.undo.push(command);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec2;
+
+    use crate::{
+        mesh_data::{only_triangles::TriangleMeshData, UpdateVertex},
+        tools::create_grid_trimesh,
+    };
+
+    use super::EditHistory;
+
+    #[test]
+    fn undo_redo_coalesced_drag() {
+        let mut mesh_data = TriangleMeshData(create_grid_trimesh(2, 2, 1f32));
+        let mut history = EditHistory::<TriangleMeshData>::default();
+        let origin = mesh_data.vertex_position(0);
+        for step in 1..=3 {
+            let from = mesh_data.vertex_position(0);
+            let to = origin + Vec2::splat(step as f32);
+            mesh_data.0.positions[0] = to;
+            history.record_drag(0, from, to);
+        }
+        history.end_drag();
+        assert!(history.undo(&mut mesh_data));
+        assert_eq!(mesh_data.vertex_position(0), origin);
+        assert!(!history.can_undo());
+        assert!(history.redo(&mut mesh_data));
+        assert_eq!(mesh_data.vertex_position(0), origin + Vec2::splat(3f32));
+    }
+
+    #[test]
+    fn undo_replace() {
+        let mut mesh_data = TriangleMeshData(create_grid_trimesh(2, 2, 1f32));
+        let mut history = EditHistory::<TriangleMeshData>::default();
+        history.apply_edit(&mut mesh_data, |data| data.0.indices.truncate(3));
+        assert_eq!(mesh_data.0.indices.len(), 3);
+        history.undo(&mut mesh_data);
+        assert_eq!(mesh_data.0.indices.len(), 6);
+        history.redo(&mut mesh_data);
+        assert_eq!(mesh_data.0.indices.len(), 3);
+    }
+}
@@ -1,30 +1,73 @@
 use std::marker::PhantomData;
 
-use bevy::{math::Vec3Swizzles, pbr::wireframe::Wireframe, prelude::*};
-use bevy_rapier3d::prelude::RapierContext;
+use bevy::{
+    app::PluginGroupBuilder,
+    ecs::query::ChangeTrackers,
+    pbr::wireframe::Wireframe,
+    prelude::*,
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        render_resource::PrimitiveTopology,
+    },
+};
+use bevy_mod_picking::Selection;
 use bevy_transform_gizmo::TransformGizmoSystem;
 
 use crate::{
+    history::{EditCommand, EditHistory},
     navmesh::NavMesh,
-    screen_physics_ray_cast,
+    plane::PlaneMapping,
+    screen_to_ground,
+    selection::{transform_selected_vertices, VertexSelectionPlugin},
+    snapping::{SnapSettings, SnappingPlugin},
     tools::{self, bevymesh_from_trimesh, navmesh_from_trimesh, TriangleMesh},
+    vertex_index::VertexIndex,
     MainCamera,
 };
 use polyanya::Mesh as PAMesh;
 
+pub use crate::mesh_data::{IntoBevyMesh, IntoPAMesh, UpdateVertex};
+
+/// Interactive edition of `MeshData`: picking, transform gizmo, `InteractMeshPlugin`,
+/// snapping and selection.
+///
+/// Add `InteractMeshPlugin` alone if picking and the gizmo are already set up.
+#[derive(Default)]
+pub struct MeshEditorPlugins<
+    MeshData: 'static + Component + Sync + Send + Clone + IntoPAMesh + UpdateVertex + IntoBevyMesh,
+> {
+    _p: PhantomData<MeshData>,
+}
+
+impl<
+        MeshData: 'static + Component + Sync + Send + Clone + IntoPAMesh + UpdateVertex + IntoBevyMesh,
+    > PluginGroup for MeshEditorPlugins<MeshData>
+{
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        bevy_mod_picking::DefaultPickingPlugins.build(group);
+        group
+            .add(bevy_transform_gizmo::TransformGizmoPlugin::default())
+            .add(InteractMeshPlugin::<MeshData> { _p: PhantomData })
+            .add(SnappingPlugin)
+            .add(VertexSelectionPlugin);
+    }
+}
+
+/// Needs picking and `TransformGizmoPlugin`, see `MeshEditorPlugins`.
 #[derive(Default)]
 pub struct InteractMeshPlugin<
-    MeshData: 'static + Component + Sync + Send + IntoPAMesh + UpdateVertex + IntoBevyMesh,
+    MeshData: 'static + Component + Sync + Send + Clone + IntoPAMesh + UpdateVertex + IntoBevyMesh,
 > {
     _p: PhantomData<MeshData>,
 }
 
-impl<MeshData: 'static + Component + Sync + Send + IntoPAMesh + UpdateVertex + IntoBevyMesh> Plugin
-    for InteractMeshPlugin<MeshData>
+impl<
+        MeshData: 'static + Component + Sync + Send + Clone + IntoPAMesh + UpdateVertex + IntoBevyMesh,
+    > Plugin for InteractMeshPlugin<MeshData>
 {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_plugins(bevy_mod_picking::DefaultPickingPlugins)
-            .add_plugin(bevy_transform_gizmo::TransformGizmoPlugin::default())
+        app.init_resource::<ConvexityPolicy>()
+            .init_resource::<PlaneMapping>()
             .add_startup_system(init_assets)
             .add_stage_before(
                 CoreStage::PreUpdate,
@@ -33,7 +76,14 @@ impl<MeshData: 'static + Component + Sync + Send + IntoPAMesh + UpdateVertex + I
             )
             .add_system_to_stage("before_preupdate", adapt_camera)
             .add_system(spawn_vertices_selectable::<MeshData>)
-            .add_system(update_vertices_position::<MeshData>)
+            .add_system(
+                update_vertices_position::<MeshData>.label(InteractMeshSystem::UpdateVertices),
+            )
+            .add_system(end_vertices_drag::<MeshData>)
+            .add_system(undo_redo_shortcuts::<MeshData>)
+            // Handles are moved back to their vertex, so after vertices follow their handles.
+            .add_system(update_vertex_handles::<MeshData>.after(InteractMeshSystem::UpdateVertices))
+            .add_system(transform_selected_vertices::<MeshData>)
             .add_system(spawn_visual_mesh::<MeshData>)
             .add_system(update_visual_mesh::<MeshData>)
             .add_system(spawn_navmesh::<MeshData>)
@@ -41,17 +91,27 @@ impl<MeshData: 'static + Component + Sync + Send + IntoPAMesh + UpdateVertex + I
     }
 }
 
-pub trait IntoPAMesh {
-    fn to_pa_mesh(&self) -> PAMesh;
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InteractMeshSystem {
+    /// Mesh data vertices updated from their moved handle.
+    UpdateVertices,
 }
-pub trait UpdateVertex {
-    fn update_vertex(&mut self, vertex_index: u32, position: Vec3);
-    // FIXME: perf is horrible but I didn't succeed in getting a generic iterator over Vec2 or Vertex.
-    fn iter_positions(&self) -> Vec<Vec2>;
+
+/// What to do when dragging a vertex makes one of its polygons concave or inverted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvexityPolicy {
+    /// Stop the vertex at the last position keeping its polygons convex.
+    Clamp,
+    /// Allow the move, concave polygons are drawn in red.
+    Highlight,
+    /// Split concave polygons, clamping if they are inverted.
+    Split,
 }
-pub trait IntoBevyMesh {
-    fn to_bevy_mesh(&self) -> Mesh;
-    fn update_mesh(&self, mesh: &mut Mesh);
+
+impl Default for ConvexityPolicy {
+    fn default() -> Self {
+        ConvexityPolicy::Clamp
+    }
 }
 
 /// Only useful if entity has a `TriangleMeshData`.
@@ -67,20 +127,39 @@ pub struct UpdateNavMesh;
 pub struct ShowAndUpdateMesh(pub Option<Handle<Mesh>>);
 
 /// Only useful if entity has a `TriangleMeshData`.
-/// Will spawn children selectable handles via bevy_transform_gizmo.
+/// Will draw all vertices as a single batched mesh, and index their positions in a `VertexIndex`.
+/// Clicking near a vertex selects it and spawns a child handle movable via bevy_transform_gizmo,
+/// only selected vertices have a handle.
 /// When these gizmos are updated, they reach for their parent `EditableMesh`
 /// and update its mesh.
 #[derive(Component)]
 pub struct EditableMesh;
 
+/// Handle of a selected vertex.
 #[derive(Component)]
 pub struct EditableMeshVertex {
     pub vertex_id: u32,
 }
 
+/// Selected vertices of an `EditableMesh`, each one getting an `EditableMeshVertex` handle.
+///
+/// Cleared when the number of vertices changes, as vertex ids are shifted.
+#[derive(Component, Default, Debug)]
+pub struct SelectedVertices {
+    pub vertices: Vec<u32>,
+}
+
+/// Batched mesh drawing all vertices of an `EditableMesh`.
+#[derive(Component)]
+struct VertexHandlesMesh(Handle<Mesh>);
+
+/// Size of vertex handles, also the distance under which a click picks a vertex.
+pub const VERTEX_HANDLE_SIZE: f32 = 1.0;
+
 pub struct InteractAssets {
     gizmo_mesh: Handle<Mesh>,
     gizmo_mesh_mat: Handle<StandardMaterial>,
+    vertices_mesh_mat: Handle<StandardMaterial>,
     visual_mesh_mat: Handle<StandardMaterial>,
 }
 
@@ -90,8 +169,16 @@ fn init_assets(
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
     commands.insert_resource(InteractAssets {
-        gizmo_mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+        gizmo_mesh: meshes.add(Mesh::from(shape::Cube {
+            size: VERTEX_HANDLE_SIZE,
+        })),
         gizmo_mesh_mat: materials.add(Color::rgb(0.99, 0.2, 0.3).into()),
+        vertices_mesh_mat: materials.add(StandardMaterial {
+            base_color: Color::rgb(0.3, 0.2, 0.99),
+            unlit: true,
+            cull_mode: None,
+            ..default()
+        }),
         visual_mesh_mat: materials.add(Color::rgb(1f32, 1f32, 1f32).into()),
     });
 }
@@ -108,19 +195,27 @@ fn spawn_visual_mesh<MeshData: IntoBevyMesh + Component>(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     assets: Res<InteractAssets>,
+    mapping: Res<PlaneMapping>,
     mut q_new_shown_meshes: Query<
-        (Entity, &MeshData, &mut ShowAndUpdateMesh),
+        (
+            Entity,
+            &MeshData,
+            &mut ShowAndUpdateMesh,
+            Option<&Transform>,
+        ),
         Added<ShowAndUpdateMesh>,
     >,
 ) {
-    for (e, mesh_data, mut show_update_mesh) in q_new_shown_meshes.iter_mut() {
-        let mesh_handle = meshes.add(mesh_data.to_bevy_mesh());
+    for (e, mesh_data, mut show_update_mesh, transform) in q_new_shown_meshes.iter_mut() {
+        let mesh_handle = meshes.add(mesh_data.to_bevy_mesh(&mapping));
         (*show_update_mesh).0 = Some(mesh_handle.clone());
         commands
             .entity(e)
             .insert_bundle(PbrBundle {
                 mesh: mesh_handle,
                 material: assets.visual_mesh_mat.clone(),
+                // Keep where the mesh was placed.
+                transform: transform.copied().unwrap_or_default(),
                 ..default()
             })
             .insert(Wireframe);
@@ -130,13 +225,14 @@ fn spawn_visual_mesh<MeshData: IntoBevyMesh + Component>(
 /// Optimised update to modify only vertex positions
 fn update_visual_mesh<MeshData: IntoBevyMesh + Component>(
     mut meshes: ResMut<Assets<Mesh>>,
+    mapping: Res<PlaneMapping>,
     q_updated_meshes: Query<(&ShowAndUpdateMesh, &MeshData), Changed<MeshData>>,
 ) {
     for (update, mesh_data) in q_updated_meshes.iter() {
         dbg!("changed");
         if let Some(mesh_handle) = update.0.as_ref() {
             if let Some(mesh) = meshes.get_mut(mesh_handle) {
-                mesh_data.update_mesh(mesh)
+                mesh_data.update_mesh(mesh, &mapping)
             }
         }
     }
@@ -145,12 +241,13 @@ fn update_visual_mesh<MeshData: IntoBevyMesh + Component>(
 /// full update
 fn update_visual_mesh<MeshData: IntoBevyMesh + Component>(
     mut meshes: ResMut<Assets<Mesh>>,
+    mapping: Res<PlaneMapping>,
     q_updated_meshes: Query<(&ShowAndUpdateMesh, &MeshData), Changed<MeshData>>,
 ) {
     for (update, mesh_data) in q_updated_meshes.iter() {
         if let Some(mesh_handle) = update.0.as_ref() {
             if let Some(mesh) = meshes.get_mut(mesh_handle) {
-                *mesh = mesh_data.to_bevy_mesh();
+                *mesh = mesh_data.to_bevy_mesh(&mapping);
             }
         }
     }
@@ -158,38 +255,398 @@ fn update_visual_mesh<MeshData: IntoBevyMesh + Component>(
 
 fn spawn_vertices_selectable<MeshData: UpdateVertex + Component>(
     mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
     assets: Res<InteractAssets>,
+    mapping: Res<PlaneMapping>,
     q_new_editable_meshes: Query<(Entity, &MeshData), Added<EditableMesh>>,
 ) {
     for (e, mesh_data) in q_new_editable_meshes.iter() {
-        commands.entity(e).add_children(|parent| {
-            for (vertex_id, position) in mesh_data.iter_positions().iter().enumerate() {
-                parent
-                    .spawn_bundle(PbrBundle {
-                        mesh: assets.gizmo_mesh.clone(),
-                        material: assets.gizmo_mesh_mat.clone(),
-                        transform: Transform::from_translation(Vec3::new(
-                            position.x, 0f32, position.y,
-                        )),
-                        ..Default::default()
-                    })
-                    .insert(EditableMeshVertex {
-                        vertex_id: vertex_id as u32,
-                    })
-                    .insert_bundle(bevy_mod_picking::PickableBundle::default())
-                    .insert(bevy_transform_gizmo::GizmoTransformable);
-            }
-        });
+        let positions = mesh_data.iter_positions();
+        let vertices_mesh = meshes.add(vertex_handles_mesh(&positions, &mapping));
+        commands
+            .entity(e)
+            .insert(EditHistory::<MeshData>::default())
+            .insert(SelectedVertices::default())
+            .insert(VertexIndex::new(positions, VERTEX_HANDLE_SIZE))
+            .insert(VertexHandlesMesh(vertices_mesh.clone()))
+            .add_children(|parent| {
+                parent.spawn_bundle(PbrBundle {
+                    mesh: vertices_mesh,
+                    material: assets.vertices_mesh_mat.clone(),
+                    ..default()
+                });
+            });
     }
 }
 
-fn update_vertices_position<MeshData: UpdateVertex + Component>(
+/// A flat square per vertex, slightly above the mesh.
+fn vertex_handles_mesh(positions: &[Vec2], mapping: &PlaneMapping) -> Mesh {
+    let vertices: Vec<[f32; 3]> = positions
+        .iter()
+        .flat_map(|p| handle_corners(*p, mapping))
+        .collect();
+    let indices = (0..positions.len() as u32)
+        .flat_map(|i| [i * 4, i * 4 + 1, i * 4 + 2, i * 4, i * 4 + 2, i * 4 + 3])
+        .collect();
+    let nb_vertices = vertices.len();
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![mapping.normal().to_array(); nb_vertices],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; nb_vertices]);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+/// Moves the squares of `moved` vertices in a mesh made by `vertex_handles_mesh`.
+fn move_vertex_handles(mesh: &mut Mesh, moved: &[u32], positions: &[Vec2], mapping: &PlaneMapping) {
+    if let Some(VertexAttributeValues::Float32x3(vertices)) =
+        mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
+    {
+        for vertex_id in moved {
+            let first = *vertex_id as usize * 4;
+            vertices[first..first + 4]
+                .copy_from_slice(&handle_corners(positions[*vertex_id as usize], mapping));
+        }
+    }
+}
+
+fn handle_corners(position: Vec2, mapping: &PlaneMapping) -> [[f32; 3]; 4] {
+    let half = VERTEX_HANDLE_SIZE / 4.0;
+    [
+        Vec2::new(-half, -half),
+        Vec2::new(-half, half),
+        Vec2::new(half, half),
+        Vec2::new(half, -half),
+    ]
+    .map(|corner| mapping.to_world_offset(position + corner, 0.01).to_array())
+}
+
+fn update_vertices_position<MeshData: UpdateVertex + Clone + Component>(
+    policy: Res<ConvexityPolicy>,
+    mapping: Res<PlaneMapping>,
+    snap_settings: Option<Res<SnapSettings>>,
     q_changed_vertices: Query<(&Parent, &EditableMeshVertex, &Transform), Changed<Transform>>,
-    mut q_parent_mesh_data: Query<&mut MeshData>,
+    mut q_parent_mesh_data: Query<(&mut MeshData, Option<&mut EditHistory<MeshData>>)>,
 ) {
     for (parent, vertex, transform) in q_changed_vertices.iter() {
-        if let Ok(mut mesh_data_to_edit) = q_parent_mesh_data.get_mut(parent.get()) {
-            mesh_data_to_edit.update_vertex(vertex.vertex_id, transform.translation);
+        if let Ok((mut mesh_data_to_edit, history)) = q_parent_mesh_data.get_mut(parent.get()) {
+            let from = mesh_data_to_edit.vertex_position(vertex.vertex_id);
+            // Marked as changed below only if the vertex actually moved, so that a move
+            // clamped back to its start doesn't rebuild the mesh and its navmesh.
+            match apply_vertex_move(
+                mesh_data_to_edit.bypass_change_detection(),
+                vertex.vertex_id,
+                mapping.to_plane(transform.translation),
+                *policy,
+                snap_settings.as_deref(),
+            ) {
+                // Also filters out transforms moved back by `update_vertex_handles`.
+                VertexMoveOutcome::Moved(to) if to == from => {}
+                VertexMoveOutcome::Moved(to) => {
+                    mesh_data_to_edit.set_changed();
+                    if let Some(mut history) = history {
+                        history.record_drag(vertex.vertex_id, from, to);
+                    }
+                }
+                VertexMoveOutcome::Split { before } => {
+                    mesh_data_to_edit.set_changed();
+                    if let Some(mut history) = history {
+                        let after = mesh_data_to_edit.clone();
+                        history.record(EditCommand::Replace { before, after });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// How `apply_vertex_move` moved a vertex.
+pub(crate) enum VertexMoveOutcome<MeshData> {
+    /// The vertex ended at this position, maybe snapped or clamped.
+    Moved(Vec2),
+    /// Polygons made concave by the move were split, `before` is the mesh data before the move.
+    Split { before: MeshData },
+}
+
+/// Moves a vertex like every vertex edit does: snapped with `snap_settings` if enabled,
+/// then kept convex as set by `policy`.
+pub(crate) fn apply_vertex_move<MeshData: UpdateVertex + Clone>(
+    mesh_data: &mut MeshData,
+    vertex_id: u32,
+    to: Vec2,
+    policy: ConvexityPolicy,
+    snap_settings: Option<&SnapSettings>,
+) -> VertexMoveOutcome<MeshData> {
+    let from = mesh_data.vertex_position(vertex_id);
+    let mut to = to;
+    if let Some(snap_settings) = snap_settings.filter(|s| s.enabled) {
+        to = snap_settings
+            .snap(
+                vertex_id,
+                to,
+                &mesh_data.iter_positions(),
+                &mesh_data.edges(),
+            )
+            .0;
+    }
+    if from == to {
+        return VertexMoveOutcome::Moved(from);
+    }
+    // Polygons already concave before the move are not our concern.
+    let was_convex = mesh_data.concave_polygons_around(vertex_id).is_empty();
+    mesh_data.update_vertex(vertex_id, to);
+    if !was_convex
+        || policy == ConvexityPolicy::Highlight
+        || mesh_data.concave_polygons_around(vertex_id).is_empty()
+    {
+        return VertexMoveOutcome::Moved(to);
+    }
+    if policy == ConvexityPolicy::Split {
+        let mut before = mesh_data.clone();
+        before.update_vertex(vertex_id, from);
+        if mesh_data.restore_convexity(vertex_id) {
+            return VertexMoveOutcome::Split { before };
+        }
+    }
+    VertexMoveOutcome::Moved(clamp_vertex_move(mesh_data, vertex_id, from, to))
+}
+
+/// Moves the vertex as far as possible from `from` towards `to`, keeping its polygons convex.
+fn clamp_vertex_move<MeshData: UpdateVertex>(
+    mesh_data: &mut MeshData,
+    vertex_id: u32,
+    from: Vec2,
+    to: Vec2,
+) -> Vec2 {
+    let mut valid = 0f32;
+    let mut invalid = 1f32;
+    for _ in 0..16 {
+        let t = (valid + invalid) / 2f32;
+        let position = from.lerp(to, t);
+        mesh_data.update_vertex(vertex_id, position);
+        if mesh_data.concave_polygons_around(vertex_id).is_empty() {
+            valid = t;
+        } else {
+            invalid = t;
+        }
+    }
+    let clamped = from.lerp(to, valid);
+    mesh_data.update_vertex(vertex_id, clamped);
+    clamped
+}
+
+/// Releasing the mouse ends the gizmo drag, next moves will be a new history entry.
+///
+/// Dragged vertices snapped onto another vertex are welded if `SnapSettings::weld` is set.
+fn end_vertices_drag<MeshData: UpdateVertex + Clone + Component>(
+    mouse_button_input: Res<Input<MouseButton>>,
+    snap_settings: Option<Res<SnapSettings>>,
+    mut q_histories: Query<(&mut MeshData, &mut EditHistory<MeshData>)>,
+) {
+    if !mouse_button_input.just_released(MouseButton::Left) {
+        return;
+    }
+    let weld = snap_settings.map_or(false, |s| s.enabled && s.weld);
+    for (mut mesh_data, mut history) in q_histories.iter_mut() {
+        let mut dragged: Vec<u32> = if weld {
+            history.current_drag().iter().map(|m| m.vertex_id).collect()
+        } else {
+            Vec::new()
+        };
+        history.end_drag();
+        // Welding shifts next vertices, so start from the last one.
+        dragged.sort_unstable();
+        for vertex in dragged.into_iter().rev() {
+            let position = mesh_data.vertex_position(vertex);
+            let other = mesh_data
+                .iter_positions()
+                .iter()
+                .enumerate()
+                .position(|(other, p)| other as u32 != vertex && *p == position);
+            if let Some(other) = other {
+                let other = other as u32;
+                let mut welded = mesh_data.clone();
+                if welded.weld_vertex(vertex.max(other), vertex.min(other)) {
+                    history.apply_edit(&mut mesh_data, |data| *data = welded);
+                }
+            }
+        }
+    }
+}
+
+/// Ctrl+Z to undo, Ctrl+Y or Ctrl+Shift+Z to redo.
+fn undo_redo_shortcuts<MeshData: UpdateVertex + Clone + Component>(
+    keys: Res<Input<KeyCode>>,
+    mut q_histories: Query<(&mut MeshData, &mut EditHistory<MeshData>)>,
+) {
+    if !keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+    let undo = keys.just_pressed(KeyCode::Z) && !shift;
+    let redo = keys.just_pressed(KeyCode::Y) || (keys.just_pressed(KeyCode::Z) && shift);
+    if !undo && !redo {
+        return;
+    }
+    for (mut mesh_data, mut history) in q_histories.iter_mut() {
+        if undo && history.can_undo() {
+            history.undo(&mut mesh_data);
+        } else if redo && history.can_redo() {
+            history.redo(&mut mesh_data);
+        }
+    }
+}
+
+/// Keeps the batched vertices mesh, the `VertexIndex` and selected vertex handles
+/// up to date with the mesh data, and picks vertices on click.
+///
+/// A click without `Alt` selects the closest vertex, `Ctrl` adds it to the selection.
+/// Handles deselected by `bevy_mod_picking` (clicking elsewhere) leave the selection.
+fn update_vertex_handles<MeshData: UpdateVertex + Component>(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    assets: Res<InteractAssets>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    mapping: Res<PlaneMapping>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut q_meshes: Query<
+        (
+            Entity,
+            &MeshData,
+            ChangeTrackers<MeshData>,
+            &mut VertexIndex,
+            &mut SelectedVertices,
+            &VertexHandlesMesh,
+            Option<&Children>,
+            Option<&GlobalTransform>,
+        ),
+        With<EditableMesh>,
+    >,
+    mut q_handles: Query<(&EditableMeshVertex, &Selection, &mut Transform)>,
+) {
+    let click = mouse_button_input.just_pressed(MouseButton::Left)
+        && !keys.any_pressed([KeyCode::LAlt, KeyCode::RAlt]);
+    let additive = keys.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+    for (e, mesh_data, tracker, mut index, mut selected, vertices_mesh, children, mesh_transform) in
+        q_meshes.iter_mut()
+    {
+        let cursor = if click {
+            windows.get_primary().and_then(|window| {
+                let (camera, camera_transform) = q_camera.iter().next()?;
+                screen_to_ground(
+                    window,
+                    camera,
+                    camera_transform,
+                    &mapping,
+                    mesh_transform,
+                    window.cursor_position()?,
+                )
+            })
+        } else {
+            None
+        };
+        let mut vertices = selected.vertices.clone();
+        if tracker.is_changed() {
+            let positions = mesh_data.iter_positions();
+            if positions.len() != index.positions().len() {
+                // Vertices were added or removed, rebuild everything.
+                vertices.clear();
+                if let Some(mesh) = meshes.get_mut(&vertices_mesh.0) {
+                    *mesh = vertex_handles_mesh(&positions, &mapping);
+                }
+                *index = VertexIndex::new(positions, VERTEX_HANDLE_SIZE);
+            } else {
+                // While dragging, only a few vertices move.
+                let moved = index.moved_vertices(&positions);
+                if !moved.is_empty() {
+                    for vertex_id in moved.iter() {
+                        index.move_vertex(*vertex_id, positions[*vertex_id as usize]);
+                    }
+                    if let Some(mesh) = meshes.get_mut(&vertices_mesh.0) {
+                        move_vertex_handles(mesh, &moved, &positions, &mapping);
+                    }
+                }
+            }
+        }
+        let handles: Vec<Entity> = children
+            .map(|children| {
+                children
+                    .iter()
+                    .copied()
+                    .filter(|child| q_handles.contains(*child))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut still_selected = false;
+        for handle in handles.iter() {
+            if let Ok((vertex, selection, _)) = q_handles.get(*handle) {
+                if selection.selected() {
+                    still_selected = true;
+                } else {
+                    vertices.retain(|v| *v != vertex.vertex_id);
+                }
+            }
+        }
+        // A click on a handle or on the gizmo keeps handles selected, it's not a pick.
+        if let Some(cursor) = cursor.filter(|_| additive || !still_selected) {
+            let picked = index.nearest(cursor, VERTEX_HANDLE_SIZE);
+            if !additive {
+                vertices.clear();
+            }
+            if let Some(picked) = picked {
+                if !vertices.contains(&picked) {
+                    vertices.push(picked);
+                }
+            }
+        }
+        let mut with_handle = Vec::new();
+        for handle in handles {
+            if let Ok((vertex, _, mut transform)) = q_handles.get_mut(handle) {
+                if !vertices.contains(&vertex.vertex_id) {
+                    commands.entity(handle).despawn_recursive();
+                    continue;
+                }
+                with_handle.push(vertex.vertex_id);
+                // Moves handles to their mesh data position, after an undo for example.
+                let position = index.positions()[vertex.vertex_id as usize];
+                let translation = mapping.to_world(position);
+                if transform.translation != translation {
+                    transform.translation = translation;
+                }
+            }
+        }
+        let to_spawn: Vec<u32> = vertices
+            .iter()
+            .copied()
+            .filter(|v| !with_handle.contains(v))
+            .collect();
+        if !to_spawn.is_empty() {
+            commands.entity(e).add_children(|parent| {
+                for vertex_id in to_spawn {
+                    let position = index.positions()[vertex_id as usize];
+                    let mut selection = Selection::default();
+                    selection.set_selected(true);
+                    parent
+                        .spawn_bundle(PbrBundle {
+                            mesh: assets.gizmo_mesh.clone(),
+                            material: assets.gizmo_mesh_mat.clone(),
+                            transform: Transform::from_translation(mapping.to_world(position)),
+                            ..Default::default()
+                        })
+                        .insert(EditableMeshVertex { vertex_id })
+                        .insert_bundle(bevy_mod_picking::PickableBundle::default())
+                        .insert(selection)
+                        .insert(bevy_transform_gizmo::GizmoTransformable);
+                }
+            });
+        }
+        if selected.vertices != vertices {
+            selected.vertices = vertices;
         }
     }
 }
@@ -200,7 +657,9 @@ fn spawn_navmesh<MeshData: IntoPAMesh + Component>(
 ) {
     for (e, mesh_data) in q_new_shown_meshes.iter_mut() {
         let navmesh = mesh_data.to_pa_mesh();
-        commands.entity(e).insert(NavMesh { navmesh });
+        commands
+            .entity(e)
+            .insert(NavMesh::new(navmesh).with_area_types(mesh_data.area_types()));
     }
 }
 
@@ -211,6 +670,6 @@ fn update_navmesh<MeshData: IntoPAMesh + Component>(
     >,
 ) {
     for (mut update, mesh_data) in q_updated_meshes.iter_mut() {
-        update.navmesh = mesh_data.to_pa_mesh();
+        *update = NavMesh::new(mesh_data.to_pa_mesh()).with_area_types(mesh_data.area_types());
     }
 }
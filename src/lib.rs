@@ -1,166 +1,80 @@
+//! Mesh data, merging, islands and navmesh path queries build without rendering,
+//! mesh processing without bevy lives in `meshquisse_core`.
+//! Where navmeshes lie in the world is set by the `plane::PlaneMapping` resource.
+//!
+//! Features:
+//! - `render`: visual meshes and debug rendering, with `bevy_polyline`, and 2D rendering
+//!   and path queries in `render_2d`.
+//! - `picking` and `gizmo`: `bevy_mod_picking` and `bevy_transform_gizmo` integrations.
+//! - `editor`: interactive mesh edition, needs `picking` and `gizmo`.
+//! - `gltf`: navmeshes built from glTF scenes.
+//! - `serde`: serde traits on mesh data, with RON, JSON and bincode helpers in `serialization`.
+//! - `tilemap`: navmeshes of Tiled and LDtk maps.
+
+#[cfg(feature = "render")]
+pub mod demo;
+#[cfg(feature = "gltf")]
+pub mod gltf_import;
+pub mod history;
+#[cfg(feature = "editor")]
 pub mod interact_mesh;
+#[cfg(feature = "render")]
+pub mod merge_debugger;
 pub mod mesh_data;
+pub mod mesh_diff;
 pub mod navmesh;
+#[cfg(feature = "render")]
+pub mod navmesh_debug;
+pub mod obj;
+pub mod offmesh;
+#[cfg(feature = "render")]
+pub mod path_query;
+pub mod plane;
+pub mod ray_picking;
+#[cfg(feature = "render")]
+pub mod render_2d;
+#[cfg(feature = "editor")]
+pub mod selection;
+pub mod snapping;
+#[cfg(test)]
+mod test_fixtures;
+#[cfg(feature = "tilemap")]
+pub mod tilemap;
 pub mod tools;
-pub mod trianglemerger;
+pub mod vertex_index;
 
-use bevy::{
-    input::keyboard::KeyboardInput, math::Vec3Swizzles, pbr::wireframe::WireframePlugin, prelude::*,
-};
+#[cfg(feature = "serde")]
+pub use meshquisse_core::serialization;
+pub use meshquisse_core::{binary, islands, svg, trianglemerger, validation};
 
-use bevy_polyline::prelude::*;
-use bevy_rapier3d::prelude::*;
-use interact_mesh::InteractMeshPlugin;
-use mesh_data::*;
-use navmesh::NavMeshPlugin;
+use bevy::prelude::*;
 
-pub struct MeshquissePlugin;
-
-impl Plugin for MeshquissePlugin {
-    fn build(&self, app: &mut App) {
-        app.insert_resource(ClearColor(Color::rgb(
-            0xF9 as f32 / 255.0,
-            0xF9 as f32 / 255.0,
-            0xFF as f32 / 255.0,
-        )))
-        .insert_resource(Msaa::default())
-        .add_plugins(DefaultPlugins)
-        .add_plugin(PolylinePlugin)
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
-        .add_plugin(RapierDebugRenderPlugin::default())
-        .add_plugin(NavMeshPlugin)
-        .add_startup_system(setup_graphics)
-        .add_startup_system(setup_physics)
-        .add_system(cast_ray_pathfinding)
-        .add_startup_system(setup_path_display)
-        .add_system(update_path_display);
-    }
-}
+#[cfg(feature = "render")]
+pub use demo::MeshquissePlugins;
 
 #[derive(Component)]
 pub struct MainCamera;
 
-fn setup_graphics(mut commands: Commands) {
-    commands
-        .spawn_bundle(Camera3dBundle {
-            transform: Transform::from_xyz(-30.0, 30.0, 100.0)
-                .looking_at(Vec3::new(0.0, 10.0, 0.0), Vec3::Y),
-            ..Default::default()
-        })
-        .insert(MainCamera);
-    const HALF_SIZE: f32 = 100.0;
-    commands.spawn_bundle(DirectionalLightBundle {
-        directional_light: DirectionalLight {
-            shadow_projection: OrthographicProjection {
-                left: -HALF_SIZE,
-                right: HALF_SIZE,
-                bottom: -HALF_SIZE,
-                top: HALF_SIZE,
-                near: -10.0 * HALF_SIZE,
-                far: 10.0 * HALF_SIZE,
-                ..default()
-            },
-            shadows_enabled: true,
-            ..default()
-        },
-        transform: Transform::from_rotation(Quat::from_euler(
-            EulerRot::ZYX,
-            0.0,
-            3f32 * std::f32::consts::PI / 5.0,
-            -std::f32::consts::PI / 4.,
-        )),
-        ..default()
-    });
-}
-
-pub fn setup_physics(mut commands: Commands) {
-    /*
-     * Ground
-     */
-    let ground_size = 200.1;
-    let ground_height = 0.1;
-
-    commands
-        .spawn_bundle(TransformBundle::from(Transform::from_xyz(
-            0.0,
-            -ground_height,
-            0.0,
-        )))
-        .insert(Collider::cuboid(ground_size, ground_height, ground_size));
-}
-
-fn cast_ray_pathfinding(
-    mut commands: Commands,
-    mut path_to_display: ResMut<PathToDisplay>,
-    windows: Res<Windows>,
-    navmesh: Query<&navmesh::NavMesh>,
-    buttons: Res<Input<MouseButton>>,
-    keys: Res<Input<KeyCode>>,
-    rapier_context: Res<RapierContext>,
-    cameras: Query<(&Camera, &GlobalTransform)>,
-) {
-    if keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
-        return;
-    }
-    if buttons.just_pressed(MouseButton::Right) {
-        path_to_display.steps.clear();
-        return;
-    }
-    if !buttons.just_pressed(MouseButton::Middle) {
-        return;
-    }
-    let navmesh = navmesh.iter().next();
-    if navmesh.is_none() {
-        return;
-    }
-    let navmesh = navmesh.unwrap();
-    if let Some(position) = screen_physics_ray_cast(cameras, windows, rapier_context) {
-        if let Some(last_pos) = path_to_display.steps.last() {
-            let new_point = position.xz();
-            if !navmesh.navmesh.point_in_mesh(new_point) {
-                info!("point not in mesh");
-                return;
-            }
-            if let Some(path) = navmesh.navmesh.path(*last_pos, position.xz()) {
-                for p in path.path {
-                    path_to_display.steps.push(p);
-                }
-            }
-        } else {
-            path_to_display.steps.push(position.xz());
-        }
-    }
-}
-
-fn screen_physics_ray_cast(
-    cameras: Query<(&Camera, &GlobalTransform)>,
-    windows: Res<Windows>,
-    rapier_context: Res<RapierContext>,
-) -> Option<Vec3> {
-    if let Some((camera, camera_transform)) = cameras.iter().next() {
-        // First, compute a ray from the mouse position.
-        let (ray_pos, ray_dir) =
-            ray_from_mouse_position(windows.get_primary().unwrap(), camera, camera_transform);
-
-        // Then cast the ray.
-        let hit = rapier_context.cast_ray(ray_pos, ray_dir, f32::MAX, true, QueryFilter::new());
-        if let Some((_entity, toi)) = hit {
-            return Some(ray_pos + ray_dir * toi);
-        }
-    }
-    None
+#[cfg(feature = "render")]
+pub(crate) fn ray_from_mouse_position(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> (Vec3, Vec3) {
+    let mouse_position = window.cursor_position().unwrap_or(Vec2::new(0.0, 0.0));
+    ray_from_screen_position(window, camera, camera_transform, mouse_position)
 }
 
 // Credit to @doomy on discord.
-fn ray_from_mouse_position(
+#[cfg(feature = "render")]
+pub(crate) fn ray_from_screen_position(
     window: &Window,
     camera: &Camera,
     camera_transform: &GlobalTransform,
+    screen_position: Vec2,
 ) -> (Vec3, Vec3) {
-    let mouse_position = window.cursor_position().unwrap_or(Vec2::new(0.0, 0.0));
-
-    let x = 2.0 * (mouse_position.x / window.width() as f32) - 1.0;
-    let y = 2.0 * (mouse_position.y / window.height() as f32) - 1.0;
+    let x = 2.0 * (screen_position.x / window.width() as f32) - 1.0;
+    let y = 2.0 * (screen_position.y / window.height() as f32) - 1.0;
 
     let camera_inverse_matrix =
         camera_transform.compute_matrix() * camera.projection_matrix().inverse();
@@ -173,54 +87,22 @@ fn ray_from_mouse_position(
     (near, dir)
 }
 
-#[derive(Default)]
-struct PolylineAssets {
-    polyline: Handle<Polyline>,
-}
-#[derive(Default)]
-struct PathToDisplay {
-    steps: Vec<Vec2>,
-}
-
-fn setup_path_display(
-    mut commands: Commands,
-    mut polyline_materials: ResMut<Assets<PolylineMaterial>>,
-    mut polylines: ResMut<Assets<Polyline>>,
-) {
-    commands.insert_resource(PathToDisplay::default());
-
-    let polyline = polylines.add(Polyline {
-        vertices: vec![-Vec3::ONE, Vec3::ONE],
-        ..Default::default()
-    });
-    commands.insert_resource(PolylineAssets {
-        polyline: polyline.clone(),
-    });
-    commands.spawn_bundle(PolylineBundle {
-        polyline: polyline,
-        material: polyline_materials.add(PolylineMaterial {
-            width: 3.0,
-            color: Color::RED,
-            perspective: true,
-            ..Default::default()
-        }),
-        ..Default::default()
-    });
-}
-
-fn update_path_display(
-    path_to_display: Res<PathToDisplay>,
-    polyline: Res<PolylineAssets>,
-    mut polylines: ResMut<Assets<Polyline>>,
-) {
-    if !path_to_display.is_changed() {
-        return;
-    }
-    if let Some(polyline_to_change) = polylines.get_mut(&polyline.polyline) {
-        polyline_to_change.vertices = path_to_display
-            .steps
-            .iter()
-            .map(|s| Vec3::new(s.x, 0f32, s.y))
-            .collect();
-    }
+/// Intersection of the ray under `screen_position` with the navmesh plane,
+/// of a mesh placed in the world by `mesh_transform` if any.
+#[cfg(feature = "editor")]
+pub(crate) fn screen_to_ground(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    mapping: &plane::PlaneMapping,
+    mesh_transform: Option<&GlobalTransform>,
+    screen_position: Vec2,
+) -> Option<Vec2> {
+    let (origin, direction) =
+        ray_from_screen_position(window, camera, camera_transform, screen_position);
+    let (origin, direction) = match mesh_transform {
+        Some(transform) => plane::ray_to_local(transform, origin, direction),
+        None => (origin, direction),
+    };
+    mapping.ray_intersection(origin, direction)
 }
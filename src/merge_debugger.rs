@@ -0,0 +1,244 @@
+use bevy::prelude::*;
+use bevy_polyline::prelude::*;
+
+use crate::{
+    history::EditHistory,
+    mesh_data::merge_triangles::ConvexPolygonsMeshData,
+    plane::PlaneMapping,
+    trianglemerger::{MergeStep, MergeStepper, MeshMerger, UnionFind},
+};
+
+/// Steps through `MeshMerger::my_merge` on entities with a `MergeDebugger`.
+///
+/// - `N`: try the next merge.
+/// - `M`: step until a merge succeeds.
+/// - `R`: run the merge until the end.
+/// - `B`: rewind the last step.
+///
+/// The candidate edge and the 2 polygons involved are drawn,
+/// the attempt result is logged and shown in the window title.
+pub struct MergeDebuggerPlugin;
+
+impl Plugin for MergeDebuggerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlaneMapping>()
+            .add_startup_system(setup_merge_debugger_display)
+            .add_system(init_merge_debugger)
+            .add_system(step_merge_debugger)
+            .add_system(update_merge_debugger_display);
+    }
+}
+
+/// Insert it next to a `ConvexPolygonsMeshData` to debug its merge.
+#[derive(Component, Default)]
+pub struct MergeDebugger {
+    state: Option<MergeDebuggerState>,
+}
+
+struct MergeDebuggerState {
+    mesh_merger: MeshMerger,
+    stepper: MergeStepper,
+    last_step: Option<MergeStep>,
+    /// Previous states, the mesh being stored only when the step changed it.
+    history: Vec<(Option<MeshMerger>, MergeStepper, Option<MergeStep>)>,
+}
+
+impl MergeDebuggerState {
+    fn new(mesh_data: &ConvexPolygonsMeshData) -> Self {
+        let mesh_merger = MeshMerger {
+            mesh_vertices: mesh_data.mesh_vertices.clone(),
+            mesh_polygons: mesh_data.mesh_polygons.clone(),
+            polygon_unions: UnionFind::new(mesh_data.mesh_polygons.len() as i32),
+        };
+        MergeDebuggerState {
+            stepper: MergeStepper::new(&mesh_merger),
+            mesh_merger,
+            last_step: None,
+            history: Vec::new(),
+        }
+    }
+
+    /// Returns true if a merge happened.
+    fn step(&mut self) -> bool {
+        let mesh_before = self.mesh_merger.clone();
+        let stepper_before = self.stepper.clone();
+        let step = match self.stepper.step(&mut self.mesh_merger) {
+            Some(step) => step,
+            None => return false,
+        };
+        let merged = step.result.is_ok();
+        self.history.push((
+            merged.then_some(mesh_before),
+            stepper_before,
+            self.last_step.take(),
+        ));
+        self.last_step = Some(step);
+        merged
+    }
+
+    fn rewind(&mut self) -> bool {
+        match self.history.pop() {
+            Some((mesh_merger, stepper, last_step)) => {
+                if let Some(mesh_merger) = mesh_merger {
+                    self.mesh_merger = mesh_merger;
+                }
+                self.stepper = stepper;
+                self.last_step = last_step;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+struct MergeDebuggerDisplay {
+    edge: Handle<Polyline>,
+    polygon_to: Handle<Polyline>,
+    polygon_from: Handle<Polyline>,
+}
+
+fn setup_merge_debugger_display(
+    mut commands: Commands,
+    mut polyline_materials: ResMut<Assets<PolylineMaterial>>,
+    mut polylines: ResMut<Assets<Polyline>>,
+) {
+    let mut spawn_polyline = |color: Color, width: f32| {
+        let polyline = polylines.add(Polyline::default());
+        commands.spawn_bundle(PolylineBundle {
+            polyline: polyline.clone(),
+            material: polyline_materials.add(PolylineMaterial {
+                width,
+                color,
+                perspective: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        polyline
+    };
+    let display = MergeDebuggerDisplay {
+        edge: spawn_polyline(Color::YELLOW, 6.0),
+        polygon_to: spawn_polyline(Color::GREEN, 3.0),
+        polygon_from: spawn_polyline(Color::BLUE, 3.0),
+    };
+    commands.insert_resource(display);
+}
+
+fn init_merge_debugger(
+    mut q_debuggers: Query<(&mut MergeDebugger, &ConvexPolygonsMeshData), Added<MergeDebugger>>,
+) {
+    for (mut debugger, mesh_data) in q_debuggers.iter_mut() {
+        debugger.state = Some(MergeDebuggerState::new(mesh_data));
+    }
+}
+
+fn step_merge_debugger(
+    keys: Res<Input<KeyCode>>,
+    mut q_debuggers: Query<(
+        &mut MergeDebugger,
+        &mut ConvexPolygonsMeshData,
+        Option<&mut EditHistory<ConvexPolygonsMeshData>>,
+    )>,
+) {
+    for (mut debugger, mut mesh_data, history) in q_debuggers.iter_mut() {
+        let state = match debugger.state.as_mut() {
+            Some(state) => state,
+            None => continue,
+        };
+        let mesh_changed = if keys.just_pressed(KeyCode::N) {
+            state.step()
+        } else if keys.just_pressed(KeyCode::M) {
+            let mut merged = false;
+            while !merged && !state.stepper.is_finished() {
+                merged = state.step();
+            }
+            merged
+        } else if keys.just_pressed(KeyCode::R) {
+            let mut merged = false;
+            while !state.stepper.is_finished() {
+                merged |= state.step();
+            }
+            merged
+        } else if keys.just_pressed(KeyCode::B) {
+            state.rewind()
+        } else {
+            continue;
+        };
+        match &state.last_step {
+            Some(step) => info!("{step:?}"),
+            None => info!("merge debugger at start"),
+        }
+        if mesh_changed {
+            let merged = ConvexPolygonsMeshData::from(&state.mesh_merger);
+            match history {
+                Some(mut history) => history.apply_edit(&mut mesh_data, |data| *data = merged),
+                None => *mesh_data = merged,
+            }
+        }
+    }
+}
+
+fn update_merge_debugger_display(
+    display: Res<MergeDebuggerDisplay>,
+    mapping: Res<PlaneMapping>,
+    mut polylines: ResMut<Assets<Polyline>>,
+    mut windows: ResMut<Windows>,
+    q_debuggers: Query<&MergeDebugger, Changed<MergeDebugger>>,
+) {
+    for debugger in q_debuggers.iter() {
+        let state = match debugger.state.as_ref() {
+            Some(state) => state,
+            None => continue,
+        };
+        let to_3d = |vertex: &u32| {
+            let p = state.mesh_merger.mesh_vertices[*vertex as usize].p;
+            mapping.to_world_offset(p, 0.1)
+        };
+        let outline = |polygon: i32| -> Vec<Vec3> {
+            if polygon == -1 {
+                return Vec::new();
+            }
+            let vertices = &state.mesh_merger.mesh_polygons[polygon as usize].vertices;
+            vertices.iter().chain(vertices.first()).map(to_3d).collect()
+        };
+        let (edge, polygon_to, polygon_from, title): (Vec<Vec3>, Vec<Vec3>, Vec<Vec3>, String) =
+            match &state.last_step {
+                Some(step) => {
+                    let title = match &step.result {
+                        Ok(_) => format!(
+                            "merged polygon {} into {} through edge {}",
+                            step.polygon_from, step.polygon_to, step.to_index
+                        ),
+                        Err(reason) => format!(
+                            "polygon {} edge {} (to {}): {reason:?}",
+                            step.polygon_to, step.to_index, step.polygon_from
+                        ),
+                    };
+                    (
+                        step.edge.iter().map(to_3d).collect(),
+                        outline(step.polygon_to),
+                        outline(step.polygon_from),
+                        title,
+                    )
+                }
+                None => (
+                    Vec::new(),
+                    Vec::new(),
+                    Vec::new(),
+                    "merge debugger: N step, M next merge, R run, B rewind".to_string(),
+                ),
+            };
+        for (handle, vertices) in [
+            (&display.edge, edge),
+            (&display.polygon_to, polygon_to),
+            (&display.polygon_from, polygon_from),
+        ] {
+            if let Some(polyline) = polylines.get_mut(handle) {
+                polyline.vertices = vertices;
+            }
+        }
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_title(title);
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use std::i32;
 
-use bevy::{math::Vec3Swizzles, prelude::*, utils::HashMap};
+use bevy::{prelude::*, utils::HashMap};
 
 use crate::{
-    interact_mesh::*,
-    tools::{self, TriangleMesh},
-    trianglemerger::{MeshMerger, Polygon, Vertex},
+    mesh_data::*,
+    svg::Svg,
+    tools::TriangleMesh,
+    trianglemerger::{AreaType, MeshMerger, Polygon, UnionFind, Vertex},
 };
 use polyanya as PA;
 use polyanya::Mesh as PAMesh;
@@ -13,7 +14,8 @@ use polyanya::Mesh as PAMesh;
 use super::only_triangles::TriangleMeshData;
 
 /// Optimized data structure to be closer to the navmesh.
-#[derive(Default, Debug, Component)]
+#[derive(Default, Debug, Clone, PartialEq, Component)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConvexPolygonsMeshData {
     pub mesh_vertices: Vec<Vertex>,
     pub mesh_polygons: Vec<Polygon>,
@@ -41,8 +43,111 @@ impl From<&MeshMerger> for ConvexPolygonsMeshData {
     }
 }
 
+/// An id of a `ConvexPolygonsMeshData` pointing past its vertices or polygons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshDataIdError {
+    /// A vertex references a polygon which doesn't exist.
+    VertexNeighbour { vertex: usize, polygon: i32 },
+    /// A polygon references a vertex which doesn't exist.
+    PolygonVertex { polygon: usize, vertex: u32 },
+    /// A polygon references a neighbour polygon which doesn't exist.
+    PolygonNeighbour { polygon: usize, neighbour: i32 },
+    /// `invalid_polygon_ids` contains a polygon which doesn't exist.
+    InvalidPolygon { polygon: u32 },
+}
+
+impl std::fmt::Display for MeshDataIdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MeshDataIdError::VertexNeighbour { vertex, polygon } => {
+                write!(f, "vertex {vertex} references unknown polygon {polygon}")
+            }
+            MeshDataIdError::PolygonVertex { polygon, vertex } => {
+                write!(f, "polygon {polygon} references unknown vertex {vertex}")
+            }
+            MeshDataIdError::PolygonNeighbour { polygon, neighbour } => {
+                write!(
+                    f,
+                    "polygon {polygon} references unknown polygon {neighbour}"
+                )
+            }
+            MeshDataIdError::InvalidPolygon { polygon } => {
+                write!(f, "unknown invalid polygon {polygon}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MeshDataIdError {}
+
+impl ConvexPolygonsMeshData {
+    /// Checks every id is in range, to call on data which wasn't built by this crate,
+    /// like deserialized meshes, before converting it.
+    pub fn check_ids(&self) -> Result<(), MeshDataIdError> {
+        let nb_vertices = self.mesh_vertices.len();
+        let nb_polygons = self.mesh_polygons.len();
+        let is_neighbour = |polygon: i32| polygon >= -1 && polygon < nb_polygons as i32;
+        for (vertex, data) in self.mesh_vertices.iter().enumerate() {
+            if let Some(polygon) = data.polygons.iter().find(|p| !is_neighbour(**p)) {
+                return Err(MeshDataIdError::VertexNeighbour {
+                    vertex,
+                    polygon: *polygon,
+                });
+            }
+        }
+        for (polygon, data) in self.mesh_polygons.iter().enumerate() {
+            if let Some(vertex) = data.vertices.iter().find(|v| **v as usize >= nb_vertices) {
+                return Err(MeshDataIdError::PolygonVertex {
+                    polygon,
+                    vertex: *vertex,
+                });
+            }
+            if let Some(neighbour) = data.polygons.iter().find(|p| !is_neighbour(**p)) {
+                return Err(MeshDataIdError::PolygonNeighbour {
+                    polygon,
+                    neighbour: *neighbour,
+                });
+            }
+        }
+        if let Some(polygon) = self
+            .invalid_polygon_ids
+            .iter()
+            .find(|p| **p as usize >= nb_polygons)
+        {
+            return Err(MeshDataIdError::InvalidPolygon { polygon: *polygon });
+        }
+        Ok(())
+    }
+}
+
+/// Polygons of `invalid_polygon_ids` are marked as merged, `MeshMerger::remove_unused` drops them.
+///
+/// Ids are expected to be in range, see `ConvexPolygonsMeshData::check_ids`.
+impl From<&ConvexPolygonsMeshData> for MeshMerger {
+    fn from(mesh_data: &ConvexPolygonsMeshData) -> Self {
+        let mut polygon_unions = UnionFind::new(mesh_data.mesh_polygons.len() as i32);
+        for polygon_index in mesh_data.invalid_polygon_ids.iter() {
+            if let Some(parent) = polygon_unions.parent.get_mut(*polygon_index as usize) {
+                *parent = -1;
+            }
+        }
+        MeshMerger {
+            mesh_vertices: mesh_data.mesh_vertices.clone(),
+            mesh_polygons: mesh_data.mesh_polygons.clone(),
+            polygon_unions,
+        }
+    }
+}
+
+impl From<&ConvexPolygonsMeshData> for Svg {
+    fn from(mesh_data: &ConvexPolygonsMeshData) -> Self {
+        Svg::from(&MeshMerger::from(mesh_data))
+    }
+}
+
 impl From<&TriangleMeshData> for ConvexPolygonsMeshData {
     fn from(triangle_mesh_data: &TriangleMeshData) -> Self {
+        let area_types = triangle_mesh_data.0.triangle_area_types();
         let mut convex_polygons_data = ConvexPolygonsMeshData {
             mesh_vertices: triangle_mesh_data
                 .0
@@ -64,6 +169,7 @@ impl From<&TriangleMeshData> for ConvexPolygonsMeshData {
                             .map(|local_index| triangle_mesh_data.0.indices[*local_index as usize])
                             .collect(),
                         polygons: Vec::new(),
+                        area_type: area_types[i as usize],
                     }
                 })
                 .collect(),
@@ -133,11 +239,20 @@ impl IntoPAMesh for ConvexPolygonsMeshData {
         );
         pa_mesh
     }
+
+    fn area_types(&self) -> Vec<AreaType> {
+        self.mesh_polygons
+            .iter()
+            .enumerate()
+            .filter(|(p_index, _)| !self.invalid_polygon_ids.contains(&(*p_index as u32)))
+            .map(|(_, p)| p.area_type)
+            .collect()
+    }
 }
 
 impl UpdateVertex for ConvexPolygonsMeshData {
-    fn update_vertex(&mut self, vertex_index: u32, position: Vec3) {
-        self.mesh_vertices[vertex_index as usize].p = position.xz();
+    fn update_vertex(&mut self, vertex_index: u32, position: Vec2) {
+        self.mesh_vertices[vertex_index as usize].p = position;
     }
 
     fn iter_positions(&self) -> Vec<Vec2> {
@@ -145,10 +260,31 @@ impl UpdateVertex for ConvexPolygonsMeshData {
         // we could pass a function to go through all positions, or leverge Into<Vec2> ?
         self.mesh_vertices.iter().map(|v| v.p).collect()
     }
+
+    fn vertex_position(&self, vertex_index: u32) -> Vec2 {
+        self.mesh_vertices[vertex_index as usize].p
+    }
+
+    fn concave_polygons_around(&self, vertex_index: u32) -> Vec<u32> {
+        self.concave_polygons_at(vertex_index)
+    }
+
+    fn restore_convexity(&mut self, vertex_index: u32) -> bool {
+        self.split_concave_polygons_at(vertex_index)
+    }
+
+    fn edges(&self) -> Vec<[u32; 2]> {
+        ConvexPolygonsMeshData::edges(self)
+    }
+
+    fn weld_vertex(&mut self, vertex_index: u32, into: u32) -> bool {
+        ConvexPolygonsMeshData::weld_vertex(self, vertex_index, into).is_ok()
+    }
 }
 
+#[cfg(feature = "render")]
 impl IntoBevyMesh for ConvexPolygonsMeshData {
-    fn to_bevy_mesh(&self) -> Mesh {
+    fn to_bevy_mesh(&self, mapping: &PlaneMapping) -> Mesh {
         use bevy::render::{mesh::Indices, prelude::*, render_resource::PrimitiveTopology};
 
         let indices_polygons = self
@@ -157,14 +293,19 @@ impl IntoBevyMesh for ConvexPolygonsMeshData {
             .enumerate()
             .filter(|(p_index, p)| self.invalid_polygon_ids.contains(&(*p_index as u32)) == false)
             .map(|(_, p)| {
-                (2..p.vertices.len())
-                    .flat_map(|i| [p.vertices[0], p.vertices[i], p.vertices[i - 1]])
+                (2..p.vertices.len()).flat_map(|i| {
+                    mapping.front_facing([p.vertices[0], p.vertices[i - 1], p.vertices[i]])
+                })
             });
         let positions = indices_polygons.clone().map(|polygon_indices| {
             polygon_indices.map(|vertex_index| self.mesh_vertices[vertex_index as usize].p)
         });
         let nb_polygons = self.mesh_polygons.len() - self.invalid_polygon_ids.len();
         let nb_vertices = indices_polygons.clone().flatten().count();
+        let convex_polygons: Vec<bool> = (0..self.mesh_polygons.len() as u32)
+            .filter(|p_index| !self.invalid_polygon_ids.contains(p_index))
+            .map(|p_index| self.is_convex_polygon(p_index))
+            .collect();
 
         let mut new_mesh = Mesh::new(PrimitiveTopology::TriangleList);
         new_mesh.insert_attribute(
@@ -172,7 +313,7 @@ impl IntoBevyMesh for ConvexPolygonsMeshData {
             positions
                 .clone()
                 .flatten()
-                .map(|p| [p.x, 0.0, p.y])
+                .map(|p| mapping.to_world(p).to_array())
                 .collect::<Vec<[f32; 3]>>(),
         );
         new_mesh.set_indices(Some(Indices::U32(
@@ -180,9 +321,7 @@ impl IntoBevyMesh for ConvexPolygonsMeshData {
         )));
         new_mesh.insert_attribute(
             Mesh::ATTRIBUTE_NORMAL,
-            (0..nb_vertices)
-                .map(|_| [0.0, 1.0, 0.0])
-                .collect::<Vec<[f32; 3]>>(),
+            vec![mapping.normal().to_array(); nb_vertices],
         );
         new_mesh.insert_attribute(
             Mesh::ATTRIBUTE_UV_0,
@@ -196,16 +335,12 @@ impl IntoBevyMesh for ConvexPolygonsMeshData {
             .clone()
             .enumerate()
             .flat_map(|(index, polygon_vertices)| {
-                fn lerp(v0: f32, v1: f32, t: f32) -> f32 {
-                    ((1.0 - t) * v0) + (t * v1)
-                }
-                let saturation = lerp(0.3f32, 1f32, (index as f32 / nb_polygons as f32) % 1f32);
-                let color = Color::hsl(
-                    ((index as f32 / nb_polygons as f32) * 360f32 + index as f32 * 12313f32)
-                        % 360f32,
-                    saturation,
-                    0.5f32,
-                );
+                // Concave polygons break navmesh queries, make them stand out.
+                let color = if convex_polygons[index] {
+                    crate::tools::polygon_color(index, nb_polygons)
+                } else {
+                    Color::RED
+                };
                 let color = [color.r(), color.g(), color.b(), 1f32];
                 (0..polygon_vertices.count()).map(move |_| color)
             })
@@ -214,7 +349,7 @@ impl IntoBevyMesh for ConvexPolygonsMeshData {
         new_mesh
     }
 
-    fn update_mesh(&self, mesh: &mut Mesh) {
+    fn update_mesh(&self, mesh: &mut Mesh, mapping: &PlaneMapping) {
         if let Some(bevy::render::mesh::VertexAttributeValues::Float32x3(ref mut positions)) =
             mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
         {
@@ -226,8 +361,9 @@ impl IntoBevyMesh for ConvexPolygonsMeshData {
                     self.invalid_polygon_ids.contains(&(*p_index as u32)) == false
                 })
                 .map(|(_, p)| {
-                    (2..p.vertices.len())
-                        .flat_map(|i| [p.vertices[0], p.vertices[i], p.vertices[i - 1]])
+                    (2..p.vertices.len()).flat_map(|i| {
+                        mapping.front_facing([p.vertices[0], p.vertices[i - 1], p.vertices[i]])
+                    })
                 });
             let new_positions = indices_polygons.clone().map(|polygon_indices| {
                 polygon_indices.map(|vertex_index| self.mesh_vertices[vertex_index as usize].p)
@@ -235,8 +371,76 @@ impl IntoBevyMesh for ConvexPolygonsMeshData {
             *positions = new_positions
                 .clone()
                 .flatten()
-                .map(|p| [p.x, 0.0, p.y])
+                .map(|p| mapping.to_world(p).to_array())
                 .collect::<Vec<[f32; 3]>>();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_fixtures::load, trianglemerger::MeshMerger};
+
+    use super::{ConvexPolygonsMeshData, MeshDataIdError};
+
+    fn two_quads() -> ConvexPolygonsMeshData {
+        ConvexPolygonsMeshData::from(&load("assets/meshes/two_quads.mesh"))
+    }
+
+    #[test]
+    fn ids_in_range() {
+        assert_eq!(two_quads().check_ids(), Ok(()));
+    }
+
+    #[test]
+    fn ids_out_of_range() {
+        let mut mesh_data = two_quads();
+        mesh_data.invalid_polygon_ids.push(4);
+        assert_eq!(
+            mesh_data.check_ids(),
+            Err(MeshDataIdError::InvalidPolygon { polygon: 4 })
+        );
+        // Still converts, ignoring the unknown polygon.
+        assert_eq!(MeshMerger::from(&mesh_data).mesh_polygons.len(), 4);
+
+        let mut mesh_data = two_quads();
+        mesh_data.mesh_polygons[1].polygons[0] = -2;
+        assert_eq!(
+            mesh_data.check_ids(),
+            Err(MeshDataIdError::PolygonNeighbour {
+                polygon: 1,
+                neighbour: -2
+            })
+        );
+
+        let mut mesh_data = two_quads();
+        mesh_data.mesh_polygons[0].vertices[0] = 100;
+        assert_eq!(
+            mesh_data.check_ids(),
+            Err(MeshDataIdError::PolygonVertex {
+                polygon: 0,
+                vertex: 100
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialization_round_trip() {
+        use crate::{
+            serialization::{from_bincode, from_json, from_ron, to_bincode, to_json, to_ron},
+            trianglemerger::AreaType,
+        };
+
+        let mut mesh_data = two_quads();
+        mesh_data.mesh_polygons[0].area_type = AreaType::GRASS;
+        mesh_data.invalid_polygon_ids.push(1);
+        let ron: ConvexPolygonsMeshData = from_ron(&to_ron(&mesh_data).unwrap()).unwrap();
+        assert_eq!(ron, mesh_data);
+        let json: ConvexPolygonsMeshData = from_json(&to_json(&mesh_data).unwrap()).unwrap();
+        assert_eq!(json, mesh_data);
+        let bincode: ConvexPolygonsMeshData =
+            from_bincode(&to_bincode(&mesh_data).unwrap()).unwrap();
+        assert_eq!(bincode, mesh_data);
+    }
+}
@@ -1,2 +1,51 @@
 pub mod merge_triangles;
 pub mod only_triangles;
+pub mod topology;
+
+use bevy::prelude::*;
+use polyanya::Mesh as PAMesh;
+
+#[cfg(feature = "render")]
+use crate::plane::PlaneMapping;
+use crate::trianglemerger::AreaType;
+
+pub trait IntoPAMesh {
+    fn to_pa_mesh(&self) -> PAMesh;
+    /// Area type of each polygon of `to_pa_mesh()`, empty if all polygons have the default type.
+    fn area_types(&self) -> Vec<AreaType> {
+        Vec::new()
+    }
+}
+pub trait UpdateVertex {
+    /// Moves a vertex, `position` is in the plane of the mesh.
+    fn update_vertex(&mut self, vertex_index: u32, position: Vec2);
+    // FIXME: perf is horrible but I didn't succeed in getting a generic iterator over Vec2 or Vertex.
+    fn iter_positions(&self) -> Vec<Vec2>;
+    fn vertex_position(&self, vertex_index: u32) -> Vec2 {
+        self.iter_positions()[vertex_index as usize]
+    }
+    /// Polygons using that vertex which are not convex and counter clockwise anymore.
+    fn concave_polygons_around(&self, _vertex_index: u32) -> Vec<u32> {
+        Vec::new()
+    }
+    /// Splits polygons using that vertex to make them convex again, returns false if it couldn't.
+    fn restore_convexity(&mut self, _vertex_index: u32) -> bool {
+        false
+    }
+    /// Edges between vertices, used for snapping.
+    fn edges(&self) -> Vec<[u32; 2]> {
+        Vec::new()
+    }
+    /// Merges `vertex_index` into `into`, shifting down next vertices,
+    /// returns false if it couldn't.
+    fn weld_vertex(&mut self, _vertex_index: u32, _into: u32) -> bool {
+        false
+    }
+}
+
+/// Bevy meshes of mesh data, laid out in the world by a `PlaneMapping`.
+#[cfg(feature = "render")]
+pub trait IntoBevyMesh {
+    fn to_bevy_mesh(&self, mapping: &PlaneMapping) -> Mesh;
+    fn update_mesh(&self, mesh: &mut Mesh, mapping: &PlaneMapping);
+}
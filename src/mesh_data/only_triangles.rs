@@ -1,11 +1,11 @@
 use std::i32;
 
-use bevy::{math::Vec3Swizzles, prelude::*, utils::HashMap};
+use bevy::{prelude::*, utils::HashMap};
 
 use crate::{
-    interact_mesh::*,
+    mesh_data::*,
     tools::{self, TriangleMesh},
-    trianglemerger::{MeshMerger, Polygon, Vertex},
+    trianglemerger::{AreaType, MeshMerger, Polygon, Vertex},
 };
 use polyanya as PA;
 use polyanya::Mesh as PAMesh;
@@ -13,31 +13,99 @@ use polyanya::Mesh as PAMesh;
 use super::merge_triangles::ConvexPolygonsMeshData;
 
 /// Meant to be used in correlation with `ShowAndUpdateMesh` and/or `EditableMesh`
-#[derive(Component, Debug, Default)]
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TriangleMeshData(pub TriangleMesh);
 
 impl IntoPAMesh for TriangleMeshData {
     fn to_pa_mesh(&self) -> PAMesh {
         tools::navmesh_from_trimesh(&self.0)
     }
+
+    fn area_types(&self) -> Vec<AreaType> {
+        self.0.triangle_area_types()
+    }
 }
 
 impl UpdateVertex for TriangleMeshData {
-    fn update_vertex(&mut self, vertex_index: u32, position: Vec3) {
-        self.0.positions[vertex_index as usize] = position.xz();
+    fn update_vertex(&mut self, vertex_index: u32, position: Vec2) {
+        self.0.positions[vertex_index as usize] = position;
     }
 
     fn iter_positions(&self) -> Vec<Vec2> {
         self.0.positions.clone()
     }
+
+    fn vertex_position(&self, vertex_index: u32) -> Vec2 {
+        self.0.positions[vertex_index as usize]
+    }
+
+    /// Triangles are always convex, but can be inverted.
+    fn concave_polygons_around(&self, vertex_index: u32) -> Vec<u32> {
+        self.0
+            .indices
+            .chunks(3)
+            .enumerate()
+            .filter(|(_, triangle)| triangle.contains(&vertex_index))
+            .filter(|(_, triangle)| {
+                let [a, b, c] = [0, 1, 2].map(|i| self.0.positions[triangle[i] as usize]);
+                (b - a).perp_dot(c - a) <= 0.0
+            })
+            .map(|(triangle_index, _)| triangle_index as u32)
+            .collect()
+    }
+
+    fn edges(&self) -> Vec<[u32; 2]> {
+        let mut edges: Vec<[u32; 2]> = self
+            .0
+            .indices
+            .chunks(3)
+            .flat_map(|t| [[t[0], t[1]], [t[1], t[2]], [t[2], t[0]]])
+            .map(|[a, b]| [a.min(b), a.max(b)])
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+        edges
+    }
+
+    /// Triangles using both vertices are removed.
+    fn weld_vertex(&mut self, vertex_index: u32, into: u32) -> bool {
+        if vertex_index == into || vertex_index as usize >= self.0.positions.len() {
+            return false;
+        }
+        let area_types = self.0.triangle_area_types();
+        let mut indices = Vec::new();
+        let mut kept_area_types = Vec::new();
+        for (triangle, area_type) in self.0.indices.chunks(3).zip(area_types) {
+            let triangle: Vec<u32> = triangle
+                .iter()
+                .map(|v| if *v == vertex_index { into } else { *v })
+                .map(|v| if v > vertex_index { v - 1 } else { v })
+                .collect();
+            if triangle[0] != triangle[1]
+                && triangle[1] != triangle[2]
+                && triangle[2] != triangle[0]
+            {
+                indices.extend(triangle);
+                kept_area_types.push(area_type);
+            }
+        }
+        self.0.positions.remove(vertex_index as usize);
+        self.0.indices = indices;
+        if !self.0.area_types.is_empty() {
+            self.0.area_types = kept_area_types;
+        }
+        true
+    }
 }
 
+#[cfg(feature = "render")]
 impl IntoBevyMesh for TriangleMeshData {
-    fn to_bevy_mesh(&self) -> Mesh {
-        tools::bevymesh_from_trimesh(&self.0)
+    fn to_bevy_mesh(&self, mapping: &PlaneMapping) -> Mesh {
+        tools::bevymesh_from_trimesh(&self.0, mapping)
     }
 
-    fn update_mesh(&self, mesh: &mut Mesh) {
+    fn update_mesh(&self, mesh: &mut Mesh, mapping: &PlaneMapping) {
         if let Some(bevy::render::mesh::VertexAttributeValues::Float32x3(ref mut positions)) =
             mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION)
         {
@@ -45,10 +113,7 @@ impl IntoBevyMesh for TriangleMeshData {
                 .iter_mut()
                 .enumerate()
                 .for_each(|(index, position)| {
-                    let pos_data = self.0.positions[index];
-                    position[0] = pos_data.x;
-                    position[1] = 0f32;
-                    position[2] = pos_data.y;
+                    *position = mapping.to_world(self.0.positions[index]).to_array();
                 });
         }
     }
@@ -67,6 +132,44 @@ impl From<&ConvexPolygonsMeshData> for TriangleMeshData {
                 .map(|v| v as u32)
                 .collect(),
             positions: convex_polygons.mesh_vertices.iter().map(|v| v.p).collect(),
+            area_types: convex_polygons
+                .mesh_polygons
+                .iter()
+                .flat_map(|p| (2..p.vertices.len()).map(|_| p.area_type))
+                .collect(),
         })
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use bevy::prelude::Vec2;
+
+    use crate::{
+        serialization::{from_bincode, from_json, from_ron, to_bincode, to_json, to_ron},
+        tools::TriangleMesh,
+        trianglemerger::AreaType,
+    };
+
+    use super::TriangleMeshData;
+
+    #[test]
+    fn serialization_round_trip() {
+        let mesh_data = TriangleMeshData(TriangleMesh {
+            indices: vec![0, 1, 2, 0, 2, 3],
+            positions: vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.5, 0.0),
+                Vec2::new(1.5, 1.5),
+                Vec2::new(0.0, 1.5),
+            ],
+            area_types: vec![AreaType::GRASS, AreaType::WATER],
+        });
+        let ron: TriangleMeshData = from_ron(&to_ron(&mesh_data).unwrap()).unwrap();
+        assert_eq!(ron, mesh_data);
+        let json: TriangleMeshData = from_json(&to_json(&mesh_data).unwrap()).unwrap();
+        assert_eq!(json, mesh_data);
+        let bincode: TriangleMeshData = from_bincode(&to_bincode(&mesh_data).unwrap()).unwrap();
+        assert_eq!(bincode, mesh_data);
+    }
+}
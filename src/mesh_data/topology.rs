@@ -0,0 +1,1130 @@
+use bevy::{prelude::*, utils::HashMap};
+
+#[cfg(feature = "editor")]
+use crate::{
+    history::EditHistory, interact_mesh::SelectedVertices, plane::PlaneMapping, screen_to_ground,
+    MainCamera,
+};
+use crate::{
+    tools::{convex_hull, subtract_convex, triangulate_simple_polygon},
+    trianglemerger::{AreaType, MeshMerger, Polygon, Vertex},
+};
+
+use super::merge_triangles::ConvexPolygonsMeshData;
+
+/// Vertices created by `carve` closer than this are welded.
+pub const CARVE_WELD_DISTANCE: f32 = 1e-4;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TopologyError {
+    InvalidVertex(u32),
+    InvalidPolygon(u32),
+    /// No polygon has an edge between the given vertices.
+    NoSuchEdge,
+    TooFewVertices,
+    NotConvex,
+    /// The new polygon would overlap an existing polygon along that edge.
+    EdgeAlreadyUsed([u32; 2]),
+    /// The vertices are not both in the polygon, or are adjacent.
+    NotADiagonal,
+    /// Removing that boundary vertex would make the mesh grow outside of its boundary.
+    ReflexBoundaryVertex,
+    /// The vertex joins several separate fans of polygons.
+    NonManifoldVertex,
+    /// The polygons around the vertex don't share an area type, so the retriangulated hole can't get one.
+    MixedAreaTypes,
+}
+
+/// Topology edits, all keeping neighbour links consistent.
+///
+/// Polygons are counter clockwise, merged polygons from `invalid_polygon_ids` are dropped
+/// by any edit, so polygon indices may change after an edit.
+impl ConvexPolygonsMeshData {
+    pub fn is_valid_polygon(&self, polygon_index: u32) -> bool {
+        (polygon_index as usize) < self.mesh_polygons.len()
+            && !self.invalid_polygon_ids.contains(&polygon_index)
+    }
+
+    /// Adds a vertex not linked to any polygon yet, to be used by `add_polygon`.
+    pub fn add_vertex(&mut self, position: Vec2) -> u32 {
+        self.mesh_vertices.push(Vertex {
+            p: position,
+            polygons: Vec::new(),
+        });
+        self.mesh_vertices.len() as u32 - 1
+    }
+
+    /// Inserts a new vertex on the edge between `a` and `b`, in every polygon having that edge.
+    pub fn insert_vertex_on_edge(
+        &mut self,
+        a: u32,
+        b: u32,
+        position: Vec2,
+    ) -> Result<u32, TopologyError> {
+        self.check_vertex(a)?;
+        self.check_vertex(b)?;
+        let edges: Vec<(usize, usize)> = self
+            .valid_polygons()
+            .filter_map(|(polygon_index, polygon)| {
+                let len = polygon.vertices.len();
+                (0..len)
+                    .find(|i| {
+                        let edge = [polygon.vertices[*i], polygon.vertices[(i + 1) % len]];
+                        edge == [a, b] || edge == [b, a]
+                    })
+                    .map(|i| (polygon_index, i + 1))
+            })
+            .collect();
+        if edges.is_empty() {
+            return Err(TopologyError::NoSuchEdge);
+        }
+        let new_vertex = self.add_vertex(position);
+        for (polygon_index, insert_at) in edges {
+            self.mesh_polygons[polygon_index]
+                .vertices
+                .insert(insert_at, new_vertex);
+        }
+        self.finish_topology_edit();
+        Ok(new_vertex)
+    }
+
+    /// Removes a vertex, retriangulating the hole left by the polygons around it.
+    ///
+    /// The polygons around it must have the same area type, given to the new triangles.
+    /// Vertices after `vertex` are shifted down by one.
+    pub fn delete_vertex(&mut self, vertex: u32) -> Result<(), TopologyError> {
+        self.check_vertex(vertex)?;
+        // For each polygon around `vertex`, the vertices before and after it.
+        let fan: Vec<(usize, u32, u32)> = self
+            .valid_polygons()
+            .filter_map(|(polygon_index, polygon)| {
+                let len = polygon.vertices.len();
+                polygon.vertices.iter().position(|v| *v == vertex).map(|i| {
+                    (
+                        polygon_index,
+                        polygon.vertices[(i + len - 1) % len],
+                        polygon.vertices[(i + 1) % len],
+                    )
+                })
+            })
+            .collect();
+        let mut area_types = fan
+            .iter()
+            .map(|(polygon_index, _, _)| self.mesh_polygons[*polygon_index].area_type);
+        let area_type = area_types.next().unwrap_or_default();
+        if area_types.any(|other| other != area_type) {
+            return Err(TopologyError::MixedAreaTypes);
+        }
+        let ring = Self::fan_ring(&fan)?;
+        if let Some(ring) = &ring {
+            if !ring.closed {
+                let first = self.mesh_vertices[ring.vertices[0] as usize].p;
+                let last = self.mesh_vertices[*ring.vertices.last().unwrap() as usize].p;
+                let p = self.mesh_vertices[vertex as usize].p;
+                if (p - last).perp_dot(first - p) <= 0.0 {
+                    return Err(TopologyError::ReflexBoundaryVertex);
+                }
+            }
+        }
+        for (polygon_index, _, _) in fan.iter() {
+            let polygon = &mut self.mesh_polygons[*polygon_index];
+            polygon.vertices.retain(|v| *v != vertex);
+            if polygon.vertices.len() < 3 {
+                self.invalid_polygon_ids.push(*polygon_index as u32);
+            }
+        }
+        if let Some(ring) = ring {
+            let points: Vec<Vec2> = ring
+                .vertices
+                .iter()
+                .map(|v| self.mesh_vertices[*v as usize].p)
+                .collect();
+            for triangle in triangulate_simple_polygon(&points) {
+                self.mesh_polygons.push(Polygon {
+                    vertices: triangle.iter().map(|i| ring.vertices[*i]).collect(),
+                    area_type,
+                    ..default()
+                });
+            }
+        }
+        self.remove_vertex_index(vertex);
+        self.finish_topology_edit();
+        Ok(())
+    }
+
+    /// Merges `vertex` into `into`, removing polygons collapsing to less than 3 vertices.
+    ///
+    /// A polygon having both vertices is split where it now touches itself,
+    /// and parts which are not convex anymore are partitioned, so slivers don't remain.
+    /// Vertices after `vertex` are shifted down by one.
+    pub fn weld_vertex(&mut self, vertex: u32, into: u32) -> Result<(), TopologyError> {
+        self.check_vertex(vertex)?;
+        self.check_vertex(into)?;
+        if vertex == into {
+            return Ok(());
+        }
+        let welded: Vec<usize> = self
+            .valid_polygons()
+            .filter(|(_, polygon)| polygon.vertices.contains(&vertex))
+            .map(|(polygon_index, _)| polygon_index)
+            .collect();
+        for polygon_index in welded {
+            let vertices: Vec<u32> = self.mesh_polygons[polygon_index]
+                .vertices
+                .iter()
+                .map(|v| if *v == vertex { into } else { *v })
+                .collect();
+            let parts: Vec<Vec<u32>> = split_loops(vertices)
+                .into_iter()
+                .filter(|part| part.len() >= 3)
+                .flat_map(|part| {
+                    if self.is_convex(&part)
+                        && MeshMerger::get_area(&self.mesh_vertices, &part) > 0.0
+                    {
+                        return vec![part];
+                    }
+                    let points: Vec<Vec2> = part
+                        .iter()
+                        .map(|v| self.mesh_vertices[*v as usize].p)
+                        .collect();
+                    convex_partition(&points)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(move |indices| indices.into_iter().map(|i| part[i]).collect())
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            let area_type = self.mesh_polygons[polygon_index].area_type;
+            let mut parts = parts.into_iter();
+            match parts.next() {
+                Some(first) => self.mesh_polygons[polygon_index].vertices = first,
+                None => self.invalid_polygon_ids.push(polygon_index as u32),
+            }
+            for part in parts {
+                self.mesh_polygons.push(Polygon {
+                    vertices: part,
+                    area_type,
+                    ..default()
+                });
+            }
+        }
+        self.remove_vertex_index(vertex);
+        self.finish_topology_edit();
+        Ok(())
+    }
+
+    /// Removes a polygon, leaving a hole in the mesh.
+    pub fn delete_polygon(&mut self, polygon_index: u32) -> Result<(), TopologyError> {
+        if !self.is_valid_polygon(polygon_index) {
+            return Err(TopologyError::InvalidPolygon(polygon_index));
+        }
+        self.invalid_polygon_ids.push(polygon_index);
+        self.finish_topology_edit();
+        Ok(())
+    }
+
+    /// Adds a convex polygon made of existing vertices, returns its index.
+    ///
+    /// Its edges may be attached to boundary edges of the mesh, but not overlap existing polygons.
+    pub fn add_polygon(
+        &mut self,
+        mut vertices: Vec<u32>,
+        area_type: AreaType,
+    ) -> Result<u32, TopologyError> {
+        if vertices.len() < 3 {
+            return Err(TopologyError::TooFewVertices);
+        }
+        for v in vertices.iter() {
+            self.check_vertex(*v)?;
+        }
+        if MeshMerger::get_area(&self.mesh_vertices, &vertices) < 0.0 {
+            vertices.reverse();
+        }
+        if !self.is_convex(&vertices) {
+            return Err(TopologyError::NotConvex);
+        }
+        let used_edges = self.directed_edges();
+        for i in 0..vertices.len() {
+            let edge = [vertices[i], vertices[(i + 1) % vertices.len()]];
+            if used_edges.contains_key(&edge) {
+                return Err(TopologyError::EdgeAlreadyUsed(edge));
+            }
+        }
+        self.mesh_polygons.push(Polygon {
+            vertices,
+            area_type,
+            ..default()
+        });
+        self.finish_topology_edit();
+        Ok(self.mesh_polygons.len() as u32 - 1)
+    }
+
+    /// Splits a polygon along the diagonal between vertices `a` and `b`,
+    /// returns the index of the new polygon.
+    ///
+    /// The other half replaces the split polygon, but like any edit this drops merged polygons,
+    /// so its index shifts down if some of them came before it.
+    pub fn split_polygon(
+        &mut self,
+        polygon_index: u32,
+        a: u32,
+        b: u32,
+    ) -> Result<u32, TopologyError> {
+        if !self.is_valid_polygon(polygon_index) {
+            return Err(TopologyError::InvalidPolygon(polygon_index));
+        }
+        let vertices = &self.mesh_polygons[polygon_index as usize].vertices;
+        let len = vertices.len();
+        let (i, j) = match (
+            vertices.iter().position(|v| *v == a),
+            vertices.iter().position(|v| *v == b),
+        ) {
+            (Some(i), Some(j)) => (i.min(j), i.max(j)),
+            _ => return Err(TopologyError::NotADiagonal),
+        };
+        if j - i < 2 || (i + len) - j < 2 {
+            return Err(TopologyError::NotADiagonal);
+        }
+        let first_half = vertices[i..=j].to_vec();
+        let second_half: Vec<u32> = vertices[j..]
+            .iter()
+            .chain(&vertices[..=i])
+            .copied()
+            .collect();
+        let polygon = &mut self.mesh_polygons[polygon_index as usize];
+        polygon.vertices = first_half;
+        let area_type = polygon.area_type;
+        // Merged polygons are dropped by `finish_topology_edit`, shifting indices.
+        let new_index = self.valid_polygons().count() as u32;
+        self.mesh_polygons.push(Polygon {
+            vertices: second_half,
+            area_type,
+            ..default()
+        });
+        self.finish_topology_edit();
+        Ok(new_index)
+    }
+
+    /// Recomputes polygon neighbours, traversable edges counts, areas and vertex polygons.
+    pub fn rebuild_neighbours(&mut self) {
+        let edges = self.directed_edges();
+        for polygon_index in 0..self.mesh_polygons.len() {
+            let area = MeshMerger::get_area(
+                &self.mesh_vertices,
+                &self.mesh_polygons[polygon_index].vertices,
+            );
+            let polygon = &mut self.mesh_polygons[polygon_index];
+            let len = polygon.vertices.len();
+            // polygons[i] is the neighbour across the edge (vertices[i], vertices[i + 1]).
+            polygon.polygons = (0..len)
+                .map(|i| {
+                    edges
+                        .get(&[polygon.vertices[(i + 1) % len], polygon.vertices[i]])
+                        .map(|p| *p as i32)
+                        .unwrap_or(-1)
+                })
+                .collect();
+            polygon.num_traversable = polygon.polygons.iter().filter(|p| **p != -1).count() as u32;
+            polygon.area = area;
+        }
+        let mut fans: Vec<Vec<(usize, u32, u32)>> = vec![Vec::new(); self.mesh_vertices.len()];
+        for (polygon_index, polygon) in self.valid_polygons() {
+            let len = polygon.vertices.len();
+            for i in 0..len {
+                fans[polygon.vertices[i] as usize].push((
+                    polygon_index,
+                    polygon.vertices[(i + len - 1) % len],
+                    polygon.vertices[(i + 1) % len],
+                ));
+            }
+        }
+        for (vertex, fan) in self.mesh_vertices.iter_mut().zip(fans) {
+            vertex.polygons = Self::fan_polygons(&fan);
+        }
+    }
+
+    /// Whether the polygon is convex and counter clockwise, as polyanya expects.
+    pub fn is_convex_polygon(&self, polygon_index: u32) -> bool {
+        let vertices = &self.mesh_polygons[polygon_index as usize].vertices;
+        self.is_convex(vertices) && MeshMerger::get_area(&self.mesh_vertices, vertices) > 0.0
+    }
+
+    /// Polygons using `vertex` which are not convex or counter clockwise.
+    pub fn concave_polygons_at(&self, vertex: u32) -> Vec<u32> {
+        self.valid_polygons()
+            .filter(|(polygon_index, polygon)| {
+                polygon.vertices.contains(&vertex) && !self.is_convex_polygon(*polygon_index as u32)
+            })
+            .map(|(polygon_index, _)| polygon_index as u32)
+            .collect()
+    }
+
+    /// Splits the concave polygons using `vertex` into convex polygons.
+    ///
+    /// Returns false without changing anything if a polygon is inverted or self intersecting.
+    pub fn split_concave_polygons_at(&mut self, vertex: u32) -> bool {
+        let mut partitions = Vec::new();
+        for polygon_index in self.concave_polygons_at(vertex) {
+            let vertices = &self.mesh_polygons[polygon_index as usize].vertices;
+            let points: Vec<Vec2> = vertices
+                .iter()
+                .map(|v| self.mesh_vertices[*v as usize].p)
+                .collect();
+            match convex_partition(&points) {
+                Some(parts) => partitions.push((
+                    polygon_index,
+                    parts
+                        .into_iter()
+                        .map(|part| part.into_iter().map(|i| vertices[i]).collect::<Vec<u32>>())
+                        .collect::<Vec<_>>(),
+                )),
+                None => return false,
+            }
+        }
+        for (polygon_index, parts) in partitions {
+            let area_type = self.mesh_polygons[polygon_index as usize].area_type;
+            let mut parts = parts.into_iter();
+            if let Some(first) = parts.next() {
+                self.mesh_polygons[polygon_index as usize].vertices = first;
+            }
+            for part in parts {
+                self.mesh_polygons.push(Polygon {
+                    vertices: part,
+                    area_type,
+                    ..default()
+                });
+            }
+        }
+        self.finish_topology_edit();
+        true
+    }
+
+    /// Fixes what `MeshMerger::validate` reports, as far as possible:
+    /// drops polygons with less than 3 vertices, missing vertices, no area or self intersecting,
+    /// reverses clockwise polygons, splits concave ones, removes unused vertices
+    /// and rebuilds neighbours.
+    ///
+    /// Returns the number of fixed polygons and vertices.
+    pub fn repair(&mut self) -> usize {
+        let nb_vertices = self.mesh_vertices.len() as u32;
+        let mut fixes = 0;
+        let mut new_polygons = Vec::new();
+        for polygon_index in 0..self.mesh_polygons.len() as u32 {
+            if !self.is_valid_polygon(polygon_index) {
+                continue;
+            }
+            let mut vertices = self.mesh_polygons[polygon_index as usize].vertices.clone();
+            let area = if vertices.len() >= 3 && vertices.iter().all(|v| *v < nb_vertices) {
+                MeshMerger::get_area(&self.mesh_vertices, &vertices)
+            } else {
+                0.0
+            };
+            if area.abs() <= f32::EPSILON {
+                self.invalid_polygon_ids.push(polygon_index);
+                fixes += 1;
+                continue;
+            }
+            let mut fixed = false;
+            if area < 0.0 {
+                vertices.reverse();
+                fixed = true;
+            }
+            if !self.is_convex(&vertices) {
+                let points: Vec<Vec2> = vertices
+                    .iter()
+                    .map(|v| self.mesh_vertices[*v as usize].p)
+                    .collect();
+                match convex_partition(&points) {
+                    Some(parts) => {
+                        let area_type = self.mesh_polygons[polygon_index as usize].area_type;
+                        let mut parts = parts.into_iter().map(|part| {
+                            part.into_iter().map(|i| vertices[i]).collect::<Vec<u32>>()
+                        });
+                        let first = parts.next().unwrap_or_default();
+                        new_polygons.extend(parts.map(|part| Polygon {
+                            vertices: part,
+                            area_type,
+                            ..default()
+                        }));
+                        vertices = first;
+                    }
+                    None => self.invalid_polygon_ids.push(polygon_index),
+                }
+                fixed = true;
+            }
+            self.mesh_polygons[polygon_index as usize].vertices = vertices;
+            if fixed {
+                fixes += 1;
+            }
+        }
+        self.mesh_polygons.extend(new_polygons);
+        self.finish_topology_edit();
+        fixes += self.remove_unused_vertices();
+        self.rebuild_neighbours();
+        fixes
+    }
+
+    /// Removes the inside of the convex hull of `footprint` from the mesh,
+    /// splitting the polygons it overlaps into convex pieces.
+    ///
+    /// New vertices are also inserted in neighbouring polygons to keep edges shared,
+    /// so the pieces can be merged afterwards. Returns the number of changed polygons.
+    pub fn carve(&mut self, footprint: &[Vec2]) -> usize {
+        let hole = convex_hull(footprint);
+        if hole.len() < 3 {
+            return 0;
+        }
+        self.finish_topology_edit();
+        let first_new_vertex = self.mesh_vertices.len() as u32;
+        let mut changed = 0;
+        let mut polygons = Vec::new();
+        for polygon in std::mem::take(&mut self.mesh_polygons) {
+            let points: Vec<Vec2> = polygon
+                .vertices
+                .iter()
+                .map(|v| self.mesh_vertices[*v as usize].p)
+                .collect();
+            let pieces = subtract_convex(&points, &hole);
+            if pieces.len() == 1 && pieces[0] == points {
+                polygons.push(polygon);
+                continue;
+            }
+            changed += 1;
+            for piece in pieces {
+                let mut vertices: Vec<u32> = piece
+                    .iter()
+                    .map(|p| match points.iter().position(|point| point == p) {
+                        Some(i) => polygon.vertices[i],
+                        None => self.weld_new_vertex(*p, first_new_vertex),
+                    })
+                    .collect();
+                vertices.dedup();
+                if vertices.len() > 1 && vertices.first() == vertices.last() {
+                    vertices.pop();
+                }
+                if vertices.len() >= 3 {
+                    polygons.push(Polygon {
+                        vertices,
+                        area_type: polygon.area_type,
+                        ..default()
+                    });
+                }
+            }
+        }
+        // Pieces of a polygon may cut an edge shared with a polygon the hole doesn't overlap.
+        let new_vertices: Vec<u32> = (first_new_vertex..self.mesh_vertices.len() as u32).collect();
+        for polygon in polygons.iter_mut() {
+            let mut vertices = Vec::with_capacity(polygon.vertices.len());
+            let len = polygon.vertices.len();
+            for i in 0..len {
+                let (a, b) = (polygon.vertices[i], polygon.vertices[(i + 1) % len]);
+                let (pa, pb) = (
+                    self.mesh_vertices[a as usize].p,
+                    self.mesh_vertices[b as usize].p,
+                );
+                vertices.push(a);
+                let mut on_edge: Vec<(f32, u32)> = new_vertices
+                    .iter()
+                    .filter(|v| **v != a && **v != b)
+                    .filter_map(|v| {
+                        let p = self.mesh_vertices[*v as usize].p;
+                        let t = (p - pa).dot(pb - pa) / (pb - pa).length_squared();
+                        (t > 0.0
+                            && t < 1.0
+                            && (pb - pa).perp_dot(p - pa).abs()
+                                <= CARVE_WELD_DISTANCE * (pb - pa).length())
+                        .then_some((t, *v))
+                    })
+                    .collect();
+                on_edge.sort_by(|x, y| x.0.total_cmp(&y.0));
+                vertices.extend(on_edge.into_iter().map(|(_, v)| v));
+            }
+            polygon.vertices = vertices;
+        }
+        self.mesh_polygons = polygons;
+        self.remove_unused_vertices();
+        self.rebuild_neighbours();
+        changed
+    }
+
+    /// Index of the vertex added since `first_new_vertex` at `position`, adding it if needed.
+    fn weld_new_vertex(&mut self, position: Vec2, first_new_vertex: u32) -> u32 {
+        let existing = (first_new_vertex..self.mesh_vertices.len() as u32)
+            .find(|v| self.mesh_vertices[*v as usize].p.distance(position) <= CARVE_WELD_DISTANCE);
+        existing.unwrap_or_else(|| self.add_vertex(position))
+    }
+
+    /// Removes vertices no polygon uses, returns how many were removed.
+    fn remove_unused_vertices(&mut self) -> usize {
+        let mut used_vertices = vec![false; self.mesh_vertices.len()];
+        for polygon in self.mesh_polygons.iter() {
+            for vertex in polygon.vertices.iter() {
+                used_vertices[*vertex as usize] = true;
+            }
+        }
+        let mut removed = 0;
+        for (vertex, used) in used_vertices.into_iter().enumerate().rev() {
+            if !used {
+                self.remove_vertex_index(vertex as u32);
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Removes a vertex no polygon uses anymore, shifting down next vertices.
+    fn remove_vertex_index(&mut self, vertex: u32) {
+        self.mesh_vertices.remove(vertex as usize);
+        for polygon in self.mesh_polygons.iter_mut() {
+            for v in polygon.vertices.iter_mut() {
+                if *v > vertex {
+                    *v -= 1;
+                }
+            }
+        }
+    }
+
+    /// Edges of valid polygons, each edge once.
+    pub fn edges(&self) -> Vec<[u32; 2]> {
+        let directed_edges = self.directed_edges();
+        directed_edges
+            .keys()
+            .filter(|[a, b]| a < b || !directed_edges.contains_key(&[*b, *a]))
+            .copied()
+            .collect()
+    }
+
+    fn check_vertex(&self, vertex: u32) -> Result<(), TopologyError> {
+        if (vertex as usize) < self.mesh_vertices.len() {
+            Ok(())
+        } else {
+            Err(TopologyError::InvalidVertex(vertex))
+        }
+    }
+
+    fn valid_polygons(&self) -> impl Iterator<Item = (usize, &Polygon)> {
+        self.mesh_polygons
+            .iter()
+            .enumerate()
+            .filter(|(polygon_index, _)| {
+                !self.invalid_polygon_ids.contains(&(*polygon_index as u32))
+            })
+    }
+
+    /// Polygon owning each directed edge.
+    fn directed_edges(&self) -> HashMap<[u32; 2], usize> {
+        let mut edges = HashMap::new();
+        for (polygon_index, polygon) in self.valid_polygons() {
+            let len = polygon.vertices.len();
+            for i in 0..len {
+                edges.insert(
+                    [polygon.vertices[i], polygon.vertices[(i + 1) % len]],
+                    polygon_index,
+                );
+            }
+        }
+        edges
+    }
+
+    pub(crate) fn is_convex(&self, vertices: &[u32]) -> bool {
+        let len = vertices.len();
+        (0..len).all(|i| {
+            let a = self.mesh_vertices[vertices[i] as usize].p;
+            let b = self.mesh_vertices[vertices[(i + 1) % len] as usize].p;
+            let c = self.mesh_vertices[vertices[(i + 2) % len] as usize].p;
+            (b - a).perp_dot(c - b) >= 0.0
+        })
+    }
+
+    /// Vertices around a vertex, counter clockwise, from the polygons of its fan.
+    fn fan_ring(fan: &[(usize, u32, u32)]) -> Result<Option<FanRing>, TopologyError> {
+        if fan.is_empty() {
+            return Ok(None);
+        }
+        // In a counter clockwise polygon, the vertex after ours comes first around our vertex.
+        let next: HashMap<u32, u32> = fan
+            .iter()
+            .map(|(_, before, after)| (*after, *before))
+            .collect();
+        let start = fan
+            .iter()
+            .map(|(_, _, after)| *after)
+            .find(|after| fan.iter().all(|(_, before, _)| before != after));
+        let closed = start.is_none();
+        let start = start.unwrap_or(fan[0].2);
+        let mut vertices = vec![start];
+        let mut current = start;
+        while let Some(following) = next.get(&current) {
+            if *following == start {
+                break;
+            }
+            vertices.push(*following);
+            current = *following;
+        }
+        if vertices.len() != fan.len() + if closed { 0 } else { 1 } {
+            return Err(TopologyError::NonManifoldVertex);
+        }
+        Ok(Some(FanRing { vertices, closed }))
+    }
+
+    /// Polygons around a vertex in counter clockwise order, `-1` marking the boundary.
+    fn fan_polygons(fan: &[(usize, u32, u32)]) -> Vec<i32> {
+        let mut polygons = Vec::new();
+        let mut remaining = fan.to_vec();
+        while !remaining.is_empty() {
+            // Start from a polygon with no neighbour before it, if any.
+            let start = (0..remaining.len())
+                .find(|i| {
+                    remaining
+                        .iter()
+                        .all(|(_, before, _)| *before != remaining[*i].2)
+                })
+                .unwrap_or(0);
+            let is_open = remaining
+                .iter()
+                .all(|(_, before, _)| *before != remaining[start].2);
+            let (mut polygon, mut before, _) = remaining.remove(start);
+            loop {
+                polygons.push(polygon as i32);
+                match remaining.iter().position(|(_, _, after)| *after == before) {
+                    Some(i) => {
+                        let (next_polygon, next_before, _) = remaining.remove(i);
+                        polygon = next_polygon;
+                        before = next_before;
+                    }
+                    None => break,
+                }
+            }
+            if is_open {
+                polygons.push(-1);
+            }
+        }
+        polygons
+    }
+
+    fn finish_topology_edit(&mut self) {
+        let mut invalid_polygon_ids = std::mem::take(&mut self.invalid_polygon_ids);
+        invalid_polygon_ids.sort_unstable();
+        invalid_polygon_ids.dedup();
+        for polygon_index in invalid_polygon_ids.into_iter().rev() {
+            self.mesh_polygons.remove(polygon_index as usize);
+        }
+        self.rebuild_neighbours();
+    }
+}
+
+/// Splits a polygon touching itself, at each repeated vertex, into simple loops.
+///
+/// Consecutive repeated vertices are merged, loops may have less than 3 vertices.
+fn split_loops(mut vertices: Vec<u32>) -> Vec<Vec<u32>> {
+    vertices.dedup();
+    while vertices.len() > 1 && vertices.first() == vertices.last() {
+        vertices.pop();
+    }
+    for i in 0..vertices.len() {
+        if let Some(j) = (i + 1..vertices.len()).find(|j| vertices[*j] == vertices[i]) {
+            let inner = vertices[i..j].to_vec();
+            let outer = vertices[j..]
+                .iter()
+                .chain(&vertices[..i])
+                .copied()
+                .collect();
+            let mut loops = split_loops(inner);
+            loops.extend(split_loops(outer));
+            return loops;
+        }
+    }
+    vec![vertices]
+}
+
+struct FanRing {
+    vertices: Vec<u32>,
+    /// False if the vertex is on the boundary.
+    closed: bool,
+}
+
+/// Splits a simple polygon into convex counter clockwise parts, indexing into `points`.
+///
+/// Triangulates it, then merges back triangles while the result stays convex (Hertel-Mehlhorn).
+/// Returns None if the polygon is clockwise or self intersecting.
+fn convex_partition(points: &[Vec2]) -> Option<Vec<Vec<usize>>> {
+    let double_area: f32 = (0..points.len())
+        .map(|i| points[i].perp_dot(points[(i + 1) % points.len()]))
+        .sum();
+    if double_area <= 0.0 {
+        return None;
+    }
+    let triangles = triangulate_simple_polygon(points);
+    let triangles_double_area: f32 = triangles
+        .iter()
+        .map(|[a, b, c]| {
+            (points[*b] - points[*a])
+                .perp_dot(points[*c] - points[*a])
+                .abs()
+        })
+        .sum();
+    if (triangles_double_area - double_area).abs() > double_area * 1e-3 {
+        return None;
+    }
+    let is_convex = |part: &[usize]| {
+        (0..part.len()).all(|i| {
+            let a = points[part[i]];
+            let b = points[part[(i + 1) % part.len()]];
+            let c = points[part[(i + 2) % part.len()]];
+            (b - a).perp_dot(c - b) >= 0.0
+        })
+    };
+    let mut parts: Vec<Vec<usize>> = triangles.into_iter().map(|t| t.to_vec()).collect();
+    let mut merged = true;
+    while merged {
+        merged = false;
+        'search: for i in 0..parts.len() {
+            for j in i + 1..parts.len() {
+                if let Some(union) = merge_parts(&parts[i], &parts[j]) {
+                    if is_convex(&union) {
+                        parts[i] = union;
+                        parts.remove(j);
+                        merged = true;
+                        break 'search;
+                    }
+                }
+            }
+        }
+    }
+    Some(parts)
+}
+
+/// Union of 2 counter clockwise polygons sharing an edge.
+fn merge_parts(a: &[usize], b: &[usize]) -> Option<Vec<usize>> {
+    for i in 0..a.len() {
+        let (p, q) = (a[i], a[(i + 1) % a.len()]);
+        if let Some(j) = b.iter().position(|v| *v == q) {
+            if b[(j + 1) % b.len()] == p {
+                // `a` from `q` around to `p`, then the rest of `b`.
+                let mut union: Vec<usize> =
+                    (0..a.len()).map(|k| a[(i + 1 + k) % a.len()]).collect();
+                union.extend((2..b.len()).map(|k| b[(j + k) % b.len()]));
+                return Some(union);
+            }
+        }
+    }
+    None
+}
+
+/// Editor actions on selected vertices of an `EditableMesh` with `ConvexPolygonsMeshData`:
+///
+/// - `V`: add a vertex under the cursor.
+/// - `I`: insert a vertex in the middle of the edge between 2 selected vertices.
+/// - `X`: delete selected vertices.
+/// - `Shift+X`: delete polygons whose vertices are all selected.
+/// - `P`: add a polygon made of the selected vertices.
+/// - `K`: split a polygon along the diagonal between 2 selected vertices.
+///
+/// Edits are recorded in the `EditHistory` if there is one.
+#[cfg(feature = "editor")]
+pub struct TopologyEditPlugin;
+
+#[cfg(feature = "editor")]
+impl Plugin for TopologyEditPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlaneMapping>()
+            .add_system(edit_topology);
+    }
+}
+
+#[cfg(feature = "editor")]
+fn edit_topology(
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    mapping: Res<PlaneMapping>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut q_meshes: Query<(
+        &SelectedVertices,
+        &mut ConvexPolygonsMeshData,
+        Option<&mut EditHistory<ConvexPolygonsMeshData>>,
+        Option<&GlobalTransform>,
+    )>,
+) {
+    let shift = keys.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+    let action: fn(
+        &mut ConvexPolygonsMeshData,
+        Vec<u32>,
+        Option<Vec2>,
+    ) -> Result<(), TopologyError> = if keys.just_pressed(KeyCode::V) {
+        |mesh_data, _, cursor| {
+            if let Some(cursor) = cursor {
+                mesh_data.add_vertex(cursor);
+            }
+            Ok(())
+        }
+    } else if keys.just_pressed(KeyCode::I) {
+        |mesh_data, selected, _| match selected[..] {
+            [a, b] => {
+                let middle = (mesh_data.mesh_vertices[a as usize].p
+                    + mesh_data.mesh_vertices[b as usize].p)
+                    / 2.0;
+                mesh_data.insert_vertex_on_edge(a, b, middle).map(|_| ())
+            }
+            _ => Err(TopologyError::NoSuchEdge),
+        }
+    } else if keys.just_pressed(KeyCode::X) && shift {
+        |mesh_data, selected, _| {
+            let polygons: Vec<u32> = mesh_data
+                .valid_polygons()
+                .filter(|(_, polygon)| polygon.vertices.iter().all(|v| selected.contains(v)))
+                .map(|(polygon_index, _)| polygon_index as u32)
+                .collect();
+            mesh_data.invalid_polygon_ids.extend(polygons);
+            mesh_data.finish_topology_edit();
+            Ok(())
+        }
+    } else if keys.just_pressed(KeyCode::X) {
+        |mesh_data, mut selected, _| {
+            // Deleting shifts next vertices, so delete from the last one.
+            selected.sort_unstable();
+            for vertex in selected.into_iter().rev() {
+                mesh_data.delete_vertex(vertex)?;
+            }
+            Ok(())
+        }
+    } else if keys.just_pressed(KeyCode::P) {
+        |mesh_data, mut selected, _| {
+            let positions = |v: &u32| mesh_data.mesh_vertices[*v as usize].p;
+            let center =
+                selected.iter().map(positions).sum::<Vec2>() / selected.len().max(1) as f32;
+            selected.sort_by(|a, b| {
+                let angle = |v: &u32| {
+                    let d = positions(v) - center;
+                    d.y.atan2(d.x)
+                };
+                angle(a).partial_cmp(&angle(b)).unwrap()
+            });
+            mesh_data
+                .add_polygon(selected, AreaType::default())
+                .map(|_| ())
+        }
+    } else if keys.just_pressed(KeyCode::K) {
+        |mesh_data, selected, _| match selected[..] {
+            [a, b] => {
+                let polygon_index = mesh_data
+                    .valid_polygons()
+                    .find(|(_, polygon)| {
+                        polygon.vertices.contains(&a) && polygon.vertices.contains(&b)
+                    })
+                    .map(|(polygon_index, _)| polygon_index as u32)
+                    .ok_or(TopologyError::NotADiagonal)?;
+                mesh_data.split_polygon(polygon_index, a, b).map(|_| ())
+            }
+            _ => Err(TopologyError::NotADiagonal),
+        }
+    } else {
+        return;
+    };
+    for (selected, mut mesh_data, history, mesh_transform) in q_meshes.iter_mut() {
+        let cursor = cursor_on_ground(&windows, &mapping, mesh_transform, &q_camera);
+        let selected = selected.vertices.clone();
+        let mut edited = mesh_data.clone();
+        match action(&mut edited, selected, cursor) {
+            Ok(()) => match history {
+                Some(mut history) => history.apply_edit(&mut mesh_data, |data| *data = edited),
+                None => *mesh_data = edited,
+            },
+            Err(error) => warn!("topology edit failed: {error:?}"),
+        }
+    }
+}
+
+/// Intersection of the mouse ray with the navmesh plane, of a mesh placed by `mesh_transform`.
+#[cfg(feature = "editor")]
+fn cursor_on_ground(
+    windows: &Windows,
+    mapping: &PlaneMapping,
+    mesh_transform: Option<&GlobalTransform>,
+    q_camera: &Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) -> Option<Vec2> {
+    let window = windows.get_primary()?;
+    let (camera, camera_transform) = q_camera.iter().next()?;
+    screen_to_ground(
+        window,
+        camera,
+        camera_transform,
+        mapping,
+        mesh_transform,
+        window.cursor_position()?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec2;
+
+    use crate::{
+        mesh_data::{merge_triangles::ConvexPolygonsMeshData, only_triangles::TriangleMeshData},
+        test_fixtures::load,
+        tools::create_grid_trimesh,
+        trianglemerger::{AreaType, MeshMerger, Polygon},
+    };
+
+    use super::{split_loops, TopologyError};
+
+    #[test]
+    fn insert_vertex_on_shared_edge() {
+        let mut mesh_data = ConvexPolygonsMeshData::from(&load("assets/meshes/quad.mesh"));
+        let new_vertex = mesh_data
+            .insert_vertex_on_edge(1, 3, Vec2::splat(0.75))
+            .unwrap();
+        assert_eq!(new_vertex, 4);
+        assert!(mesh_data
+            .mesh_polygons
+            .iter()
+            .all(|p| p.vertices.len() == 4 && p.vertices.contains(&4)));
+        assert!(mesh_data
+            .mesh_polygons
+            .iter()
+            .all(|p| p.num_traversable == 2));
+    }
+
+    #[test]
+    fn split_then_delete() {
+        let mut mesh_data = ConvexPolygonsMeshData::from(&load("assets/meshes/quad.mesh"));
+        mesh_data.delete_polygon(1).unwrap();
+        assert_eq!(mesh_data.mesh_polygons.len(), 1);
+        assert_eq!(mesh_data.mesh_polygons[0].num_traversable, 0);
+        assert_eq!(
+            mesh_data.split_polygon(0, 0, 1),
+            Err(TopologyError::NotADiagonal)
+        );
+        let polygon = mesh_data
+            .add_polygon(vec![1, 2, 3], AreaType::GRASS)
+            .unwrap();
+        assert_eq!(
+            mesh_data.mesh_polygons[polygon as usize].area_type,
+            AreaType::GRASS
+        );
+        assert_eq!(mesh_data.mesh_polygons[0].num_traversable, 1);
+        assert_eq!(
+            mesh_data.add_polygon(vec![1, 2, 3], AreaType::GRASS),
+            Err(TopologyError::EdgeAlreadyUsed([1, 2]))
+        );
+    }
+
+    #[test]
+    fn delete_boundary_vertex() {
+        let mut mesh_data = ConvexPolygonsMeshData::from(&load("assets/meshes/quad.mesh"));
+        mesh_data.delete_vertex(0).unwrap();
+        assert_eq!(mesh_data.mesh_vertices.len(), 3);
+        let total_area: f32 = mesh_data.mesh_polygons.iter().map(|p| p.area).sum();
+        assert_eq!(total_area / 2.0, 1.125);
+    }
+
+    #[test]
+    fn delete_vertex_between_area_types() {
+        let mut mesh_data = ConvexPolygonsMeshData::from(&load("assets/meshes/quad.mesh"));
+        mesh_data.mesh_polygons[1].area_type = AreaType::GRASS;
+        let before = mesh_data.clone();
+        assert_eq!(
+            mesh_data.delete_vertex(1),
+            Err(TopologyError::MixedAreaTypes)
+        );
+        assert_eq!(mesh_data.mesh_polygons, before.mesh_polygons);
+    }
+
+    #[test]
+    fn split_dragged_concave_polygon() {
+        let mut mesh_merger = load("assets/meshes/quad.mesh");
+        mesh_merger.my_merge();
+        mesh_merger.remove_unused();
+        let mut mesh_data = ConvexPolygonsMeshData::from(&mesh_merger);
+        assert_eq!(mesh_data.mesh_polygons.len(), 1);
+        mesh_data.mesh_vertices[2].p = Vec2::new(0.5, 0.5);
+        assert_eq!(mesh_data.concave_polygons_at(2), vec![0]);
+        assert!(mesh_data.split_concave_polygons_at(2));
+        assert_eq!(mesh_data.mesh_polygons.len(), 2);
+        assert!(mesh_data.concave_polygons_at(2).is_empty());
+        // An inverted polygon can't be split.
+        mesh_data.mesh_vertices[2].p = Vec2::new(-1.0, -1.0);
+        assert!(!mesh_data.split_concave_polygons_at(2));
+    }
+
+    #[test]
+    fn repair_concave_and_degenerate() {
+        let mut mesh_merger = load("assets/meshes/quad.mesh");
+        mesh_merger.my_merge();
+        mesh_merger.remove_unused();
+        let mut mesh_data = ConvexPolygonsMeshData::from(&mesh_merger);
+        mesh_data.mesh_vertices[2].p = Vec2::new(0.5, 0.5);
+        mesh_data.add_vertex(Vec2::new(5.0, 5.0));
+        mesh_data.mesh_polygons.push(Polygon {
+            vertices: vec![0, 1],
+            ..Default::default()
+        });
+        assert_eq!(mesh_data.repair(), 3);
+        assert_eq!(mesh_data.mesh_polygons.len(), 2);
+        assert_eq!(mesh_data.mesh_vertices.len(), 4);
+        assert_eq!(MeshMerger::from(&mesh_data).validate(), vec![]);
+    }
+
+    #[test]
+    fn weld_collapses_triangle() {
+        let mut mesh_data = ConvexPolygonsMeshData::from(&load("assets/meshes/quad.mesh"));
+        assert_eq!(mesh_data.edges().len(), 5);
+        mesh_data.weld_vertex(3, 1).unwrap();
+        assert_eq!(mesh_data.mesh_vertices.len(), 3);
+        assert!(mesh_data.mesh_polygons.is_empty());
+    }
+
+    #[test]
+    fn weld_opposite_corners() {
+        let mut mesh_data = ConvexPolygonsMeshData::default();
+        for p in [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0), (3.0, 1.0)] {
+            mesh_data.add_vertex(Vec2::new(p.0, p.1));
+        }
+        mesh_data
+            .add_polygon(vec![0, 1, 2, 3], AreaType::default())
+            .unwrap();
+        mesh_data
+            .add_polygon(vec![1, 4, 2], AreaType::GRASS)
+            .unwrap();
+        mesh_data.weld_vertex(2, 0).unwrap();
+        // The quad collapses into two edges, the triangle now uses vertex 0.
+        assert_eq!(mesh_data.mesh_polygons.len(), 1);
+        let triangle = &mesh_data.mesh_polygons[0];
+        assert_eq!(triangle.vertices, vec![1, 3, 0]);
+        assert_eq!(triangle.area_type, AreaType::GRASS);
+        assert_eq!(triangle.num_traversable, 0);
+        assert_eq!(MeshMerger::from(&mesh_data).validate(), vec![]);
+    }
+
+    #[test]
+    fn loops_split_at_repeated_vertices() {
+        assert_eq!(split_loops(vec![0, 1, 0, 3]), vec![vec![0, 1], vec![0, 3]]);
+        assert_eq!(
+            split_loops(vec![0, 1, 2, 0, 3, 4, 0]),
+            vec![vec![0, 1, 2], vec![0, 3, 4]]
+        );
+    }
+
+    #[test]
+    fn carve_hole_in_grid() {
+        let triangles = TriangleMeshData(create_grid_trimesh(5, 5, 1f32));
+        let mut mesh_data = ConvexPolygonsMeshData::from(&triangles);
+        let hole = [
+            Vec2::new(1.5, 1.5),
+            Vec2::new(2.5, 1.5),
+            Vec2::new(2.5, 2.5),
+            Vec2::new(1.5, 2.5),
+        ];
+        assert!(mesh_data.carve(&hole) > 0);
+        let area: f32 = mesh_data
+            .mesh_polygons
+            .iter()
+            .map(|p| MeshMerger::get_area(&mesh_data.mesh_vertices, &p.vertices) / 2.0)
+            .sum();
+        assert!((area - 15.0).abs() < 1e-4);
+        let mut mesh_merger = MeshMerger::from(&mesh_data);
+        assert_eq!(mesh_merger.validate(), vec![]);
+        mesh_merger.my_merge();
+        mesh_merger.remove_unused();
+        assert_eq!(mesh_merger.validate(), vec![]);
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+
+use glam::Vec2;
+use polyanya::Mesh as PAMesh;
+
+use crate::islands::polygon_area;
+
+/// Differences between two meshes, typically before and after merging.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshDiff {
+    pub polygon_counts: (usize, usize),
+    pub vertex_counts: (usize, usize),
+    pub areas: (f32, f32),
+    /// Side of the square cells used to sample coverage.
+    pub cell_size: f32,
+    /// Centers of cells walkable in the first mesh but not the second.
+    pub only_in_a: Vec<Vec2>,
+    /// Centers of cells walkable in the second mesh but not the first.
+    pub only_in_b: Vec<Vec2>,
+}
+
+impl MeshDiff {
+    /// Compares `a` and `b`, sampling their coverage on a grid of `cell_size` cells.
+    ///
+    /// Coverage mismatches smaller than a cell may be missed.
+    pub fn new(a: &PAMesh, b: &PAMesh, cell_size: f32) -> Self {
+        let mut only_in_a = Vec::new();
+        let mut only_in_b = Vec::new();
+        if let Some((min, max)) = bounds(a)
+            .into_iter()
+            .chain(bounds(b))
+            .reduce(|(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)))
+        {
+            let cells = ((max - min) / cell_size).ceil();
+            for y in 0..cells.y as u32 {
+                for x in 0..cells.x as u32 {
+                    let center = min + (Vec2::new(x as f32, y as f32) + 0.5) * cell_size;
+                    match (a.point_in_mesh(center), b.point_in_mesh(center)) {
+                        (true, false) => only_in_a.push(center),
+                        (false, true) => only_in_b.push(center),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        MeshDiff {
+            polygon_counts: (a.polygons.len(), b.polygons.len()),
+            vertex_counts: (a.vertices.len(), b.vertices.len()),
+            areas: (area(a), area(b)),
+            cell_size,
+            only_in_a,
+            only_in_b,
+        }
+    }
+
+    /// Estimated area of the coverage mismatches, in both directions.
+    pub fn mismatch_areas(&self) -> (f32, f32) {
+        let cell_area = self.cell_size * self.cell_size;
+        (
+            self.only_in_a.len() as f32 * cell_area,
+            self.only_in_b.len() as f32 * cell_area,
+        )
+    }
+
+    pub fn same_coverage(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty()
+    }
+}
+
+impl fmt::Display for MeshDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mismatch_a, mismatch_b) = self.mismatch_areas();
+        writeln!(
+            f,
+            "polygons: {} -> {} ({:+})",
+            self.polygon_counts.0,
+            self.polygon_counts.1,
+            self.polygon_counts.1 as i64 - self.polygon_counts.0 as i64
+        )?;
+        writeln!(
+            f,
+            "vertices: {} -> {} ({:+})",
+            self.vertex_counts.0,
+            self.vertex_counts.1,
+            self.vertex_counts.1 as i64 - self.vertex_counts.0 as i64
+        )?;
+        writeln!(
+            f,
+            "area: {} -> {} ({:+})",
+            self.areas.0,
+            self.areas.1,
+            self.areas.1 - self.areas.0
+        )?;
+        write!(
+            f,
+            "coverage mismatch (cell size {}): {} cells (~{mismatch_a}) only in first, {} cells (~{mismatch_b}) only in second",
+            self.cell_size,
+            self.only_in_a.len(),
+            self.only_in_b.len(),
+        )
+    }
+}
+
+fn bounds(navmesh: &PAMesh) -> Option<(Vec2, Vec2)> {
+    navmesh
+        .vertices
+        .iter()
+        .map(|v| (v.coords, v.coords))
+        .reduce(|(min, max), (p, _)| (min.min(p), max.max(p)))
+}
+
+fn area(navmesh: &PAMesh) -> f32 {
+    navmesh
+        .polygons
+        .iter()
+        .map(|polygon| {
+            polygon_area(
+                &polygon
+                    .vertices
+                    .iter()
+                    .map(|v| navmesh.vertices[*v as usize].coords)
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use polyanya::Mesh as PAMesh;
+
+    use crate::{
+        mesh_data::{merge_triangles::ConvexPolygonsMeshData, IntoPAMesh},
+        test_fixtures::load,
+        trianglemerger::MeshMerger,
+    };
+
+    use super::MeshDiff;
+
+    fn to_pa_mesh(mesh_merger: &MeshMerger) -> PAMesh {
+        ConvexPolygonsMeshData::from(mesh_merger).to_pa_mesh()
+    }
+
+    #[test]
+    fn diff_merged_quad() {
+        let mut mesh_merger = load("assets/meshes/quad.mesh");
+        let quad = to_pa_mesh(&mesh_merger);
+        mesh_merger.my_merge();
+        mesh_merger.remove_unused();
+        let merged = to_pa_mesh(&mesh_merger);
+        let diff = MeshDiff::new(&quad, &merged, 0.1);
+        assert_eq!(diff.polygon_counts, (2, 1));
+        assert_eq!(diff.areas, (2.25, 2.25));
+        assert!(diff.same_coverage());
+    }
+
+    #[test]
+    fn diff_coverage() {
+        let quad = to_pa_mesh(&load("assets/meshes/quad.mesh"));
+        let two_quads = to_pa_mesh(&load("assets/meshes/two_quads.mesh"));
+        let diff = MeshDiff::new(&quad, &two_quads, 0.1);
+        assert!(diff.only_in_a.is_empty());
+        assert_eq!(diff.only_in_b.len(), 100);
+    }
+}
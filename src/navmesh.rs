@@ -1,81 +1,909 @@
-use bevy::pbr::wireframe::WireframePlugin;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+};
+
 use bevy::prelude::*;
-use bevy::render::mesh::Indices;
-use bevy::render::render_resource::PrimitiveTopology;
-use bevy::render::settings::{WgpuFeatures, WgpuSettings};
-use polyanya::Mesh as PAMesh;
+use bevy::utils::HashMap;
+use polyanya::{Mesh as PAMesh, Path};
 
-use crate::tools;
+use crate::{
+    binary::{BinaryMesh, SpatialIndex},
+    islands::Islands,
+    offmesh::{LinkedPath, OffMeshLink, OffMeshLinks, PathSegment},
+    plane::PlaneMapping,
+    tools,
+    trianglemerger::AreaType,
+};
 
+/// Core of navmeshes, free of rendering so it can run in a headless app.
+///
+/// Answers `PathRequest`s.
 pub struct NavMeshPlugin;
 
 impl Plugin for NavMeshPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(WgpuSettings {
-            features: WgpuFeatures::POLYGON_MODE_LINE,
-            ..default()
-        })
-        .add_startup_system(setup_navmesh_graphics); //.add_system(update_mesh_visualization);
+        app.init_resource::<PlaneMapping>()
+            .add_system(answer_path_requests);
     }
 }
 
 #[derive(Component)]
 pub struct NavMesh {
     pub navmesh: PAMesh,
+    /// Connected components of `navmesh`, used to reject unreachable queries early.
+    pub islands: Islands,
+    /// Area type of each polygon of `navmesh`.
+    pub area_types: Vec<AreaType>,
+    /// Speeds up `polygon_at`, polygons are searched linearly without it.
+    pub spatial_index: Option<SpatialIndex>,
+}
+
+/// The `NavMesh` entity used by an agent or a `PathRequest`.
+///
+/// An app can have a navmesh per agent size, per floor or per layer,
+/// each entity referencing the one it walks on.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NavMeshRef(pub Entity);
+
+/// Path between two world positions, on the navmesh of the `NavMeshRef` of the same entity.
+///
+/// `NavMeshPlugin` answers it with a `PathResult` on the same entity,
+/// taking the `OffMeshLinks` and `GlobalTransform` of the navmesh entity into account,
+/// and the `AreaCosts` of the requesting entity if it has some.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct PathRequest {
+    pub from: Vec3,
+    pub to: Vec3,
+}
+
+/// Answer to the `PathRequest` of the same entity.
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub struct PathResult {
+    /// World positions of the path, `None` if there is no path.
+    pub points: Option<Vec<Vec3>>,
+    /// The same path split between walks and off-mesh link traversals, in world positions.
+    pub segments: Option<Vec<PathSegment<Vec3>>>,
+}
+
+/// Traversal cost of each area type for an agent type.
+///
+/// Area types without a cost are excluded: the agent can't walk on them.
+/// On an entity with a `PathRequest`, the answered path is the cheapest one for these costs.
+#[derive(Clone, Debug, Component)]
+pub struct AreaCosts {
+    pub costs: HashMap<AreaType, f32>,
+}
+
+impl Default for AreaCosts {
+    /// Every area type costs 1, except `AreaType::FORBIDDEN` which is excluded.
+    fn default() -> Self {
+        AreaCosts {
+            costs: [AreaType::ROAD, AreaType::GRASS, AreaType::WATER]
+                .into_iter()
+                .map(|area_type| (area_type, 1f32))
+                .collect(),
+        }
+    }
+}
+
+impl AreaCosts {
+    pub fn with_cost(mut self, area_type: AreaType, cost: f32) -> Self {
+        self.costs.insert(area_type, cost);
+        self
+    }
+
+    pub fn excluding(mut self, area_type: AreaType) -> Self {
+        self.costs.remove(&area_type);
+        self
+    }
+
+    /// `None` if the area type is excluded.
+    pub fn cost(&self, area_type: AreaType) -> Option<f32> {
+        self.costs.get(&area_type).copied()
+    }
+}
+
+/// Reads the navmesh in place, reusing the islands and spatial index stored with it.
+impl From<&BinaryMesh<'_>> for NavMesh {
+    fn from(mesh: &BinaryMesh<'_>) -> Self {
+        let vertices = (0..mesh.vertex_count())
+            .map(|v| {
+                polyanya::Vertex::new(
+                    mesh.vertex(v),
+                    mesh.vertex_polygons(v).map(|p| p as isize).collect(),
+                )
+            })
+            .collect();
+        let polygons = (0..mesh.polygon_count())
+            .map(|p| polyanya::Polygon::new(mesh.polygon_vertices(p).collect(), false))
+            .collect();
+        let navmesh = PAMesh::new(vertices, polygons);
+        NavMesh {
+            islands: mesh
+                .islands()
+                .unwrap_or_else(|| Islands::from_pa_mesh(&navmesh)),
+            area_types: (0..mesh.polygon_count())
+                .map(|p| mesh.area_type(p))
+                .collect(),
+            spatial_index: mesh.spatial_index(),
+            navmesh,
+        }
+    }
+}
+
+/// A path with its length weighted by the costs of the areas it crosses.
+#[derive(Debug)]
+pub struct WeightedPath {
+    pub path: Path,
+    pub cost: f32,
+}
+
+impl NavMesh {
+    pub fn new(navmesh: PAMesh) -> Self {
+        let islands = Islands::from_pa_mesh(&navmesh);
+        let area_types = vec![AreaType::default(); navmesh.polygons.len()];
+        NavMesh {
+            navmesh,
+            islands,
+            area_types,
+            spatial_index: None,
+        }
+    }
+
+    /// Sets the area type of each polygon, missing ones get the default area type.
+    pub fn with_area_types(mut self, mut area_types: Vec<AreaType>) -> Self {
+        area_types.resize(self.navmesh.polygons.len(), AreaType::default());
+        self.area_types = area_types;
+        self
+    }
+
+    /// Path between two world positions, for a navmesh placed in the world by `transform`.
+    ///
+    /// `from` and `to` are projected on the navmesh plane, the path points are on it.
+    /// With `costs`, the path is the cheapest one for them, see `path_for_agent`.
+    pub fn world_path(
+        &self,
+        from: Vec3,
+        to: Vec3,
+        links: &[OffMeshLink],
+        costs: Option<&AreaCosts>,
+        mapping: &PlaneMapping,
+        transform: &GlobalTransform,
+    ) -> Option<LinkedPath<Vec3>> {
+        let from = mapping.to_plane_with(transform, from);
+        let to = mapping.to_plane_with(transform, to);
+        let path = match costs {
+            Some(costs) => self.path_for_agent(from, to, links, costs),
+            None => self.path_with_links(from, to, links),
+        }?;
+        Some(path.map(|p| mapping.to_world_with(transform, *p)))
+    }
+
+    /// Path between `from` and `to` for an agent with `costs`, never crossing excluded areas.
+    ///
+    /// Without links this is the `weighted_path`. With links, area costs only exclude polygons
+    /// and the path is the shortest one on the navmesh `for_agent`.
+    pub fn path_for_agent(
+        &self,
+        from: Vec2,
+        to: Vec2,
+        links: &[OffMeshLink],
+        costs: &AreaCosts,
+    ) -> Option<LinkedPath> {
+        if links.is_empty() {
+            let weighted = self.weighted_path(from, to, costs)?;
+            return Some(LinkedPath {
+                segments: vec![PathSegment::Walk(
+                    std::iter::once(from).chain(weighted.path.path).collect(),
+                )],
+                cost: weighted.cost,
+            });
+        }
+        self.for_agent(costs).path_with_links(from, to, links)
+    }
+
+    /// Copy of this navmesh without the polygons excluded by `costs`.
+    ///
+    /// Meant to be computed once per agent type, for queries which don't know about costs
+    /// like `path` or `path_with_links`. `weighted_path` excludes areas by itself.
+    pub fn for_agent(&self, costs: &AreaCosts) -> NavMesh {
+        let mut new_indices = vec![-1isize; self.navmesh.polygons.len()];
+        let mut kept = 0;
+        for (polygon_index, area_type) in self.area_types.iter().enumerate() {
+            if costs.cost(*area_type).is_some() {
+                new_indices[polygon_index] = kept;
+                kept += 1;
+            }
+        }
+        let vertices = self
+            .navmesh
+            .vertices
+            .iter()
+            .map(|v| {
+                polyanya::Vertex::new(
+                    v.coords,
+                    v.polygons
+                        .iter()
+                        .map(|p| {
+                            if *p == -1 {
+                                -1
+                            } else {
+                                new_indices[*p as usize]
+                            }
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+        let polygons = self
+            .navmesh
+            .polygons
+            .iter()
+            .enumerate()
+            .filter(|(polygon_index, _)| new_indices[*polygon_index] != -1)
+            .map(|(_, p)| polyanya::Polygon::new(p.vertices.clone(), p.is_one_way))
+            .collect();
+        let area_types = self
+            .area_types
+            .iter()
+            .enumerate()
+            .filter(|(polygon_index, _)| new_indices[*polygon_index] != -1)
+            .map(|(_, area_type)| *area_type)
+            .collect();
+        NavMesh::new(PAMesh::new(vertices, polygons)).with_area_types(area_types)
+    }
+
+    /// Cost of following `path`, each segment length being weighted by the cost of the areas it crosses.
+    ///
+    /// A segment is split where it enters and leaves polygons, each part is charged with the cost of
+    /// its polygon. Parts along an edge shared by polygons take the cheapest one,
+    /// parts out of the mesh the cost of the default area type.
+    /// Returns `None` if a segment crosses an excluded area.
+    pub fn path_cost(&self, path: &[Vec2], costs: &AreaCosts) -> Option<f32> {
+        path.windows(2)
+            .map(|segment| self.segment_cost(segment[0], segment[1], costs))
+            .sum()
+    }
+
+    fn segment_cost(&self, a: Vec2, b: Vec2, costs: &AreaCosts) -> Option<f32> {
+        let direction = b - a;
+        let length = direction.length();
+        if length <= f32::EPSILON {
+            return Some(0.0);
+        }
+        let mut cuts = vec![0f32, 1f32];
+        for polygon in self.navmesh.polygons.iter() {
+            if let Some((enter, leave)) = self.clip_segment(&polygon.vertices, a, direction) {
+                cuts.push(enter);
+                cuts.push(leave);
+            }
+        }
+        cuts.sort_by(f32::total_cmp);
+        cuts.dedup();
+        cuts.windows(2)
+            .filter(|part| part[1] - part[0] > f32::EPSILON)
+            .map(|part| {
+                let middle = a + direction * (part[0] + part[1]) / 2.0;
+                let area_costs: Vec<Option<f32>> = self
+                    .navmesh
+                    .polygons
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, polygon)| self.polygon_contains(&polygon.vertices, middle))
+                    .map(|(polygon_index, _)| costs.cost(self.area_types[polygon_index]))
+                    .collect();
+                let cost = if area_costs.is_empty() {
+                    costs.cost(AreaType::default())
+                } else {
+                    area_costs
+                        .into_iter()
+                        .flatten()
+                        .min_by(|a, b| a.total_cmp(b))
+                }?;
+                Some((part[1] - part[0]) * length * cost)
+            })
+            .sum()
+    }
+
+    /// Part of the segment `origin + t * direction`, `t` in `0..=1`, inside a convex polygon.
+    fn clip_segment(&self, vertices: &[u32], origin: Vec2, direction: Vec2) -> Option<(f32, f32)> {
+        let (mut enter, mut leave) = (0f32, 1f32);
+        for i in 0..vertices.len() {
+            let start = self.navmesh.vertices[vertices[i] as usize].coords;
+            let end = self.navmesh.vertices[vertices[(i + 1) % vertices.len()] as usize].coords;
+            let edge = end - start;
+            // Inside is on the left of each edge: `numerator + t * denominator >= 0`.
+            let numerator = edge.perp_dot(origin - start);
+            let denominator = edge.perp_dot(direction);
+            if denominator.abs() <= f32::EPSILON {
+                if numerator < 0.0 {
+                    return None;
+                }
+            } else if denominator > 0.0 {
+                enter = enter.max(-numerator / denominator);
+            } else {
+                leave = leave.min(-numerator / denominator);
+            }
+        }
+        (enter < leave).then_some((enter, leave))
+    }
+
+    /// Like `polygon_at`, with a small tolerance for points on edges.
+    fn polygon_contains(&self, vertices: &[u32], point: Vec2) -> bool {
+        (0..vertices.len()).all(|i| {
+            let a = self.navmesh.vertices[vertices[i] as usize].coords;
+            let b = self.navmesh.vertices[vertices[(i + 1) % vertices.len()] as usize].coords;
+            (b - a).perp_dot(point - a) >= -1e-4 * (b - a).length()
+        })
+    }
+
+    /// Cheapest path between `from` and `to` for an agent with `costs`, with its weighted cost.
+    ///
+    /// Searches the polygon graph, each polygon being crossed at the cost of its area type
+    /// between the middles of its portals, then pulls the path taut in the found polygon corridor.
+    /// Excluded area types are never crossed.
+    pub fn weighted_path(&self, from: Vec2, to: Vec2, costs: &AreaCosts) -> Option<WeightedPath> {
+        let corridor = self.cheapest_corridor(from, to, costs)?;
+        let mut portals = vec![(from, from)];
+        portals.extend(
+            corridor
+                .windows(2)
+                .map(|pair| self.portal(pair[0], pair[1])),
+        );
+        portals.push((to, to));
+        let points = string_pull(&portals);
+        let cost = self.path_cost(&points, costs)?;
+        let length = points.windows(2).map(|p| p[0].distance(p[1])).sum();
+        Some(WeightedPath {
+            path: Path {
+                length,
+                path: points.into_iter().skip(1).collect(),
+            },
+            cost,
+        })
+    }
+
+    /// Polygons from the one containing `from` to the one containing `to`, Dijkstra over polygon adjacency.
+    fn cheapest_corridor(&self, from: Vec2, to: Vec2, costs: &AreaCosts) -> Option<Vec<usize>> {
+        let polygon_cost = |polygon: usize| costs.cost(self.area_types[polygon]);
+        let start = self
+            .polygon_at(from)
+            .filter(|p| polygon_cost(*p).is_some())?;
+        let goal = self.polygon_at(to).filter(|p| polygon_cost(*p).is_some())?;
+        if self.islands.island_of(start) != self.islands.island_of(goal) {
+            return None;
+        }
+        let edges = tools::edge_polygons(&self.navmesh);
+        let nb_polygons = self.navmesh.polygons.len();
+        // Node `nb_polygons` is `to`, reached from `goal`.
+        let target = nb_polygons;
+        let mut best = vec![f32::INFINITY; nb_polygons + 1];
+        let mut entry = vec![from; nb_polygons];
+        let mut previous = vec![usize::MAX; nb_polygons + 1];
+        let mut to_visit = BinaryHeap::new();
+        best[start] = 0.0;
+        to_visit.push(Reverse(Visit(0.0, start)));
+        while let Some(Reverse(Visit(cost, node))) = to_visit.pop() {
+            if node == target {
+                break;
+            }
+            if cost > best[node] {
+                continue;
+            }
+            let crossing_cost = polygon_cost(node).unwrap();
+            if node == goal {
+                let total = cost + entry[node].distance(to) * crossing_cost;
+                if total < best[target] {
+                    best[target] = total;
+                    previous[target] = node;
+                    to_visit.push(Reverse(Visit(total, target)));
+                }
+            }
+            let vertices = &self.navmesh.polygons[node].vertices;
+            for i in 0..vertices.len() {
+                let (a, b) = (vertices[i], vertices[(i + 1) % vertices.len()]);
+                for neighbour in edges[&(a.min(b), a.max(b))].iter() {
+                    let neighbour = *neighbour as usize;
+                    if neighbour == node || polygon_cost(neighbour).is_none() {
+                        continue;
+                    }
+                    let middle = (self.navmesh.vertices[a as usize].coords
+                        + self.navmesh.vertices[b as usize].coords)
+                        / 2.0;
+                    let total = cost + entry[node].distance(middle) * crossing_cost;
+                    if total < best[neighbour] {
+                        best[neighbour] = total;
+                        entry[neighbour] = middle;
+                        previous[neighbour] = node;
+                        to_visit.push(Reverse(Visit(total, neighbour)));
+                    }
+                }
+            }
+        }
+        if !best[target].is_finite() {
+            return None;
+        }
+        let mut corridor = vec![previous[target]];
+        while *corridor.last().unwrap() != start {
+            corridor.push(previous[*corridor.last().unwrap()]);
+        }
+        corridor.reverse();
+        Some(corridor)
+    }
+
+    /// Edge shared by two neighbour polygons, as `(left, right)` walking from `from` to `to`.
+    fn portal(&self, from: usize, to: usize) -> (Vec2, Vec2) {
+        let vertices = &self.navmesh.polygons[from].vertices;
+        let to_vertices = &self.navmesh.polygons[to].vertices;
+        let i = (0..vertices.len())
+            .find(|i| {
+                to_vertices.contains(&vertices[*i])
+                    && to_vertices.contains(&vertices[(*i + 1) % vertices.len()])
+            })
+            .unwrap();
+        let right = self.navmesh.vertices[vertices[i] as usize].coords;
+        let left = self.navmesh.vertices[vertices[(i + 1) % vertices.len()] as usize].coords;
+        // Polygons are counter clockwise, so their inside is on the left of their edges.
+        (left, right)
+    }
+
+    /// Index of the polygon containing `point`, if any.
+    pub fn polygon_at(&self, point: Vec2) -> Option<usize> {
+        let contains = |polygon_index: &usize| {
+            let vertices = &self.navmesh.polygons[*polygon_index].vertices;
+            (0..vertices.len()).all(|i| {
+                let a = self.navmesh.vertices[vertices[i] as usize].coords;
+                let b = self.navmesh.vertices[vertices[(i + 1) % vertices.len()] as usize].coords;
+                (b - a).perp_dot(point - a) >= 0f32
+            })
+        };
+        match &self.spatial_index {
+            Some(spatial_index) => spatial_index.candidates(point).find(contains),
+            None => (0..self.navmesh.polygons.len()).find(contains),
+        }
+    }
+
+    /// Island containing `point`, if any.
+    pub fn island_at(&self, point: Vec2) -> Option<u32> {
+        self.polygon_at(point)
+            .and_then(|polygon| self.islands.island_of(polygon))
+    }
+
+    /// Path between `from` and `to`,
+    /// returns `None` without searching if they are not on the same island.
+    pub fn path(&self, from: Vec2, to: Vec2) -> Option<Path> {
+        match (self.island_at(from), self.island_at(to)) {
+            (Some(from_island), Some(to_island)) if from_island == to_island => {
+                self.navmesh.path(from, to)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Polygon reached with the cost to reach it, ordered by cost for the Dijkstra queue.
+struct Visit(f32, usize);
+
+impl PartialEq for Visit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Visit {}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
-struct NavMeshMaterials {
-    mesh: Handle<StandardMaterial>,
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
 }
 
-fn setup_navmesh_graphics(mut commands: Commands, mut materials: ResMut<Assets<StandardMaterial>>) {
-    commands.insert_resource(NavMeshMaterials {
-        mesh: materials.add(Color::rgb(0.8, 0.4, 0.3).into()),
-    });
+/// Shortest path through `portals`, the first and last ones being the path ends.
+///
+/// Simple stupid funnel algorithm: the funnel from the apex narrows portal after portal,
+/// a side crossing the other one makes that corner the next apex.
+fn string_pull(portals: &[(Vec2, Vec2)]) -> Vec<Vec2> {
+    let (mut apex, _) = portals[0];
+    let (mut left, mut right) = (apex, apex);
+    let (mut apex_index, mut left_index, mut right_index) = (0, 0, 0);
+    let mut points = vec![apex];
+    let mut i = 1;
+    while i < portals.len() {
+        let (portal_left, portal_right) = portals[i];
+        // Right side narrows when the new right is not on the right of the funnel right side.
+        if (right - apex).perp_dot(portal_right - apex) >= 0.0 {
+            if apex == right || (left - apex).perp_dot(portal_right - apex) < 0.0 {
+                right = portal_right;
+                right_index = i;
+            } else {
+                // Crosses the left side, the left corner is on the path.
+                points.push(left);
+                apex = left;
+                apex_index = left_index;
+                right = apex;
+                right_index = apex_index;
+                i = apex_index + 1;
+                continue;
+            }
+        }
+        if (left - apex).perp_dot(portal_left - apex) <= 0.0 {
+            if apex == left || (right - apex).perp_dot(portal_left - apex) > 0.0 {
+                left = portal_left;
+                left_index = i;
+            } else {
+                points.push(right);
+                apex = right;
+                apex_index = right_index;
+                left = apex;
+                left_index = apex_index;
+                i = apex_index + 1;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    let (end, _) = portals[portals.len() - 1];
+    if points.last() != Some(&end) {
+        points.push(end);
+    }
+    points
 }
 
-fn update_mesh_visualization(
+/// Answers requests which changed, whose navmesh changed or moved, and the ones still waiting
+/// for their navmesh to exist.
+fn answer_path_requests(
     mut commands: Commands,
-    materials: Res<NavMeshMaterials>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    q: Query<(Entity, &NavMesh), Changed<NavMesh>>,
+    mapping: Res<PlaneMapping>,
+    removed_costs: RemovedComponents<AreaCosts>,
+    q_requests: Query<(
+        Entity,
+        &PathRequest,
+        &NavMeshRef,
+        Option<&PathResult>,
+        Option<&AreaCosts>,
+        ChangeTrackers<PathRequest>,
+        ChangeTrackers<NavMeshRef>,
+        Option<ChangeTrackers<AreaCosts>>,
+    )>,
+    q_navmeshes: Query<(
+        &NavMesh,
+        Option<&OffMeshLinks>,
+        Option<&GlobalTransform>,
+        ChangeTrackers<NavMesh>,
+        Option<ChangeTrackers<OffMeshLinks>>,
+        Option<ChangeTrackers<GlobalTransform>>,
+    )>,
 ) {
-    for (e, navmesh) in q.iter() {
-        // TODO: only modify mesh resource and not recreate a new one
-        // TODO: so modifying the entity is not even mandatory.
-        let mut new_mesh = Mesh::new(PrimitiveTopology::TriangleList);
-        new_mesh.insert_attribute(
-            Mesh::ATTRIBUTE_POSITION,
-            navmesh
-                .navmesh
-                .vertices
-                .iter()
-                .map(|v| [v.coords.x, 0.0, v.coords.y])
-                .collect::<Vec<[f32; 3]>>(),
-        );
-        new_mesh.set_indices(Some(Indices::U32(tools::triangulate(&navmesh.navmesh))));
-        new_mesh.insert_attribute(
-            Mesh::ATTRIBUTE_NORMAL,
-            (0..navmesh.navmesh.vertices.len())
-                .into_iter()
-                .map(|_| [0.0, 1.0, 0.0])
-                .collect::<Vec<[f32; 3]>>(),
+    for (e, request, navmesh_ref, result, costs, request_tracker, ref_tracker, costs_tracker) in
+        q_requests.iter()
+    {
+        let request_changed = request_tracker.is_changed()
+            || ref_tracker.is_changed()
+            || costs_tracker.map_or(false, |tracker| tracker.is_changed())
+            || removed_costs.iter().any(|removed| removed == e);
+        let (navmesh, links, transform, navmesh_tracker, links_tracker, transform_tracker) =
+            match q_navmeshes.get(navmesh_ref.0) {
+                Ok(navmesh) => navmesh,
+                Err(_) => {
+                    if request_changed {
+                        warn!("path request on {:?}, which has no NavMesh", navmesh_ref.0);
+                    }
+                    // Pending until the navmesh exists, a result for a removed navmesh is stale.
+                    if result.is_some() {
+                        commands.entity(e).remove::<PathResult>();
+                    }
+                    continue;
+                }
+            };
+        let navmesh_changed = navmesh_tracker.is_changed()
+            || links_tracker.map_or(false, |tracker| tracker.is_changed())
+            || transform_tracker.map_or(false, |tracker| tracker.is_changed());
+        if !request_changed && !navmesh_changed && result.is_some() {
+            continue;
+        }
+        let links = links.map(|links| links.0.as_slice()).unwrap_or_default();
+        let transform = transform.copied().unwrap_or_default();
+        let path = navmesh.world_path(request.from, request.to, links, costs, &mapping, &transform);
+        commands.entity(e).insert(PathResult {
+            points: path.as_ref().map(LinkedPath::points),
+            segments: path.map(|path| path.segments),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::{
+        binary::{BinaryMesh, BinaryOptions},
+        mesh_data::{merge_triangles::ConvexPolygonsMeshData, IntoPAMesh},
+        offmesh::{OffMeshLink, OffMeshLinks, PathSegment},
+        test_fixtures::{load, two_quads},
+        trianglemerger::AreaType,
+    };
+
+    use super::{AreaCosts, NavMesh, NavMeshPlugin, NavMeshRef, PathRequest, PathResult};
+
+    #[test]
+    fn from_binary_mesh() {
+        let bytes = load("assets/meshes/two_quads.mesh").to_binary(&BinaryOptions::default());
+        let navmesh = NavMesh::from(&BinaryMesh::parse(&bytes).unwrap());
+        assert!(navmesh.spatial_index.is_some());
+        assert_eq!(navmesh.islands, two_quads().islands);
+        assert_eq!(navmesh.polygon_at(Vec2::new(1.0, 0.2)), Some(0));
+        assert_eq!(navmesh.polygon_at(Vec2::new(4.8, 0.5)), Some(3));
+        assert_eq!(navmesh.polygon_at(Vec2::new(3.0, 0.5)), None);
+        assert!(navmesh
+            .path(Vec2::new(0.2, 0.2), Vec2::new(1.2, 1.2))
+            .is_some());
+    }
+
+    #[test]
+    fn requests_use_their_placed_navmesh() {
+        let mut app = App::new();
+        app.add_plugin(NavMeshPlugin);
+        let offset = Vec3::new(100.0, 0.0, 0.0);
+        let origin = app.world.spawn().insert(two_quads()).id();
+        let moved = app
+            .world
+            .spawn()
+            .insert(two_quads())
+            .insert(GlobalTransform::from_translation(offset))
+            .id();
+        let from = Vec3::new(0.5, 0.0, 0.5);
+        let to = Vec3::new(1.0, 0.0, 1.2);
+        let on_origin = app
+            .world
+            .spawn()
+            .insert(PathRequest { from, to })
+            .insert(NavMeshRef(origin))
+            .id();
+        let on_moved = app
+            .world
+            .spawn()
+            .insert(PathRequest {
+                from: from + offset,
+                to: to + offset,
+            })
+            .insert(NavMeshRef(moved))
+            .id();
+        let outside_moved = app
+            .world
+            .spawn()
+            .insert(PathRequest { from, to })
+            .insert(NavMeshRef(moved))
+            .id();
+        app.update();
+
+        let points = |e: Entity| app.world.get::<PathResult>(e).unwrap().points.clone();
+        assert_eq!(points(on_origin), Some(vec![from, to]));
+        let moved_points = points(on_moved).unwrap();
+        assert_eq!(moved_points.len(), 2);
+        assert!(moved_points[0].abs_diff_eq(from + offset, 1e-4));
+        assert!(moved_points[1].abs_diff_eq(to + offset, 1e-4));
+        assert_eq!(points(outside_moved), None);
+    }
+
+    #[test]
+    fn requests_follow_navmesh_changes() {
+        let mut app = App::new();
+        app.add_plugin(NavMeshPlugin);
+        let navmesh = app.world.spawn().insert(two_quads()).id();
+        let from = Vec3::new(0.5, 0.0, 0.5);
+        let to = Vec3::new(1.0, 0.0, 1.2);
+        let request = app
+            .world
+            .spawn()
+            .insert(PathRequest { from, to })
+            .insert(NavMeshRef(navmesh))
+            .id();
+        app.update();
+        let points = |app: &App| app.world.get::<PathResult>(request).unwrap().points.clone();
+        assert_eq!(points(&app), Some(vec![from, to]));
+
+        // Moving the navmesh away leaves the request outside of it.
+        app.world
+            .entity_mut(navmesh)
+            .insert(GlobalTransform::from_translation(Vec3::new(
+                100.0, 0.0, 0.0,
+            )));
+        app.update();
+        assert_eq!(points(&app), None);
+
+        app.world
+            .entity_mut(navmesh)
+            .insert(GlobalTransform::default());
+        app.update();
+        assert_eq!(points(&app), Some(vec![from, to]));
+
+        // A navmesh without the polygons under the request.
+        let mesh_data = ConvexPolygonsMeshData::from(&load("assets/meshes/quad.mesh"));
+        app.world.entity_mut(request).insert(PathRequest {
+            from: Vec3::new(4.2, 0.0, 0.5),
+            to: Vec3::new(4.8, 0.0, 0.5),
+        });
+        app.update();
+        assert!(points(&app).is_some());
+        *app.world.get_mut::<NavMesh>(navmesh).unwrap() = NavMesh::new(mesh_data.to_pa_mesh());
+        app.update();
+        assert_eq!(points(&app), None);
+    }
+
+    #[test]
+    fn requests_wait_for_their_navmesh() {
+        let mut app = App::new();
+        app.add_plugin(NavMeshPlugin);
+        let navmesh = app.world.spawn().id();
+        let from = Vec3::new(0.5, 0.0, 0.5);
+        let to = Vec3::new(1.0, 0.0, 1.2);
+        let request = app
+            .world
+            .spawn()
+            .insert(PathRequest { from, to })
+            .insert(NavMeshRef(navmesh))
+            .id();
+        app.update();
+        assert!(app.world.get::<PathResult>(request).is_none());
+        app.update();
+
+        app.world.entity_mut(navmesh).insert(two_quads());
+        app.update();
+        assert_eq!(
+            app.world.get::<PathResult>(request).unwrap().points,
+            Some(vec![from, to])
         );
 
-        new_mesh.insert_attribute(
-            Mesh::ATTRIBUTE_UV_0,
-            navmesh
-                .navmesh
-                .vertices
-                .iter()
-                .map(|v| [v.coords.x, v.coords.y])
-                .collect::<Vec<[f32; 2]>>(),
+        // The result goes stale with its navmesh.
+        app.world.entity_mut(navmesh).remove::<NavMesh>();
+        app.update();
+        assert!(app.world.get::<PathResult>(request).is_none());
+    }
+
+    #[test]
+    fn results_tell_links_from_walks() {
+        let mut app = App::new();
+        app.add_plugin(NavMeshPlugin);
+        let navmesh = app
+            .world
+            .spawn()
+            .insert(two_quads())
+            .insert(OffMeshLinks(vec![OffMeshLink::new(
+                Vec2::new(1.0, 0.5),
+                Vec2::new(4.2, 0.5),
+                false,
+                1.0,
+            )]))
+            .id();
+        let from = Vec3::new(0.5, 0.0, 0.5);
+        let to = Vec3::new(4.7, 0.0, 0.5);
+        let request = app
+            .world
+            .spawn()
+            .insert(PathRequest { from, to })
+            .insert(NavMeshRef(navmesh))
+            .id();
+        app.update();
+        let result = app.world.get::<PathResult>(request).unwrap();
+        let (start, end) = (Vec3::new(1.0, 0.0, 0.5), Vec3::new(4.2, 0.0, 0.5));
+        assert_eq!(result.points, Some(vec![from, start, end, to]));
+        assert_eq!(
+            result.segments,
+            Some(vec![
+                PathSegment::Walk(vec![from, start]),
+                PathSegment::Link {
+                    link_index: 0,
+                    start,
+                    end
+                },
+                PathSegment::Walk(vec![end, to]),
+            ])
         );
-        let nav_mesh_handle = meshes.add(new_mesh);
-        commands.entity(e).insert_bundle(PbrBundle {
-            mesh: nav_mesh_handle,
-            material: materials.mesh.clone(),
-            ..default()
-        });
+    }
+
+    /// 3 by 3 unit squares, the middle one is water.
+    fn pond() -> NavMesh {
+        let mut mesh_data = ConvexPolygonsMeshData::default();
+        for y in 0..4 {
+            for x in 0..4 {
+                mesh_data.add_vertex(Vec2::new(x as f32, y as f32));
+            }
+        }
+        for y in 0..3 {
+            for x in 0..3 {
+                let corner = y * 4 + x;
+                let area_type = if (x, y) == (1, 1) {
+                    AreaType::WATER
+                } else {
+                    AreaType::ROAD
+                };
+                mesh_data
+                    .add_polygon(vec![corner, corner + 1, corner + 5, corner + 4], area_type)
+                    .unwrap();
+            }
+        }
+        NavMesh::new(mesh_data.to_pa_mesh()).with_area_types(mesh_data.area_types())
+    }
+
+    #[test]
+    fn segments_are_costed_per_polygon() {
+        let navmesh = pond();
+        let costs = AreaCosts::default().with_cost(AreaType::WATER, 10.0);
+        let from = Vec2::new(0.5, 1.5);
+        let to = Vec2::new(2.5, 1.5);
+        // Half a unit of road, a unit of water, half a unit of road.
+        let straight = navmesh.path_cost(&[from, to], &costs).unwrap();
+        assert!((straight - 11.0).abs() < 1e-4);
+        // Along the water edge, the road side is taken.
+        let along = navmesh
+            .path_cost(&[Vec2::new(1.0, 2.0), Vec2::new(2.0, 2.0)], &costs)
+            .unwrap();
+        assert!((along - 1.0).abs() < 1e-4);
+        let without_water = AreaCosts::default().excluding(AreaType::WATER);
+        assert_eq!(navmesh.path_cost(&[from, to], &without_water), None);
+    }
+
+    #[test]
+    fn cheaper_detour_wins() {
+        let navmesh = pond();
+        let from = Vec2::new(0.5, 1.5);
+        let to = Vec2::new(2.5, 1.5);
+        let cheap_water = AreaCosts::default();
+        let straight = navmesh.weighted_path(from, to, &cheap_water).unwrap();
+        assert_eq!(straight.path.path, vec![to]);
+        assert!((straight.cost - 2.0).abs() < 1e-4);
+
+        let expensive_water = AreaCosts::default().with_cost(AreaType::WATER, 10.0);
+        let detour = navmesh.weighted_path(from, to, &expensive_water).unwrap();
+        assert_eq!(detour.path.path.len(), 3);
+        assert!(detour.path.path[..2]
+            .iter()
+            .all(|p| p.abs_diff_eq(Vec2::new(p.x, 2.0), 1e-4)));
+        let detour_cost = 2.0 * 2f32.sqrt() / 2.0 + 1.0;
+        assert!((detour.cost - detour_cost).abs() < 1e-4);
+        assert!((detour.path.length - detour_cost).abs() < 1e-4);
+    }
+
+    #[test]
+    fn requests_avoid_excluded_areas() {
+        let mut app = App::new();
+        app.add_plugin(NavMeshPlugin);
+        let navmesh = app.world.spawn().insert(pond()).id();
+        let request = app
+            .world
+            .spawn()
+            .insert(PathRequest {
+                from: Vec3::new(0.5, 0.0, 1.5),
+                to: Vec3::new(2.5, 0.0, 1.5),
+            })
+            .insert(NavMeshRef(navmesh))
+            .insert(AreaCosts::default().excluding(AreaType::WATER))
+            .id();
+        app.update();
+        let points = app
+            .world
+            .get::<PathResult>(request)
+            .unwrap()
+            .points
+            .clone()
+            .unwrap();
+        // Around the water, by two of its corners.
+        assert_eq!(points.len(), 4);
+        assert!(points[1..3]
+            .iter()
+            .all(|p| (p.z - 1.0).abs() < 1e-4 || (p.z - 2.0).abs() < 1e-4));
+
+        app.world
+            .entity_mut(request)
+            .insert(AreaCosts::default().excluding(AreaType::ROAD));
+        app.update();
+        assert_eq!(app.world.get::<PathResult>(request).unwrap().points, None);
     }
 }
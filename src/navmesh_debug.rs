@@ -0,0 +1,531 @@
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy::utils::HashMap;
+use bevy_polyline::prelude::*;
+use polyanya::Mesh as PAMesh;
+
+use crate::{navmesh::NavMesh, plane::PlaneMapping, MainCamera};
+
+/// Draws every `NavMesh` with a color per polygon,
+/// its boundary and portal edges, and optionally polygon and vertex indices.
+///
+/// Edges are polylines, it needs `PolylinePlugin`.
+pub struct NavMeshDebugPlugin;
+
+impl Plugin for NavMeshDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavMeshDebugConfig>()
+            .init_resource::<PlaneMapping>()
+            .add_startup_system(setup_debug_materials)
+            .add_system(toggle_debug_visuals)
+            .add_system(spawn_debug_visuals)
+            .add_system(update_debug_visuals)
+            .add_system(despawn_orphan_labels)
+            .add_system(update_label_positions);
+    }
+}
+
+pub struct NavMeshDebugConfig {
+    pub enabled: bool,
+    /// Key toggling `enabled`.
+    pub toggle_key: Option<KeyCode>,
+    pub show_polygon_labels: bool,
+    pub show_vertex_labels: bool,
+    /// Labels are only shown if a font is given.
+    pub label_font: Option<Handle<Font>>,
+    /// Offset above the navmesh plane, to avoid z-fighting with other meshes.
+    pub height_offset: f32,
+}
+
+impl Default for NavMeshDebugConfig {
+    fn default() -> Self {
+        NavMeshDebugConfig {
+            enabled: true,
+            toggle_key: Some(KeyCode::F3),
+            show_polygon_labels: false,
+            show_vertex_labels: false,
+            label_font: None,
+            height_offset: 0.05,
+        }
+    }
+}
+
+struct NavMeshDebugMaterials {
+    polygons: Handle<StandardMaterial>,
+    boundary: Handle<PolylineMaterial>,
+    portals: Handle<PolylineMaterial>,
+}
+
+/// Debug meshes of a `NavMesh`, spawned as children of its entity.
+#[derive(Component)]
+pub struct NavMeshDebugVisual {
+    polygons: Handle<Mesh>,
+    /// Polyline entities, one per strip of connected edges.
+    boundary: Vec<Entity>,
+    portals: Vec<Entity>,
+    /// UI label entities, with the world position they follow.
+    labels: Vec<(Entity, Vec3)>,
+}
+
+/// Marks entities spawned by this plugin, to toggle their visibility.
+#[derive(Component)]
+struct NavMeshDebugPart;
+
+/// UI labels can't be children of the navmesh entity, they remember it to be despawned with it.
+#[derive(Component)]
+struct NavMeshDebugLabel {
+    navmesh: Entity,
+}
+
+fn setup_debug_materials(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut polyline_materials: ResMut<Assets<PolylineMaterial>>,
+) {
+    commands.insert_resource(NavMeshDebugMaterials {
+        polygons: materials.add(StandardMaterial {
+            base_color: Color::rgba(1.0, 1.0, 1.0, 0.8),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+        boundary: polyline_materials.add(PolylineMaterial {
+            width: 3.0,
+            color: Color::BLACK,
+            perspective: false,
+            ..default()
+        }),
+        portals: polyline_materials.add(PolylineMaterial {
+            width: 1.5,
+            color: Color::GRAY,
+            perspective: false,
+            ..default()
+        }),
+    });
+}
+
+fn toggle_debug_visuals(
+    keys: Res<Input<KeyCode>>,
+    mut config: ResMut<NavMeshDebugConfig>,
+    mut q_parts: Query<&mut Visibility, With<NavMeshDebugPart>>,
+) {
+    if let Some(toggle_key) = config.toggle_key {
+        if keys.just_pressed(toggle_key) {
+            config.enabled = !config.enabled;
+        }
+    }
+    if !config.is_changed() {
+        return;
+    }
+    for mut visibility in q_parts.iter_mut() {
+        visibility.is_visible = config.enabled;
+    }
+}
+
+fn spawn_debug_visuals(
+    mut commands: Commands,
+    config: Res<NavMeshDebugConfig>,
+    mapping: Res<PlaneMapping>,
+    materials: Res<NavMeshDebugMaterials>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut polylines: ResMut<Assets<Polyline>>,
+    q_navmeshes: Query<(Entity, &NavMesh), Without<NavMeshDebugVisual>>,
+) {
+    for (e, navmesh) in q_navmeshes.iter() {
+        let mut polygons = Mesh::new(PrimitiveTopology::TriangleList);
+        fill_polygons_mesh(
+            &mut polygons,
+            &navmesh.navmesh,
+            &mapping,
+            config.height_offset,
+        );
+        let polygons = meshes.add(polygons);
+        let polygons_entity = commands
+            .spawn_bundle(PbrBundle {
+                mesh: polygons.clone(),
+                material: materials.polygons.clone(),
+                visibility: Visibility {
+                    is_visible: config.enabled,
+                },
+                ..default()
+            })
+            .insert(NavMeshDebugPart)
+            .id();
+        commands.entity(e).add_child(polygons_entity);
+        let (boundary_strips, portal_strips) =
+            edge_strips(&navmesh.navmesh, &mapping, config.height_offset * 2f32);
+        let mut visual = NavMeshDebugVisual {
+            polygons,
+            boundary: Vec::new(),
+            portals: Vec::new(),
+            labels: Vec::new(),
+        };
+        for (strips, entities, material) in [
+            (boundary_strips, &mut visual.boundary, &materials.boundary),
+            (portal_strips, &mut visual.portals, &materials.portals),
+        ] {
+            for strip in strips {
+                entities.push(spawn_polyline(
+                    &mut commands,
+                    &mut polylines,
+                    e,
+                    material,
+                    config.enabled,
+                    strip,
+                ));
+            }
+        }
+        if let Some(font) = config.label_font.as_ref() {
+            for (text, position, color) in label_specs(&config, &mapping, &navmesh.navmesh) {
+                let label = spawn_label(&mut commands, font, e, text, color);
+                visual.labels.push((label, position));
+            }
+        }
+        commands.entity(e).insert(visual);
+    }
+}
+
+fn spawn_polyline(
+    commands: &mut Commands,
+    polylines: &mut Assets<Polyline>,
+    parent: Entity,
+    material: &Handle<PolylineMaterial>,
+    visible: bool,
+    vertices: Vec<Vec3>,
+) -> Entity {
+    let polyline = commands
+        .spawn_bundle(PolylineBundle {
+            polyline: polylines.add(Polyline { vertices }),
+            material: material.clone(),
+            visibility: Visibility {
+                is_visible: visible,
+            },
+            ..default()
+        })
+        .insert(NavMeshDebugPart)
+        .id();
+    commands.entity(parent).add_child(polyline);
+    polyline
+}
+
+fn spawn_label(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    navmesh: Entity,
+    text: String,
+    color: Color,
+) -> Entity {
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                text,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 14.0,
+                    color,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                ..default()
+            }),
+        )
+        .insert(NavMeshDebugPart)
+        .insert(NavMeshDebugLabel { navmesh })
+        .id()
+}
+
+/// Updates the existing assets and entities rather than creating new ones,
+/// only spawning or despawning the extra polylines and labels.
+fn update_debug_visuals(
+    mut commands: Commands,
+    config: Res<NavMeshDebugConfig>,
+    mapping: Res<PlaneMapping>,
+    materials: Res<NavMeshDebugMaterials>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut polylines: ResMut<Assets<Polyline>>,
+    mut q_navmeshes: Query<(Entity, &NavMesh, &mut NavMeshDebugVisual), Changed<NavMesh>>,
+    q_polylines: Query<&Handle<Polyline>>,
+    mut q_texts: Query<&mut Text>,
+) {
+    for (e, navmesh, mut visual) in q_navmeshes.iter_mut() {
+        let visual = &mut *visual;
+        if let Some(polygons) = meshes.get_mut(&visual.polygons) {
+            fill_polygons_mesh(polygons, &navmesh.navmesh, &mapping, config.height_offset);
+        }
+        let (boundary_strips, portal_strips) =
+            edge_strips(&navmesh.navmesh, &mapping, config.height_offset * 2f32);
+        for (strips, entities, material) in [
+            (boundary_strips, &mut visual.boundary, &materials.boundary),
+            (portal_strips, &mut visual.portals, &materials.portals),
+        ] {
+            for extra in entities.drain(strips.len().min(entities.len())..) {
+                commands.entity(extra).despawn_recursive();
+            }
+            for (i, strip) in strips.into_iter().enumerate() {
+                match entities.get(i) {
+                    Some(entity) => {
+                        let polyline = q_polylines
+                            .get(*entity)
+                            .ok()
+                            .and_then(|handle| polylines.get_mut(handle));
+                        if let Some(polyline) = polyline {
+                            polyline.vertices = strip;
+                        }
+                    }
+                    None => entities.push(spawn_polyline(
+                        &mut commands,
+                        &mut polylines,
+                        e,
+                        material,
+                        config.enabled,
+                        strip,
+                    )),
+                }
+            }
+        }
+        let font = match config.label_font.as_ref() {
+            Some(font) => font,
+            None => continue,
+        };
+        let specs = label_specs(&config, &mapping, &navmesh.navmesh);
+        for (extra, _) in visual.labels.drain(specs.len().min(visual.labels.len())..) {
+            commands.entity(extra).despawn_recursive();
+        }
+        for (i, (text, position, color)) in specs.into_iter().enumerate() {
+            match visual.labels.get_mut(i) {
+                Some((label, label_position)) => {
+                    *label_position = position;
+                    if let Ok(mut label_text) = q_texts.get_mut(*label) {
+                        label_text.sections[0].value = text;
+                        label_text.sections[0].style.color = color;
+                    }
+                }
+                None => {
+                    let label = spawn_label(&mut commands, font, e, text, color);
+                    visual.labels.push((label, position));
+                }
+            }
+        }
+    }
+}
+
+/// Labels of navmeshes which were despawned or lost their debug visual.
+fn despawn_orphan_labels(
+    mut commands: Commands,
+    q_labels: Query<(Entity, &NavMeshDebugLabel)>,
+    q_visuals: Query<(), With<NavMeshDebugVisual>>,
+) {
+    for (label, debug_label) in q_labels.iter() {
+        if !q_visuals.contains(debug_label.navmesh) {
+            commands.entity(label).despawn_recursive();
+        }
+    }
+}
+
+fn update_label_positions(
+    config: Res<NavMeshDebugConfig>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    q_navmeshes: Query<(&NavMeshDebugVisual, Option<&GlobalTransform>)>,
+    mut q_labels: Query<(&mut Style, &mut Visibility)>,
+) {
+    let (camera, camera_transform) = match q_camera.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    for (visual, navmesh_transform) in q_navmeshes.iter() {
+        let navmesh_matrix = navmesh_transform
+            .map(|transform| transform.compute_matrix())
+            .unwrap_or_default();
+        for (label, position) in visual.labels.iter() {
+            if let Ok((mut style, mut visibility)) = q_labels.get_mut(*label) {
+                let viewport_position = camera.world_to_viewport(
+                    camera_transform,
+                    navmesh_matrix.transform_point3(*position),
+                );
+                visibility.is_visible = config.enabled && viewport_position.is_some();
+                if let Some(viewport_position) = viewport_position {
+                    style.position.left = Val::Px(viewport_position.x);
+                    style.position.bottom = Val::Px(viewport_position.y);
+                }
+            }
+        }
+    }
+}
+
+/// Text, position in the navmesh space and color of each label to show.
+fn label_specs(
+    config: &NavMeshDebugConfig,
+    mapping: &PlaneMapping,
+    navmesh: &PAMesh,
+) -> Vec<(String, Vec3, Color)> {
+    let mut labels = Vec::new();
+    if config.show_polygon_labels {
+        for (polygon_index, polygon) in navmesh.polygons.iter().enumerate() {
+            let center = polygon
+                .vertices
+                .iter()
+                .map(|v| navmesh.vertices[*v as usize].coords)
+                .sum::<Vec2>()
+                / polygon.vertices.len() as f32;
+            labels.push((polygon_index.to_string(), center, Color::BLACK));
+        }
+    }
+    if config.show_vertex_labels {
+        for (vertex_index, vertex) in navmesh.vertices.iter().enumerate() {
+            labels.push((vertex_index.to_string(), vertex.coords, Color::BLUE));
+        }
+    }
+    labels
+        .into_iter()
+        .map(|(text, position, color)| {
+            (
+                text,
+                mapping.to_world_offset(position, config.height_offset),
+                color,
+            )
+        })
+        .collect()
+}
+
+/// Each polygon has its own vertices, to have a color per polygon.
+pub(crate) fn fill_polygons_mesh(
+    mesh: &mut Mesh,
+    navmesh: &PAMesh,
+    mapping: &PlaneMapping,
+    height: f32,
+) {
+    let nb_polygons = navmesh.polygons.len();
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+    for (polygon_index, polygon) in navmesh.polygons.iter().enumerate() {
+        let first = positions.len() as u32;
+        let color = crate::tools::polygon_color(polygon_index, nb_polygons).as_rgba_f32();
+        for v in polygon.vertices.iter() {
+            let p = navmesh.vertices[*v as usize].coords;
+            positions.push(mapping.to_world_offset(p, height).to_array());
+            colors.push(color);
+        }
+        for i in 2..polygon.vertices.len() as u32 {
+            indices.extend(mapping.front_facing([first, first + i - 1, first + i]));
+        }
+    }
+    let nb_vertices = positions.len();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![mapping.normal().to_array(); nb_vertices],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; nb_vertices]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.set_indices(Some(Indices::U32(indices)));
+}
+
+/// Positions of boundary and portal edges, as line lists.
+///
+/// Edges shared by 2 polygons are portals, others are on the boundary.
+pub(crate) fn edges_positions(
+    navmesh: &PAMesh,
+    mapping: &PlaneMapping,
+    height: f32,
+) -> (Vec<[f32; 3]>, Vec<[f32; 3]>) {
+    let mut boundary_positions = Vec::new();
+    let mut portal_positions = Vec::new();
+    for ((a, b), polygons) in crate::tools::edge_polygons(navmesh).iter() {
+        let a = navmesh.vertices[*a as usize].coords;
+        let b = navmesh.vertices[*b as usize].coords;
+        let positions = if polygons.len() > 1 {
+            &mut portal_positions
+        } else {
+            &mut boundary_positions
+        };
+        positions.push(mapping.to_world_offset(a, height).to_array());
+        positions.push(mapping.to_world_offset(b, height).to_array());
+    }
+    (boundary_positions, portal_positions)
+}
+
+/// Boundary and portal edges chained into polylines.
+fn edge_strips(
+    navmesh: &PAMesh,
+    mapping: &PlaneMapping,
+    height: f32,
+) -> (Vec<Vec<Vec3>>, Vec<Vec<Vec3>>) {
+    let (portals, boundary): (Vec<_>, Vec<_>) = crate::tools::edge_polygons(navmesh)
+        .into_iter()
+        .partition(|(_, polygons)| polygons.len() > 1);
+    let to_positions = |edges: Vec<((u32, u32), Vec<i32>)>| {
+        chain_edges(edges.into_iter().map(|(edge, _)| edge).collect())
+            .into_iter()
+            .map(|strip| {
+                strip
+                    .into_iter()
+                    .map(|v| mapping.to_world_offset(navmesh.vertices[v as usize].coords, height))
+                    .collect()
+            })
+            .collect()
+    };
+    (to_positions(boundary), to_positions(portals))
+}
+
+/// Chains edges sharing vertices into strips of vertices, each edge being in a single strip.
+fn chain_edges(edges: Vec<(u32, u32)>) -> Vec<Vec<u32>> {
+    let mut vertex_edges: HashMap<u32, Vec<usize>> = HashMap::default();
+    for (edge_index, (a, b)) in edges.iter().enumerate() {
+        vertex_edges.entry(*a).or_default().push(edge_index);
+        vertex_edges.entry(*b).or_default().push(edge_index);
+    }
+    let mut used = vec![false; edges.len()];
+    let mut strips = Vec::new();
+    for (edge_index, (a, b)) in edges.iter().enumerate() {
+        if used[edge_index] {
+            continue;
+        }
+        used[edge_index] = true;
+        let mut strip = vec![*a, *b];
+        // Extend the end, then the start by extending the reversed strip.
+        for _ in 0..2 {
+            while let Some(next) = vertex_edges[strip.last().unwrap()]
+                .iter()
+                .copied()
+                .find(|next| !used[*next])
+            {
+                used[next] = true;
+                let (a, b) = edges[next];
+                let end = *strip.last().unwrap();
+                strip.push(if a == end { b } else { a });
+            }
+            strip.reverse();
+        }
+        strips.push(strip);
+    }
+    strips
+}
+
+pub(crate) fn fill_lines_mesh(mesh: &mut Mesh, positions: Vec<[f32; 3]>, mapping: &PlaneMapping) {
+    let nb_vertices = positions.len();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![mapping.normal().to_array(); nb_vertices],
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; nb_vertices]);
+    mesh.set_indices(Some(Indices::U32((0..nb_vertices as u32).collect())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::chain_edges;
+
+    #[test]
+    fn chained_edges() {
+        // A square loop in shuffled order, and a separate edge.
+        let strips = chain_edges(vec![(2, 3), (0, 1), (7, 8), (3, 0), (1, 2)]);
+        assert_eq!(strips.len(), 2);
+        assert_eq!(strips[0].len(), 5);
+        assert_eq!(strips[0].first(), strips[0].last());
+        assert_eq!(strips[1], vec![7, 8]);
+    }
+}
@@ -0,0 +1,236 @@
+//! Wavefront OBJ import and export, to edit navmeshes in DCC tools.
+//!
+//! OBJ files are Y up: a vertex `(x, y, z)` is the navmesh point `(x, z)`, `y` is dropped.
+//! Faces are written with their normal pointing up.
+
+use std::fmt::{self, Write};
+
+use bevy::prelude::*;
+use polyanya::Mesh as PAMesh;
+
+use crate::{
+    mesh_data::merge_triangles::ConvexPolygonsMeshData,
+    tools::triangulate_simple_polygon,
+    trianglemerger::{MeshMerger, Polygon, Vertex},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjImportOptions {
+    /// Triangulates faces which are not convex, otherwise they are an error.
+    pub triangulate_non_convex: bool,
+    /// Merges polygons with `MeshMerger` after import.
+    pub merge: bool,
+}
+
+impl Default for ObjImportOptions {
+    fn default() -> Self {
+        ObjImportOptions {
+            triangulate_non_convex: true,
+            merge: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjError {
+    /// A line couldn't be parsed, lines start at 1.
+    Parse { line: usize, message: String },
+    /// The face isn't convex and `triangulate_non_convex` is off, faces start at 0.
+    NonConvexFace(u32),
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            ObjError::NonConvexFace(face) => write!(f, "face {face} is not convex"),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// Reads vertices and faces, other statements (normals, uvs, groups, materials...) are ignored.
+///
+/// Polygons get the default area type, faces can be in any winding.
+pub fn read_obj(
+    text: &str,
+    options: &ObjImportOptions,
+) -> Result<ConvexPolygonsMeshData, ObjError> {
+    let mut mesh_data = ConvexPolygonsMeshData::default();
+    for (line_index, line) in text.lines().enumerate() {
+        let error = |message: String| ObjError::Parse {
+            line: line_index + 1,
+            message,
+        };
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let coords = tokens
+                    .take(3)
+                    .map(|t| t.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| error(e.to_string()))?;
+                if coords.len() < 3 {
+                    return Err(error("vertex needs 3 coordinates".to_string()));
+                }
+                mesh_data.mesh_vertices.push(Vertex {
+                    p: Vec2::new(coords[0], coords[2]),
+                    polygons: Vec::new(),
+                });
+            }
+            Some("f") => {
+                let nb_vertices = mesh_data.mesh_vertices.len() as i64;
+                let vertices = tokens
+                    .map(|t| {
+                        // `v`, `v/vt`, `v//vn` or `v/vt/vn`, negative indices are relative.
+                        let index = t
+                            .split('/')
+                            .next()
+                            .unwrap_or_default()
+                            .parse::<i64>()
+                            .map_err(|e| error(e.to_string()))?;
+                        let index = if index < 0 {
+                            nb_vertices + index
+                        } else {
+                            index - 1
+                        };
+                        if index < 0 || index >= nb_vertices {
+                            return Err(error(format!("invalid vertex index {t}")));
+                        }
+                        Ok(index as u32)
+                    })
+                    .collect::<Result<Vec<u32>, _>>()?;
+                if vertices.len() < 3 {
+                    return Err(error("face needs 3 vertices".to_string()));
+                }
+                mesh_data.mesh_polygons.push(Polygon {
+                    vertices,
+                    ..default()
+                });
+            }
+            _ => (),
+        }
+    }
+
+    let mut polygons = Vec::new();
+    for (face, mut polygon) in std::mem::take(&mut mesh_data.mesh_polygons)
+        .into_iter()
+        .enumerate()
+    {
+        if MeshMerger::get_area(&mesh_data.mesh_vertices, &polygon.vertices) < 0.0 {
+            polygon.vertices.reverse();
+        }
+        if mesh_data.is_convex(&polygon.vertices) {
+            polygons.push(polygon);
+        } else if options.triangulate_non_convex {
+            let points: Vec<Vec2> = polygon
+                .vertices
+                .iter()
+                .map(|v| mesh_data.mesh_vertices[*v as usize].p)
+                .collect();
+            polygons.extend(
+                triangulate_simple_polygon(&points)
+                    .into_iter()
+                    .map(|triangle| Polygon {
+                        vertices: triangle.iter().map(|i| polygon.vertices[*i]).collect(),
+                        area_type: polygon.area_type,
+                        ..default()
+                    }),
+            );
+        } else {
+            return Err(ObjError::NonConvexFace(face as u32));
+        }
+    }
+    mesh_data.mesh_polygons = polygons;
+    mesh_data.rebuild_neighbours();
+
+    if options.merge {
+        let mut mesh_merger = MeshMerger::from(&mesh_data);
+        mesh_merger.my_merge();
+        mesh_merger.remove_unused();
+        mesh_data = ConvexPolygonsMeshData::from(&mesh_merger);
+    }
+    Ok(mesh_data)
+}
+
+/// Writes vertices on the ground and one face per polygon.
+///
+/// Any mesh data can be written through `IntoPAMesh`: `write_obj(&mesh_data.to_pa_mesh())`.
+pub fn write_obj(mesh: &PAMesh) -> String {
+    let mut obj = String::from("# navmesh exported by meshquisse\no navmesh\n");
+    for vertex in mesh.vertices.iter() {
+        let _ = writeln!(obj, "v {} 0 {}", vertex.coords.x, vertex.coords.y);
+    }
+    for polygon in mesh.polygons.iter() {
+        // Counter clockwise in navmesh coordinates is clockwise seen from above.
+        let indices: Vec<String> = polygon
+            .vertices
+            .iter()
+            .rev()
+            .map(|v| (v + 1).to_string())
+            .collect();
+        let _ = writeln!(obj, "f {}", indices.join(" "));
+    }
+    obj
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        mesh_data::{merge_triangles::ConvexPolygonsMeshData, IntoPAMesh},
+        trianglemerger::MeshMerger,
+    };
+
+    use super::{read_obj, write_obj, ObjError, ObjImportOptions};
+
+    const L_SHAPE: &str = "
+v 0 0 0
+v 2 0 0
+v 2 0 1
+v 1 0 1
+v 1 0 2
+v 0 0 2
+vn 0 1 0
+f 6//1 5//1 4//1 3//1 2//1 1//1
+";
+
+    #[test]
+    fn concave_face_is_triangulated_and_merged() {
+        let mesh_data = read_obj(L_SHAPE, &ObjImportOptions::default()).unwrap();
+        assert_eq!(mesh_data.mesh_vertices.len(), 6);
+        assert!(mesh_data.mesh_polygons.len() < 4);
+        assert_eq!(MeshMerger::from(&mesh_data).validate(), vec![]);
+    }
+
+    #[test]
+    fn concave_face_without_triangulation() {
+        let options = ObjImportOptions {
+            triangulate_non_convex: false,
+            ..Default::default()
+        };
+        assert!(matches!(
+            read_obj(L_SHAPE, &options),
+            Err(ObjError::NonConvexFace(0))
+        ));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mesh_data = read_obj(L_SHAPE, &ObjImportOptions::default()).unwrap();
+        let obj = write_obj(&mesh_data.to_pa_mesh());
+        let options = ObjImportOptions {
+            triangulate_non_convex: false,
+            merge: false,
+        };
+        let reread = read_obj(&obj, &options).unwrap();
+        let positions = |m: &ConvexPolygonsMeshData| -> Vec<_> {
+            m.mesh_vertices.iter().map(|v| v.p).collect()
+        };
+        let polygons = |m: &ConvexPolygonsMeshData| -> Vec<_> {
+            m.mesh_polygons.iter().map(|p| p.vertices.clone()).collect()
+        };
+        assert_eq!(positions(&reread), positions(&mesh_data));
+        assert_eq!(polygons(&reread), polygons(&mesh_data));
+    }
+}
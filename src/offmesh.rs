@@ -0,0 +1,371 @@
+use bevy::prelude::*;
+#[cfg(feature = "render")]
+use bevy_polyline::prelude::*;
+
+use crate::navmesh::NavMesh;
+#[cfg(feature = "render")]
+use crate::plane::PlaneMapping;
+
+/// Connection between two points of a navmesh which polygons can't express:
+/// doors, jump-downs, ladders, teleporters...
+#[derive(Clone, Debug, PartialEq)]
+pub struct OffMeshLink {
+    pub start: Vec2,
+    pub end: Vec2,
+    /// If false, the link can only be traversed from `start` to `end`.
+    pub bidirectional: bool,
+    /// Cost of traversing the link, comparable to a distance walked on the navmesh.
+    /// Must be finite and not negative.
+    pub cost: f32,
+}
+
+impl OffMeshLink {
+    /// Panics if `cost` is negative or not finite.
+    pub fn new(start: Vec2, end: Vec2, bidirectional: bool, cost: f32) -> Self {
+        let link = OffMeshLink {
+            start,
+            end,
+            bidirectional,
+            cost,
+        };
+        link.check_cost();
+        link
+    }
+
+    fn check_cost(&self) {
+        assert!(
+            self.cost.is_finite() && self.cost >= 0f32,
+            "off-mesh link cost must be finite and not negative, got {}",
+            self.cost
+        );
+    }
+}
+
+/// Off-mesh links taken into account by path queries on the `NavMesh` of the same entity.
+#[derive(Component, Clone, Debug, Default)]
+pub struct OffMeshLinks(pub Vec<OffMeshLink>);
+
+/// Part of a path, with positions on the navmesh plane or in the world.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment<P = Vec2> {
+    /// Points walked on the navmesh, starting with the segment start.
+    Walk(Vec<P>),
+    /// Traversal of `OffMeshLinks.0[link_index]`, from `start` to `end`.
+    Link { link_index: usize, start: P, end: P },
+}
+
+impl<P> PathSegment<P> {
+    /// Same segment with each position mapped by `f`.
+    pub fn map<Q>(&self, mut f: impl FnMut(&P) -> Q) -> PathSegment<Q> {
+        match self {
+            PathSegment::Walk(points) => PathSegment::Walk(points.iter().map(f).collect()),
+            PathSegment::Link {
+                link_index,
+                start,
+                end,
+            } => PathSegment::Link {
+                link_index: *link_index,
+                start: f(start),
+                end: f(end),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkedPath<P = Vec2> {
+    pub segments: Vec<PathSegment<P>>,
+    /// Walked length plus the cost of traversed links.
+    pub cost: f32,
+}
+
+impl<P: Copy + PartialEq> LinkedPath<P> {
+    /// Same path with each position mapped by `f`, like from the navmesh plane to the world.
+    pub fn map<Q>(&self, mut f: impl FnMut(&P) -> Q) -> LinkedPath<Q> {
+        LinkedPath {
+            segments: self
+                .segments
+                .iter()
+                .map(|segment| segment.map(&mut f))
+                .collect(),
+            cost: self.cost,
+        }
+    }
+
+    /// All points of the path, link traversals being straight lines.
+    pub fn points(&self) -> Vec<P> {
+        let mut points: Vec<P> = Vec::new();
+        for segment in self.segments.iter() {
+            let segment_points = match segment {
+                PathSegment::Walk(points) => points.clone(),
+                PathSegment::Link { start, end, .. } => vec![*start, *end],
+            };
+            for p in segment_points {
+                if points.last() != Some(&p) {
+                    points.push(p);
+                }
+            }
+        }
+        points
+    }
+}
+
+/// A link traversed in one direction.
+struct Traversal {
+    link_index: usize,
+    start: Vec2,
+    end: Vec2,
+    cost: f32,
+}
+
+impl NavMesh {
+    /// Path between `from` and `to`, possibly going through `links`.
+    ///
+    /// Runs a Dijkstra over link endpoints, walking between them with polyanya.
+    /// Panics if a link cost is negative or not finite.
+    pub fn path_with_links(
+        &self,
+        from: Vec2,
+        to: Vec2,
+        links: &[OffMeshLink],
+    ) -> Option<LinkedPath> {
+        let traversals: Vec<Traversal> = links
+            .iter()
+            .enumerate()
+            .flat_map(|(link_index, link)| {
+                link.check_cost();
+                let forward = Traversal {
+                    link_index,
+                    start: link.start,
+                    end: link.end,
+                    cost: link.cost,
+                };
+                let backward = link.bidirectional.then_some(Traversal {
+                    link_index,
+                    start: link.end,
+                    end: link.start,
+                    cost: link.cost,
+                });
+                std::iter::once(forward).chain(backward)
+            })
+            .collect();
+        // Node 0 is `from`, node 1 is `to`, then the end of each traversal.
+        let node_position = |node: usize| match node {
+            0 => from,
+            1 => to,
+            n => traversals[n - 2].end,
+        };
+        let nb_nodes = traversals.len() + 2;
+        let mut costs = vec![f32::INFINITY; nb_nodes];
+        // For each reached node, the node it was reached from and the walk before it.
+        let mut previous: Vec<Option<(usize, Vec<Vec2>)>> = vec![None; nb_nodes];
+        let mut visited = vec![false; nb_nodes];
+        costs[0] = 0f32;
+
+        loop {
+            let current = (0..nb_nodes)
+                .filter(|n| !visited[*n] && costs[*n].is_finite())
+                .min_by(|a, b| costs[*a].total_cmp(&costs[*b]))?;
+            if current == 1 {
+                break;
+            }
+            visited[current] = true;
+            let position = node_position(current);
+
+            // Walk directly to `to`, or to the start of a traversal then take it.
+            let targets = std::iter::once((1, to, 0f32)).chain(
+                traversals
+                    .iter()
+                    .enumerate()
+                    .map(|(t, traversal)| (t + 2, traversal.start, traversal.cost)),
+            );
+            for (node, walk_to, link_cost) in targets {
+                if visited[node] {
+                    continue;
+                }
+                if let Some((walk, length)) = self.walk(position, walk_to) {
+                    let cost = costs[current] + length + link_cost;
+                    if cost < costs[node] {
+                        costs[node] = cost;
+                        previous[node] = Some((current, walk));
+                    }
+                }
+            }
+        }
+
+        let mut segments = Vec::new();
+        let mut node = 1;
+        while let Some((from_node, walk)) = previous[node].take() {
+            if node >= 2 {
+                let traversal = &traversals[node - 2];
+                segments.push(PathSegment::Link {
+                    link_index: traversal.link_index,
+                    start: traversal.start,
+                    end: traversal.end,
+                });
+            }
+            segments.push(PathSegment::Walk(walk));
+            node = from_node;
+        }
+        segments.reverse();
+        Some(LinkedPath {
+            segments,
+            cost: costs[1],
+        })
+    }
+
+    /// Points walked from `from` to `to` (including `from`) and their length.
+    fn walk(&self, from: Vec2, to: Vec2) -> Option<(Vec<Vec2>, f32)> {
+        if from.distance_squared(to) < f32::EPSILON {
+            return Some((vec![from], 0f32));
+        }
+        let path = self.path(from, to)?;
+        Some((
+            std::iter::once(from).chain(path.path).collect(),
+            path.length,
+        ))
+    }
+}
+
+/// Renders `OffMeshLinks` as arcs above the navmesh.
+#[cfg(feature = "render")]
+pub struct OffMeshLinkPlugin;
+
+#[cfg(feature = "render")]
+impl Plugin for OffMeshLinkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlaneMapping>()
+            .add_startup_system(setup_link_materials)
+            .add_system(update_link_visuals);
+    }
+}
+
+#[cfg(feature = "render")]
+struct OffMeshLinkMaterials {
+    one_way: Handle<PolylineMaterial>,
+    bidirectional: Handle<PolylineMaterial>,
+}
+
+/// Marks polylines spawned to render a link, as children of the `OffMeshLinks` entity.
+#[cfg(feature = "render")]
+#[derive(Component)]
+struct OffMeshLinkVisual;
+
+#[cfg(feature = "render")]
+fn setup_link_materials(
+    mut commands: Commands,
+    mut polyline_materials: ResMut<Assets<PolylineMaterial>>,
+) {
+    commands.insert_resource(OffMeshLinkMaterials {
+        one_way: polyline_materials.add(PolylineMaterial {
+            width: 3.0,
+            color: Color::ORANGE,
+            perspective: true,
+            ..Default::default()
+        }),
+        bidirectional: polyline_materials.add(PolylineMaterial {
+            width: 3.0,
+            color: Color::CYAN,
+            perspective: true,
+            ..Default::default()
+        }),
+    });
+}
+
+#[cfg(feature = "render")]
+fn update_link_visuals(
+    mut commands: Commands,
+    materials: Res<OffMeshLinkMaterials>,
+    mapping: Res<PlaneMapping>,
+    mut polylines: ResMut<Assets<Polyline>>,
+    q_links: Query<(Entity, &OffMeshLinks, Option<&Children>), Changed<OffMeshLinks>>,
+    q_visuals: Query<(), With<OffMeshLinkVisual>>,
+) {
+    const ARC_SEGMENTS: usize = 16;
+    for (e, links, children) in q_links.iter() {
+        for child in children.iter().flat_map(|c| c.iter()) {
+            if q_visuals.get(*child).is_ok() {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        commands.entity(e).add_children(|parent| {
+            for link in links.0.iter() {
+                let height = link.start.distance(link.end) / 4f32;
+                let vertices = (0..=ARC_SEGMENTS)
+                    .map(|i| {
+                        let t = i as f32 / ARC_SEGMENTS as f32;
+                        let p = link.start.lerp(link.end, t);
+                        mapping.to_world_offset(p, 4f32 * height * t * (1f32 - t))
+                    })
+                    .collect();
+                parent
+                    .spawn_bundle(PolylineBundle {
+                        polyline: polylines.add(Polyline {
+                            vertices,
+                            ..Default::default()
+                        }),
+                        material: if link.bidirectional {
+                            materials.bidirectional.clone()
+                        } else {
+                            materials.one_way.clone()
+                        },
+                        ..Default::default()
+                    })
+                    .insert(OffMeshLinkVisual);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec2;
+
+    use crate::test_fixtures::two_quads;
+
+    use super::{OffMeshLink, PathSegment};
+
+    #[test]
+    fn path_through_link() {
+        let navmesh = two_quads();
+        let from = Vec2::new(0.5, 0.5);
+        let to = Vec2::new(4.7, 0.5);
+        assert!(navmesh.path(from, to).is_none());
+        let links = [OffMeshLink {
+            start: Vec2::new(1.0, 0.5),
+            end: Vec2::new(4.2, 0.5),
+            bidirectional: false,
+            cost: 1.0,
+        }];
+        let path = navmesh.path_with_links(from, to, &links).unwrap();
+        assert_eq!(path.segments.len(), 3);
+        assert_eq!(
+            path.segments[1],
+            PathSegment::Link {
+                link_index: 0,
+                start: Vec2::new(1.0, 0.5),
+                end: Vec2::new(4.2, 0.5),
+            }
+        );
+        assert!((path.cost - 2.0).abs() < 0.001);
+        assert!(navmesh.path_with_links(to, from, &links).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "off-mesh link cost must be finite and not negative")]
+    fn negative_link_cost() {
+        OffMeshLink::new(Vec2::ZERO, Vec2::ONE, true, -1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "off-mesh link cost must be finite and not negative")]
+    fn non_finite_link_cost() {
+        let links = [OffMeshLink {
+            start: Vec2::new(1.0, 0.5),
+            end: Vec2::new(4.2, 0.5),
+            bidirectional: false,
+            cost: f32::NAN,
+        }];
+        two_quads().path_with_links(Vec2::new(0.5, 0.5), Vec2::new(4.7, 0.5), &links);
+    }
+}
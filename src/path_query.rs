@@ -0,0 +1,174 @@
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use bevy_polyline::prelude::*;
+
+use crate::{
+    navmesh::NavMesh,
+    offmesh::OffMeshLinks,
+    plane::PlaneMapping,
+    ray_from_mouse_position,
+    ray_picking::{NavMeshHeights, RayPicking},
+};
+
+/// Interactive path queries: middle click adds a step to the displayed path, right click clears it.
+///
+/// Clicks go through the first `Camera3d`.
+/// The first step picks the `NavMesh` entity under the mouse, next steps stay on it.
+/// Paths follow the `GlobalTransform` of that entity.
+///
+/// Needs `PolylinePlugin`. Clicks are picked as set by the `RayPicking` resource,
+/// on the plane by default, no physics engine is involved.
+///
+/// In 2D, see `render_2d::PathQuery2dPlugin`.
+pub struct PathQueryPlugin;
+
+impl Plugin for PathQueryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlaneMapping>()
+            .init_resource::<RayPicking>()
+            .init_resource::<PathToDisplay<Camera3d>>()
+            .add_startup_system(setup_path_display)
+            .add_system(click_pathfinding::<Camera3d>)
+            .add_system(update_path_display);
+    }
+}
+
+/// Steps of the path clicked through the camera with the `C` component.
+pub struct PathToDisplay<C> {
+    /// On the plane of `navmesh`.
+    pub steps: Vec<Vec2>,
+    pub navmesh: Option<Entity>,
+    camera: PhantomData<fn() -> C>,
+}
+
+impl<C> Default for PathToDisplay<C> {
+    fn default() -> Self {
+        PathToDisplay {
+            steps: Vec::new(),
+            navmesh: None,
+            camera: PhantomData,
+        }
+    }
+}
+
+/// Adds a step on middle click through the first camera with the `C` component,
+/// clears the path on right click. Clicks with control held are left to the editor.
+pub(crate) fn click_pathfinding<C: Component>(
+    mut path_to_display: ResMut<PathToDisplay<C>>,
+    windows: Res<Windows>,
+    navmeshes: Query<(
+        Entity,
+        &NavMesh,
+        Option<&OffMeshLinks>,
+        Option<&NavMeshHeights>,
+        Option<&GlobalTransform>,
+    )>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mapping: Res<PlaneMapping>,
+    picking: Res<RayPicking>,
+    cameras: Query<(&Camera, &GlobalTransform), With<C>>,
+) {
+    if keys.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        return;
+    }
+    if buttons.just_pressed(MouseButton::Right) {
+        path_to_display.steps.clear();
+        path_to_display.navmesh = None;
+        return;
+    }
+    if !buttons.just_pressed(MouseButton::Middle) {
+        return;
+    }
+    let hit = windows.get_primary().and_then(|window| {
+        let (camera, camera_transform) = cameras.iter().next()?;
+        let (origin, direction) = ray_from_mouse_position(window, camera, camera_transform);
+        picking.pick_navmesh(
+            origin,
+            direction,
+            &mapping,
+            navmeshes
+                .iter()
+                .filter(|(e, ..)| {
+                    path_to_display
+                        .navmesh
+                        .map_or(true, |navmesh| navmesh == *e)
+                })
+                .map(|(e, navmesh, _, heights, transform)| (e, navmesh, heights, transform)),
+        )
+    });
+    let (e, position) = match hit {
+        Some((e, hit)) => (e, hit.position),
+        None => return,
+    };
+    let (_, navmesh, links, _, _) = navmeshes.get(e).unwrap();
+    if !navmesh.navmesh.point_in_mesh(position) {
+        info!("point not in mesh");
+        return;
+    }
+    if let Some(last_pos) = path_to_display.steps.last() {
+        let links = links.map(|links| links.0.as_slice()).unwrap_or_default();
+        if let Some(path) = navmesh.path_with_links(*last_pos, position, links) {
+            for p in path.points().into_iter().skip(1) {
+                path_to_display.steps.push(p);
+            }
+        }
+    } else {
+        path_to_display.steps.push(position);
+        path_to_display.navmesh = Some(e);
+    }
+}
+
+#[derive(Default)]
+struct PolylineAssets {
+    polyline: Handle<Polyline>,
+}
+
+fn setup_path_display(
+    mut commands: Commands,
+    mut polyline_materials: ResMut<Assets<PolylineMaterial>>,
+    mut polylines: ResMut<Assets<Polyline>>,
+) {
+    let polyline = polylines.add(Polyline {
+        vertices: vec![-Vec3::ONE, Vec3::ONE],
+        ..Default::default()
+    });
+    commands.insert_resource(PolylineAssets {
+        polyline: polyline.clone(),
+    });
+    commands.spawn_bundle(PolylineBundle {
+        polyline: polyline,
+        material: polyline_materials.add(PolylineMaterial {
+            width: 3.0,
+            color: Color::RED,
+            perspective: true,
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+}
+
+fn update_path_display(
+    path_to_display: Res<PathToDisplay<Camera3d>>,
+    mapping: Res<PlaneMapping>,
+    polyline: Res<PolylineAssets>,
+    mut polylines: ResMut<Assets<Polyline>>,
+    q_transforms: Query<(&GlobalTransform, ChangeTrackers<GlobalTransform>)>,
+) {
+    let (transform, moved) = path_to_display
+        .navmesh
+        .and_then(|navmesh| q_transforms.get(navmesh).ok())
+        .map(|(transform, tracker)| (*transform, tracker.is_changed()))
+        .unwrap_or_default();
+    if !path_to_display.is_changed() && !moved {
+        return;
+    }
+    if let Some(polyline_to_change) = polylines.get_mut(&polyline.polyline) {
+        polyline_to_change.vertices = path_to_display
+            .steps
+            .iter()
+            .map(|s| mapping.to_world_with(&transform, *s))
+            .collect();
+    }
+}
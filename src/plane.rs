@@ -0,0 +1,186 @@
+//! Where the navmesh plane lies in the world.
+//!
+//! Navmeshes are 2D, `PlaneMapping` converts their points to world positions and back,
+//! so the same meshes work in 3D worlds with Y up and in Bevy 2D.
+//! Insert it as a resource before adding plugins to change it, the default is `XZ`.
+
+use bevy::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaneMapping {
+    /// Navmesh `(x, y)` is world `(x, 0, y)`, seen from above: 3D worlds with Y up.
+    XZ,
+    /// Navmesh `(x, y)` is world `(x, y, 0)`, seen from +Z: Bevy 2D.
+    XY,
+    /// Navmesh `(x, y)` is world `origin + x * x_axis + y * y_axis`,
+    /// seen from the side of `x_axis.cross(y_axis)`. Axes should be orthogonal.
+    Custom {
+        origin: Vec3,
+        x_axis: Vec3,
+        y_axis: Vec3,
+    },
+}
+
+impl Default for PlaneMapping {
+    fn default() -> Self {
+        PlaneMapping::XZ
+    }
+}
+
+impl PlaneMapping {
+    /// Origin, x axis and y axis of the plane in the world.
+    pub fn axes(&self) -> (Vec3, Vec3, Vec3) {
+        match *self {
+            PlaneMapping::XZ => (Vec3::ZERO, Vec3::X, Vec3::Z),
+            PlaneMapping::XY => (Vec3::ZERO, Vec3::X, Vec3::Y),
+            PlaneMapping::Custom {
+                origin,
+                x_axis,
+                y_axis,
+            } => (origin, x_axis, y_axis),
+        }
+    }
+
+    /// Side the plane is seen from, debug meshes face it and offsets go towards it.
+    pub fn normal(&self) -> Vec3 {
+        match *self {
+            PlaneMapping::XZ => Vec3::Y,
+            PlaneMapping::XY => Vec3::Z,
+            PlaneMapping::Custom { x_axis, y_axis, .. } => x_axis.cross(y_axis).normalize(),
+        }
+    }
+
+    pub fn to_world(&self, point: Vec2) -> Vec3 {
+        let (origin, x_axis, y_axis) = self.axes();
+        origin + x_axis * point.x + y_axis * point.y
+    }
+
+    /// World position of `point`, `offset` above the plane, to draw over other meshes.
+    pub fn to_world_offset(&self, point: Vec2, offset: f32) -> Vec3 {
+        self.to_world(point) + self.normal() * offset
+    }
+
+    /// Projects a world position on the plane.
+    pub fn to_plane(&self, position: Vec3) -> Vec2 {
+        let (origin, x_axis, y_axis) = self.axes();
+        let relative = position - origin;
+        Vec2::new(
+            relative.dot(x_axis) / x_axis.length_squared(),
+            relative.dot(y_axis) / y_axis.length_squared(),
+        )
+    }
+
+    /// Intersection of a ray with the plane, `None` if parallel or behind the ray origin.
+    pub fn ray_intersection(&self, origin: Vec3, direction: Vec3) -> Option<Vec2> {
+        let (plane_origin, _, _) = self.axes();
+        let normal = self.normal();
+        let denominator = direction.dot(normal);
+        if denominator.abs() < f32::EPSILON {
+            return None;
+        }
+        let t = (plane_origin - origin).dot(normal) / denominator;
+        (t >= 0.0).then(|| self.to_plane(origin + direction * t))
+    }
+
+    /// World position of `point`, on the plane of a navmesh placed in the world by `transform`.
+    pub fn to_world_with(&self, transform: &GlobalTransform, point: Vec2) -> Vec3 {
+        transform
+            .compute_matrix()
+            .transform_point3(self.to_world(point))
+    }
+
+    /// Projects a world position on the plane of a navmesh placed in the world by `transform`.
+    pub fn to_plane_with(&self, transform: &GlobalTransform, position: Vec3) -> Vec2 {
+        self.to_plane(
+            transform
+                .compute_matrix()
+                .inverse()
+                .transform_point3(position),
+        )
+    }
+
+    /// Orders a counter clockwise navmesh triangle so that it faces `normal()` in the world.
+    pub fn front_facing(&self, [a, b, c]: [u32; 3]) -> [u32; 3] {
+        let (_, x_axis, y_axis) = self.axes();
+        if x_axis.cross(y_axis).dot(self.normal()) < 0.0 {
+            [a, c, b]
+        } else {
+            [a, b, c]
+        }
+    }
+}
+
+/// A world ray in the local space of an entity placed by `transform`.
+///
+/// Distances along the ray, in units of its direction, are the same in both spaces.
+pub fn ray_to_local(transform: &GlobalTransform, origin: Vec3, direction: Vec3) -> (Vec3, Vec3) {
+    let inverse = transform.compute_matrix().inverse();
+    (
+        inverse.transform_point3(origin),
+        inverse.transform_vector3(direction),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use bevy::prelude::*;
+
+    use super::{ray_to_local, PlaneMapping};
+
+    #[test]
+    fn round_trips() {
+        let point = Vec2::new(3.0, -2.0);
+        let custom = PlaneMapping::Custom {
+            origin: Vec3::new(1.0, 2.0, 3.0),
+            x_axis: Vec3::X * 2.0,
+            y_axis: -Vec3::Y,
+        };
+        for mapping in [PlaneMapping::XZ, PlaneMapping::XY, custom] {
+            let position = mapping.to_world_offset(point, 0.5);
+            assert!(mapping.to_plane(position).abs_diff_eq(point, 1e-5));
+        }
+        assert_eq!(PlaneMapping::XZ.to_world(point), Vec3::new(3.0, 0.0, -2.0));
+        assert_eq!(PlaneMapping::XY.to_world(point), Vec3::new(3.0, -2.0, 0.0));
+    }
+
+    #[test]
+    fn ray_hits_plane() {
+        let xz = PlaneMapping::XZ;
+        let hit = xz.ray_intersection(Vec3::new(1.0, 10.0, 2.0), Vec3::new(0.0, -2.0, 0.0));
+        assert_eq!(hit, Some(Vec2::new(1.0, 2.0)));
+        assert_eq!(
+            xz.ray_intersection(Vec3::new(1.0, 10.0, 2.0), Vec3::Y),
+            None
+        );
+        let xy = PlaneMapping::XY;
+        let hit = xy.ray_intersection(Vec3::new(4.0, 5.0, 100.0), -Vec3::Z);
+        assert_eq!(hit, Some(Vec2::new(4.0, 5.0)));
+    }
+
+    #[test]
+    fn triangles_face_the_viewer() {
+        // Counter clockwise in the navmesh is clockwise seen from above in XZ.
+        assert_eq!(PlaneMapping::XZ.front_facing([0, 1, 2]), [0, 2, 1]);
+        assert_eq!(PlaneMapping::XY.front_facing([0, 1, 2]), [0, 1, 2]);
+    }
+
+    #[test]
+    fn placed_navmesh() {
+        let transform = GlobalTransform::from(
+            Transform::from_xyz(10.0, 1.0, 0.0).with_rotation(Quat::from_rotation_y(FRAC_PI_2)),
+        );
+        let xz = PlaneMapping::XZ;
+        let point = Vec2::new(2.0, 0.0);
+        // The navmesh X axis is turned towards -Z.
+        let position = xz.to_world_with(&transform, point);
+        assert!(position.abs_diff_eq(Vec3::new(10.0, 1.0, -2.0), 1e-5));
+        assert!(xz
+            .to_plane_with(&transform, position)
+            .abs_diff_eq(point, 1e-5));
+        let (origin, direction) = ray_to_local(&transform, position + Vec3::Y * 5.0, -Vec3::Y);
+        let hit = xz.ray_intersection(origin, direction).unwrap();
+        assert!(hit.abs_diff_eq(point, 1e-5));
+    }
+}
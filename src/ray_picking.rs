@@ -0,0 +1,246 @@
+//! Where a ray, usually under the mouse, hits navmeshes, without a physics engine.
+
+use bevy::prelude::*;
+
+use crate::{
+    navmesh::NavMesh,
+    plane::{ray_to_local, PlaneMapping},
+};
+
+/// How clicks find their position on navmeshes, used by `PathQueryPlugin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RayPicking {
+    /// Anywhere on the `PlaneMapping` plane, even outside of navmeshes.
+    Plane,
+    /// On navmesh polygons only. They are raised by the `NavMeshHeights` of their entity
+    /// if it has one, so hills and ramps are picked where they are drawn.
+    Polygons,
+}
+
+impl Default for RayPicking {
+    fn default() -> Self {
+        RayPicking::Plane
+    }
+}
+
+/// Height of each vertex of the `NavMesh` of the same entity, along the plane normal.
+///
+/// Only used for picking, path queries stay on the plane.
+#[derive(Component, Clone, Debug, Default)]
+pub struct NavMeshHeights(pub Vec<f32>);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    /// On the navmesh plane.
+    pub position: Vec2,
+    /// Distance along the ray, in units of its direction.
+    pub distance: f32,
+}
+
+impl RayPicking {
+    /// Closest hit of the ray on `navmeshes`, or on the plane for `RayPicking::Plane`.
+    pub fn pick<'a>(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        mapping: &PlaneMapping,
+        navmeshes: impl IntoIterator<Item = (&'a NavMesh, Option<&'a NavMeshHeights>)>,
+    ) -> Option<RayHit> {
+        match self {
+            RayPicking::Plane => ray_plane(origin, direction, mapping),
+            RayPicking::Polygons => navmeshes
+                .into_iter()
+                .filter_map(|(navmesh, heights)| {
+                    ray_navmesh(origin, direction, mapping, navmesh, heights)
+                })
+                .min_by(|a, b| a.distance.total_cmp(&b.distance)),
+        }
+    }
+
+    /// Closest navmesh entity hit by the ray, each navmesh being placed in the world
+    /// by its optional `GlobalTransform`. The hit position is on the plane of that navmesh.
+    ///
+    /// With `RayPicking::Plane`, hits in a navmesh come before closer hits out of any navmesh.
+    pub fn pick_navmesh<'a>(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        mapping: &PlaneMapping,
+        navmeshes: impl IntoIterator<
+            Item = (
+                Entity,
+                &'a NavMesh,
+                Option<&'a NavMeshHeights>,
+                Option<&'a GlobalTransform>,
+            ),
+        >,
+    ) -> Option<(Entity, RayHit)> {
+        navmeshes
+            .into_iter()
+            .filter_map(|(e, navmesh, heights, transform)| {
+                let (origin, direction) = match transform {
+                    Some(transform) => ray_to_local(transform, origin, direction),
+                    None => (origin, direction),
+                };
+                let hit = self.pick(origin, direction, mapping, [(navmesh, heights)])?;
+                let outside = navmesh.polygon_at(hit.position).is_none();
+                Some((outside, e, hit))
+            })
+            .min_by(|(a_outside, _, a), (b_outside, _, b)| {
+                a_outside
+                    .cmp(b_outside)
+                    .then(a.distance.total_cmp(&b.distance))
+            })
+            .map(|(_, e, hit)| (e, hit))
+    }
+}
+
+fn ray_plane(origin: Vec3, direction: Vec3, mapping: &PlaneMapping) -> Option<RayHit> {
+    let position = mapping.ray_intersection(origin, direction)?;
+    Some(RayHit {
+        position,
+        distance: (mapping.to_world(position) - origin).length() / direction.length(),
+    })
+}
+
+/// Closest hit of the ray on the polygons of `navmesh`.
+pub fn ray_navmesh(
+    origin: Vec3,
+    direction: Vec3,
+    mapping: &PlaneMapping,
+    navmesh: &NavMesh,
+    heights: Option<&NavMeshHeights>,
+) -> Option<RayHit> {
+    let heights = match heights {
+        Some(heights) => heights,
+        None => {
+            // Flat polygons: the plane hit, if it's in the navmesh.
+            let hit = ray_plane(origin, direction, mapping)?;
+            return navmesh.polygon_at(hit.position).map(|_| hit);
+        }
+    };
+    let world = |vertex: u32| {
+        let height = heights.0.get(vertex as usize).copied().unwrap_or_default();
+        mapping.to_world_offset(navmesh.navmesh.vertices[vertex as usize].coords, height)
+    };
+    navmesh
+        .navmesh
+        .polygons
+        .iter()
+        .flat_map(|polygon| {
+            (2..polygon.vertices.len()).map(|i| {
+                [
+                    polygon.vertices[0],
+                    polygon.vertices[i - 1],
+                    polygon.vertices[i],
+                ]
+            })
+        })
+        .filter_map(|triangle| {
+            let distance = ray_triangle(origin, direction, triangle.map(world))?;
+            Some(RayHit {
+                position: mapping.to_plane(origin + direction * distance),
+                distance,
+            })
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+/// Möller–Trumbore intersection, from both sides, `None` behind the ray origin.
+fn ray_triangle(origin: Vec3, direction: Vec3, [a, b, c]: [Vec3; 3]) -> Option<f32> {
+    let ab = b - a;
+    let ac = c - a;
+    let p = direction.cross(ac);
+    let determinant = ab.dot(p);
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let ao = origin - a;
+    let u = ao.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = ao.cross(ab);
+    let v = direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = ac.dot(q) * inverse;
+    (t >= 0.0).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::{plane::PlaneMapping, test_fixtures::two_quads};
+
+    use super::{NavMeshHeights, RayPicking};
+
+    #[test]
+    fn plane_and_flat_polygons() {
+        let navmesh = two_quads();
+        let mapping = PlaneMapping::XZ;
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        let inside = Vec3::new(0.5, 10.0, 0.5);
+        let outside = Vec3::new(3.0, 10.0, 0.5);
+        let hit = RayPicking::Plane.pick(outside, down, &mapping, [(&navmesh, None)]);
+        assert_eq!(hit.map(|hit| hit.position), Some(Vec2::new(3.0, 0.5)));
+        let hit = RayPicking::Polygons.pick(inside, down, &mapping, [(&navmesh, None)]);
+        assert_eq!(hit.map(|hit| hit.position), Some(Vec2::new(0.5, 0.5)));
+        let hit = RayPicking::Polygons.pick(outside, down, &mapping, [(&navmesh, None)]);
+        assert_eq!(hit, None);
+    }
+
+    #[test]
+    fn placed_navmeshes() {
+        let navmesh = two_quads();
+        let mapping = PlaneMapping::XZ;
+        let low = Entity::from_raw(0);
+        let high = Entity::from_raw(1);
+        let raised = GlobalTransform::from_translation(Vec3::new(10.0, 5.0, 0.0));
+        let navmeshes = || {
+            [
+                (low, &navmesh, None, None),
+                (high, &navmesh, None, Some(&raised)),
+            ]
+        };
+        let down = -Vec3::Y;
+        // Above both navmeshes, the raised one is hit first.
+        let hit = RayPicking::Polygons.pick_navmesh(
+            Vec3::new(10.5, 10.0, 0.5),
+            down,
+            &mapping,
+            navmeshes(),
+        );
+        assert_eq!(
+            hit.map(|(e, hit)| (e, hit.position)),
+            Some((high, Vec2::new(0.5, 0.5)))
+        );
+        // Only above the low navmesh: its plane is farther but the ray is in its polygons.
+        let hit =
+            RayPicking::Plane.pick_navmesh(Vec3::new(0.5, 10.0, 0.5), down, &mapping, navmeshes());
+        assert_eq!(
+            hit.map(|(e, hit)| (e, hit.position)),
+            Some((low, Vec2::new(0.5, 0.5)))
+        );
+    }
+
+    #[test]
+    fn raised_polygons() {
+        let navmesh = two_quads();
+        let heights = NavMeshHeights(vec![2.0; navmesh.navmesh.vertices.len()]);
+        let mapping = PlaneMapping::XZ;
+        // Slanted ray, crossing the y = 0 plane beyond the mesh but the raised polygons above it.
+        let origin = Vec3::new(0.25, 4.0, 0.5);
+        let direction = Vec3::new(1.0, -2.0, 0.0);
+        let hit = RayPicking::Polygons
+            .pick(origin, direction, &mapping, [(&navmesh, Some(&heights))])
+            .unwrap();
+        assert!(hit.position.abs_diff_eq(Vec2::new(1.25, 0.5), 1e-5));
+        assert!((hit.distance - 1.0).abs() < 1e-5);
+        let flat = RayPicking::Polygons.pick(origin, direction, &mapping, [(&navmesh, None)]);
+        assert_eq!(flat, None);
+    }
+}
@@ -0,0 +1,245 @@
+//! Navmeshes in Bevy 2D: debug rendering with 2D meshes,
+//! and path queries picking on the navmesh plane.
+//!
+//! Meant for a `Camera2dBundle`, with `PlaneMapping::XY` which these plugins insert
+//! if no `PlaneMapping` is there yet.
+
+use bevy::{
+    app::PluginGroupBuilder,
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use crate::{
+    navmesh::{NavMesh, NavMeshPlugin},
+    navmesh_debug::{edges_positions, fill_lines_mesh, fill_polygons_mesh, NavMeshDebugConfig},
+    path_query::{click_pathfinding, PathToDisplay},
+    plane::PlaneMapping,
+    ray_picking::RayPicking,
+};
+
+/// Navmesh, 2D debug rendering and 2D path queries.
+pub struct MeshquissePlugins2d;
+
+impl PluginGroup for MeshquissePlugins2d {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        group
+            .add(NavMeshPlugin)
+            .add(NavMesh2dDebugPlugin)
+            .add(PathQuery2dPlugin);
+    }
+}
+
+fn init_plane_mapping(app: &mut App) {
+    if !app.world.contains_resource::<PlaneMapping>() {
+        app.insert_resource(PlaneMapping::XY);
+    }
+}
+
+/// Draws every `NavMesh` like `NavMeshDebugPlugin` does, as 2D meshes.
+///
+/// Uses `NavMeshDebugConfig`, `height_offset` orders the layers, labels are not drawn.
+pub struct NavMesh2dDebugPlugin;
+
+impl Plugin for NavMesh2dDebugPlugin {
+    fn build(&self, app: &mut App) {
+        init_plane_mapping(app);
+        app.init_resource::<NavMeshDebugConfig>()
+            .add_startup_system(setup_debug_materials_2d)
+            .add_system(toggle_debug_visuals_2d)
+            .add_system(spawn_debug_visuals_2d)
+            .add_system(update_debug_visuals_2d);
+    }
+}
+
+struct NavMesh2dDebugMaterials {
+    polygons: Handle<ColorMaterial>,
+    boundary: Handle<ColorMaterial>,
+    portals: Handle<ColorMaterial>,
+}
+
+/// 2D debug meshes of a `NavMesh`, spawned as children of its entity.
+#[derive(Component)]
+pub struct NavMesh2dDebugVisual {
+    polygons: Handle<Mesh>,
+    boundary: Handle<Mesh>,
+    portals: Handle<Mesh>,
+}
+
+/// Marks entities spawned by this plugin, to toggle their visibility.
+#[derive(Component)]
+struct NavMesh2dDebugPart;
+
+fn setup_debug_materials_2d(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(NavMesh2dDebugMaterials {
+        polygons: materials.add(ColorMaterial::from(Color::rgba(1.0, 1.0, 1.0, 0.8))),
+        boundary: materials.add(ColorMaterial::from(Color::BLACK)),
+        portals: materials.add(ColorMaterial::from(Color::GRAY)),
+    });
+}
+
+fn toggle_debug_visuals_2d(
+    keys: Res<Input<KeyCode>>,
+    mut config: ResMut<NavMeshDebugConfig>,
+    mut q_parts: Query<&mut Visibility, With<NavMesh2dDebugPart>>,
+) {
+    if let Some(toggle_key) = config.toggle_key {
+        if keys.just_pressed(toggle_key) {
+            config.enabled = !config.enabled;
+        }
+    }
+    if !config.is_changed() {
+        return;
+    }
+    for mut visibility in q_parts.iter_mut() {
+        visibility.is_visible = config.enabled;
+    }
+}
+
+/// Polygons, boundary and portal meshes of a navmesh.
+fn debug_meshes_2d(navmesh: &NavMesh, mapping: &PlaneMapping, height: f32) -> [Mesh; 3] {
+    let mut polygons = Mesh::new(PrimitiveTopology::TriangleList);
+    fill_polygons_mesh(&mut polygons, &navmesh.navmesh, mapping, height);
+    let (boundary_positions, portal_positions) =
+        edges_positions(&navmesh.navmesh, mapping, height * 2f32);
+    let mut boundary = Mesh::new(PrimitiveTopology::LineList);
+    fill_lines_mesh(&mut boundary, boundary_positions, mapping);
+    let mut portals = Mesh::new(PrimitiveTopology::LineList);
+    fill_lines_mesh(&mut portals, portal_positions, mapping);
+    [polygons, boundary, portals]
+}
+
+fn spawn_debug_visuals_2d(
+    mut commands: Commands,
+    config: Res<NavMeshDebugConfig>,
+    mapping: Res<PlaneMapping>,
+    materials: Res<NavMesh2dDebugMaterials>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    q_navmeshes: Query<(Entity, &NavMesh), Without<NavMesh2dDebugVisual>>,
+) {
+    for (e, navmesh) in q_navmeshes.iter() {
+        let [polygons, boundary, portals] =
+            debug_meshes_2d(navmesh, &mapping, config.height_offset);
+        let visual = NavMesh2dDebugVisual {
+            polygons: meshes.add(polygons),
+            boundary: meshes.add(boundary),
+            portals: meshes.add(portals),
+        };
+        commands.entity(e).add_children(|parent| {
+            for (mesh, material) in [
+                (&visual.polygons, &materials.polygons),
+                (&visual.boundary, &materials.boundary),
+                (&visual.portals, &materials.portals),
+            ] {
+                parent
+                    .spawn_bundle(MaterialMesh2dBundle {
+                        mesh: Mesh2dHandle(mesh.clone()),
+                        material: material.clone(),
+                        visibility: Visibility {
+                            is_visible: config.enabled,
+                        },
+                        ..default()
+                    })
+                    .insert(NavMesh2dDebugPart);
+            }
+        });
+        commands.entity(e).insert(visual);
+    }
+}
+
+fn update_debug_visuals_2d(
+    config: Res<NavMeshDebugConfig>,
+    mapping: Res<PlaneMapping>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    q_navmeshes: Query<(&NavMesh, &NavMesh2dDebugVisual), Changed<NavMesh>>,
+) {
+    for (navmesh, visual) in q_navmeshes.iter() {
+        let new_meshes = debug_meshes_2d(navmesh, &mapping, config.height_offset);
+        for (handle, new_mesh) in [&visual.polygons, &visual.boundary, &visual.portals]
+            .into_iter()
+            .zip(new_meshes)
+        {
+            if let Some(mesh) = meshes.get_mut(handle) {
+                *mesh = new_mesh;
+            }
+        }
+    }
+}
+
+/// Interactive path queries, picking as set by `RayPicking`:
+/// middle click adds a step to the displayed path, right click clears it.
+///
+/// Like `PathQueryPlugin`, through the first `Camera2d`:
+/// the first step picks the `NavMesh` entity the path stays on.
+pub struct PathQuery2dPlugin;
+
+impl Plugin for PathQuery2dPlugin {
+    fn build(&self, app: &mut App) {
+        init_plane_mapping(app);
+        app.init_resource::<RayPicking>()
+            .init_resource::<PathToDisplay2d>()
+            .add_startup_system(setup_path_display_2d)
+            .add_system(click_pathfinding::<Camera2d>)
+            .add_system(update_path_display_2d);
+    }
+}
+
+/// Steps of the path drawn by `PathQuery2dPlugin`.
+pub type PathToDisplay2d = PathToDisplay<Camera2d>;
+
+struct PathDisplay2d {
+    mesh: Handle<Mesh>,
+}
+
+/// Above the navmesh debug layers.
+const PATH_HEIGHT: f32 = 1.0;
+
+fn setup_path_display_2d(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mesh = meshes.add(Mesh::new(PrimitiveTopology::LineStrip));
+    commands.spawn_bundle(MaterialMesh2dBundle {
+        mesh: Mesh2dHandle(mesh.clone()),
+        material: materials.add(ColorMaterial::from(Color::RED)),
+        ..default()
+    });
+    commands.insert_resource(PathDisplay2d { mesh });
+}
+
+fn update_path_display_2d(
+    path_to_display: Res<PathToDisplay2d>,
+    mapping: Res<PlaneMapping>,
+    display: Res<PathDisplay2d>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    q_transforms: Query<(&GlobalTransform, ChangeTrackers<GlobalTransform>)>,
+) {
+    let (transform, moved) = path_to_display
+        .navmesh
+        .and_then(|navmesh| q_transforms.get(navmesh).ok())
+        .map(|(transform, tracker)| (*transform, tracker.is_changed()))
+        .unwrap_or_default();
+    if !path_to_display.is_changed() && !moved {
+        return;
+    }
+    if let Some(mesh) = meshes.get_mut(&display.mesh) {
+        let positions: Vec<[f32; 3]> = path_to_display
+            .steps
+            .iter()
+            .map(|p| {
+                let offset = mapping.normal() * PATH_HEIGHT;
+                (mapping.to_world_with(&transform, *p) + offset).to_array()
+            })
+            .collect();
+        let nb_vertices = positions.len();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            vec![mapping.normal().to_array(); nb_vertices],
+        );
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; nb_vertices]);
+        mesh.set_indices(Some(Indices::U32((0..nb_vertices as u32).collect())));
+    }
+}
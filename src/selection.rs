@@ -0,0 +1,382 @@
+use bevy::prelude::*;
+use bevy_polyline::prelude::*;
+
+use crate::{
+    history::{EditCommand, EditHistory, VertexMove},
+    interact_mesh::{
+        apply_vertex_move, ConvexityPolicy, SelectedVertices, UpdateVertex, VertexMoveOutcome,
+    },
+    plane::PlaneMapping,
+    screen_to_ground,
+    snapping::SnapSettings,
+    vertex_index::VertexIndex,
+    MainCamera,
+};
+
+/// Rectangle and lasso selection of vertices of `EditableMesh` entities.
+///
+/// - `Alt` + left drag: rectangle selection.
+/// - `Alt` + `Shift` + left drag: lasso selection.
+/// - Hold `Ctrl` to add to the current selection.
+///
+/// Dragging the gizmo moves all selected vertices together,
+/// group transforms of `InteractMeshPlugin` act on the selection too.
+pub struct VertexSelectionPlugin;
+
+impl Plugin for VertexSelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectionDrag>()
+            .init_resource::<PlaneMapping>()
+            .add_startup_system(setup_selection_display)
+            .add_system(drag_selection)
+            .add_system(update_selection_display);
+    }
+}
+
+/// Selection area being drawn, in screen coordinates.
+#[derive(Default)]
+struct SelectionDrag {
+    points: Vec<Vec2>,
+    lasso: bool,
+}
+
+impl SelectionDrag {
+    /// Outline of the selection area.
+    fn outline(&self) -> Vec<Vec2> {
+        match (self.lasso, self.points.first(), self.points.last()) {
+            (false, Some(a), Some(b)) => vec![*a, Vec2::new(b.x, a.y), *b, Vec2::new(a.x, b.y)],
+            _ => self.points.clone(),
+        }
+    }
+}
+
+struct SelectionDisplay {
+    outline: Handle<Polyline>,
+}
+
+fn setup_selection_display(
+    mut commands: Commands,
+    mut polyline_materials: ResMut<Assets<PolylineMaterial>>,
+    mut polylines: ResMut<Assets<Polyline>>,
+) {
+    let outline = polylines.add(Polyline::default());
+    commands.spawn_bundle(PolylineBundle {
+        polyline: outline.clone(),
+        material: polyline_materials.add(PolylineMaterial {
+            width: 2.0,
+            color: Color::ORANGE,
+            perspective: false,
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    commands.insert_resource(SelectionDisplay { outline });
+}
+
+fn drag_selection(
+    mouse_button_input: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    mut drag: ResMut<SelectionDrag>,
+    mapping: Res<PlaneMapping>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut q_meshes: Query<(
+        &VertexIndex,
+        &mut SelectedVertices,
+        Option<&GlobalTransform>,
+    )>,
+) {
+    let cursor = match windows.get_primary().and_then(|w| w.cursor_position()) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    if mouse_button_input.just_pressed(MouseButton::Left)
+        && keys.any_pressed([KeyCode::LAlt, KeyCode::RAlt])
+    {
+        drag.points = vec![cursor];
+        drag.lasso = keys.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+        return;
+    }
+    if drag.points.is_empty() {
+        return;
+    }
+    if mouse_button_input.pressed(MouseButton::Left) {
+        if drag.lasso {
+            if drag.points.last() != Some(&cursor) {
+                drag.points.push(cursor);
+            }
+        } else {
+            drag.points.truncate(1);
+            drag.points.push(cursor);
+        }
+        return;
+    }
+    let outline = drag.outline();
+    drag.points.clear();
+    let (camera, camera_transform) = match q_camera.iter().next() {
+        Some(camera) => camera,
+        None => return,
+    };
+    let additive = keys.any_pressed([KeyCode::LControl, KeyCode::RControl]);
+    for (index, mut selected, mesh_transform) in q_meshes.iter_mut() {
+        let mesh_transform = mesh_transform.copied().unwrap_or_default();
+        let mut vertices = if additive {
+            selected.vertices.clone()
+        } else {
+            Vec::new()
+        };
+        for (vertex_id, position) in index.positions().iter().enumerate() {
+            let inside = camera
+                .world_to_viewport(
+                    camera_transform,
+                    mapping.to_world_with(&mesh_transform, *position),
+                )
+                .map_or(false, |p| point_in_polygon(p, &outline));
+            if inside && !vertices.contains(&(vertex_id as u32)) {
+                vertices.push(vertex_id as u32);
+            }
+        }
+        selected.vertices = vertices;
+    }
+}
+
+fn update_selection_display(
+    drag: Res<SelectionDrag>,
+    display: Res<SelectionDisplay>,
+    windows: Res<Windows>,
+    mapping: Res<PlaneMapping>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut polylines: ResMut<Assets<Polyline>>,
+) {
+    if !drag.is_changed() {
+        return;
+    }
+    let (window, (camera, camera_transform)) = match (windows.get_primary(), q_camera.iter().next())
+    {
+        (Some(window), Some(camera)) => (window, camera),
+        _ => return,
+    };
+    let outline = drag.outline();
+    // The outline is drawn on the ground, slightly above meshes.
+    let mut vertices: Vec<Vec3> = outline
+        .iter()
+        .filter_map(|p| screen_to_ground(window, camera, camera_transform, &mapping, None, *p))
+        .map(|p| mapping.to_world_offset(p, 0.2))
+        .collect();
+    if let Some(first) = vertices.first().copied() {
+        vertices.push(first);
+    }
+    if let Some(polyline) = polylines.get_mut(&display.outline) {
+        polyline.vertices = vertices;
+    }
+}
+
+/// Even-odd rule, `polygon` can be concave.
+fn point_in_polygon(point: Vec2, polygon: &[Vec2]) -> bool {
+    let mut inside = false;
+    for i in 0..polygon.len() {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Applies `transform` to `vertices`, recorded as a single history command.
+///
+/// Each vertex is moved like a drag moves it: snapped with `snap_settings`,
+/// then kept convex as set by `policy`.
+pub fn transform_vertices<MeshData: UpdateVertex + Clone + Send + Sync + 'static>(
+    mesh_data: &mut MeshData,
+    history: Option<&mut EditHistory<MeshData>>,
+    vertices: &[u32],
+    policy: ConvexityPolicy,
+    snap_settings: Option<&SnapSettings>,
+    transform: impl Fn(Vec2) -> Vec2,
+) {
+    let before = mesh_data.clone();
+    let mut moves = Vec::new();
+    let mut split = false;
+    for vertex_id in vertices {
+        let from = mesh_data.vertex_position(*vertex_id);
+        match apply_vertex_move(
+            mesh_data,
+            *vertex_id,
+            transform(from),
+            policy,
+            snap_settings,
+        ) {
+            VertexMoveOutcome::Moved(to) if to == from => {}
+            VertexMoveOutcome::Moved(to) => moves.push(VertexMove {
+                vertex_id: *vertex_id,
+                from,
+                to,
+            }),
+            VertexMoveOutcome::Split { .. } => split = true,
+        }
+    }
+    if let Some(history) = history {
+        if split {
+            let after = mesh_data.clone();
+            history.record(EditCommand::Replace { before, after });
+        } else if !moves.is_empty() {
+            history.record(EditCommand::MoveVertices(moves));
+        }
+    }
+}
+
+pub const GROUP_TRANSLATION_STEP: f32 = 0.1;
+/// In radians.
+pub const GROUP_ROTATION_STEP: f32 = std::f32::consts::PI / 12.0;
+pub const GROUP_SCALE_STEP: f32 = 1.1;
+
+/// Group transforms of selected vertices, around their center:
+///
+/// - Arrows: translate by `GROUP_TRANSLATION_STEP`.
+/// - `Comma` / `Period`: rotate by `GROUP_ROTATION_STEP`.
+/// - `Equals` / `Minus`: scale up / down by `GROUP_SCALE_STEP`.
+pub(crate) fn transform_selected_vertices<MeshData: UpdateVertex + Clone + Component>(
+    keys: Res<Input<KeyCode>>,
+    policy: Res<ConvexityPolicy>,
+    snap_settings: Option<Res<SnapSettings>>,
+    mut q_meshes: Query<(
+        &mut MeshData,
+        &SelectedVertices,
+        Option<&mut EditHistory<MeshData>>,
+    )>,
+) {
+    let step = GROUP_TRANSLATION_STEP;
+    let translation = [
+        (KeyCode::Left, Vec2::new(-step, 0.0)),
+        (KeyCode::Right, Vec2::new(step, 0.0)),
+        (KeyCode::Up, Vec2::new(0.0, -step)),
+        (KeyCode::Down, Vec2::new(0.0, step)),
+    ]
+    .into_iter()
+    .find(|(key, _)| keys.just_pressed(*key))
+    .map(|(_, translation)| translation);
+    let rotation = if keys.just_pressed(KeyCode::Comma) {
+        Some(GROUP_ROTATION_STEP)
+    } else if keys.just_pressed(KeyCode::Period) {
+        Some(-GROUP_ROTATION_STEP)
+    } else {
+        None
+    };
+    let scale = if keys.just_pressed(KeyCode::Equals) {
+        Some(GROUP_SCALE_STEP)
+    } else if keys.just_pressed(KeyCode::Minus) {
+        Some(1.0 / GROUP_SCALE_STEP)
+    } else {
+        None
+    };
+    if translation.is_none() && rotation.is_none() && scale.is_none() {
+        return;
+    }
+    for (mut mesh_data, selected, mut history) in q_meshes.iter_mut() {
+        let selected = &selected.vertices;
+        if selected.is_empty() {
+            continue;
+        }
+        let center = selected
+            .iter()
+            .map(|v| mesh_data.vertex_position(*v))
+            .sum::<Vec2>()
+            / selected.len() as f32;
+        let rotation = Mat2::from_angle(rotation.unwrap_or_default());
+        let scale = scale.unwrap_or(1.0);
+        let translation = translation.unwrap_or_default();
+        transform_vertices(
+            &mut *mesh_data,
+            history.as_deref_mut(),
+            selected,
+            *policy,
+            snap_settings.as_deref(),
+            |p| center + (rotation * (p - center)) * scale + translation,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec2;
+
+    use crate::{
+        history::EditHistory,
+        interact_mesh::{ConvexityPolicy, UpdateVertex},
+        mesh_data::only_triangles::TriangleMeshData,
+        snapping::SnapSettings,
+        tools::create_grid_trimesh,
+    };
+
+    use super::{point_in_polygon, transform_vertices};
+
+    #[test]
+    fn lasso_contains() {
+        let l_shape = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(1.0, 2.0),
+            Vec2::new(0.0, 2.0),
+        ];
+        assert!(point_in_polygon(Vec2::new(0.5, 1.5), &l_shape));
+        assert!(!point_in_polygon(Vec2::new(1.5, 1.5), &l_shape));
+    }
+
+    #[test]
+    fn group_transform_is_one_undo() {
+        let mut mesh_data = TriangleMeshData(create_grid_trimesh(2, 2, 1f32));
+        let mut history = EditHistory::<TriangleMeshData>::default();
+        transform_vertices(
+            &mut mesh_data,
+            Some(&mut history),
+            &[1, 3],
+            ConvexityPolicy::default(),
+            None,
+            |p| p * 2.0,
+        );
+        assert_eq!(mesh_data.vertex_position(3), Vec2::new(2.0, 2.0));
+        history.undo(&mut mesh_data);
+        assert_eq!(mesh_data.vertex_position(1), Vec2::new(1.0, 0.0));
+        assert_eq!(mesh_data.vertex_position(3), Vec2::new(1.0, 1.0));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn group_transform_snaps() {
+        let mut mesh_data = TriangleMeshData(create_grid_trimesh(2, 2, 1f32));
+        let mut history = EditHistory::<TriangleMeshData>::default();
+        let grid = SnapSettings {
+            grid: Some(1.0),
+            vertex_radius: None,
+            edge_radius: None,
+            ..Default::default()
+        };
+        let nudge = |p: Vec2| p + Vec2::splat(0.25);
+        transform_vertices(
+            &mut mesh_data,
+            Some(&mut history),
+            &[3],
+            ConvexityPolicy::default(),
+            Some(&grid),
+            nudge,
+        );
+        // Snapped back on the grid, nothing to undo.
+        assert_eq!(mesh_data.vertex_position(3), Vec2::new(1.0, 1.0));
+        assert!(!history.can_undo());
+        transform_vertices(
+            &mut mesh_data,
+            Some(&mut history),
+            &[3],
+            ConvexityPolicy::default(),
+            None,
+            nudge,
+        );
+        assert_eq!(mesh_data.vertex_position(3), Vec2::new(1.25, 1.25));
+    }
+}
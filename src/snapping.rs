@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+
+/// Snaps vertices dragged in an `EditableMesh`, see `SnapSettings`.
+pub struct SnappingPlugin;
+
+impl Plugin for SnappingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SnapSettings>()
+            .add_system(toggle_snapping);
+    }
+}
+
+/// Snapping of dragged vertices, in the plane space of the mesh given by `PlaneMapping`.
+///
+/// A vertex snaps first to other vertices, then to edges, then to the grid.
+pub struct SnapSettings {
+    pub enabled: bool,
+    /// Key toggling `enabled`.
+    pub toggle_key: Option<KeyCode>,
+    /// Size of the grid cells, no grid snapping if None or not positive.
+    pub grid: Option<f32>,
+    /// Distance under which a vertex snaps to another vertex.
+    pub vertex_radius: Option<f32>,
+    /// Welds a vertex snapped onto another one when the drag ends.
+    pub weld: bool,
+    /// Distance under which a vertex snaps onto an edge.
+    pub edge_radius: Option<f32>,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        SnapSettings {
+            enabled: true,
+            toggle_key: Some(KeyCode::G),
+            grid: None,
+            vertex_radius: Some(0.2),
+            weld: false,
+            edge_radius: Some(0.1),
+        }
+    }
+}
+
+/// What a position was snapped to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Snap {
+    Vertex(u32),
+    Edge([u32; 2]),
+    Grid,
+    None,
+}
+
+impl SnapSettings {
+    /// Snaps `position` of the vertex `vertex_id`,
+    /// `positions` and `edges` being the vertices and edges of its mesh.
+    pub fn snap(
+        &self,
+        vertex_id: u32,
+        position: Vec2,
+        positions: &[Vec2],
+        edges: &[[u32; 2]],
+    ) -> (Vec2, Snap) {
+        if !self.enabled {
+            return (position, Snap::None);
+        }
+        if let Some(radius) = self.vertex_radius {
+            let closest = positions
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other as u32 != vertex_id)
+                .map(|(other, p)| (other as u32, *p, p.distance(position)))
+                .filter(|(_, _, distance)| *distance <= radius)
+                .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+            if let Some((other, p, _)) = closest {
+                return (p, Snap::Vertex(other));
+            }
+        }
+        if let Some(radius) = self.edge_radius {
+            let closest = edges
+                .iter()
+                .filter(|edge| !edge.contains(&vertex_id))
+                .filter_map(|edge| {
+                    closest_on_segment(
+                        position,
+                        positions[edge[0] as usize],
+                        positions[edge[1] as usize],
+                    )
+                    .map(|p| (*edge, p, p.distance(position)))
+                })
+                .filter(|(_, _, distance)| *distance <= radius)
+                .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+            if let Some((edge, p, _)) = closest {
+                return (p, Snap::Edge(edge));
+            }
+        }
+        if let Some(grid) = self.grid.filter(|grid| *grid > 0.0) {
+            return ((position / grid).round() * grid, Snap::Grid);
+        }
+        (position, Snap::None)
+    }
+}
+
+fn closest_on_segment(p: Vec2, a: Vec2, b: Vec2) -> Option<Vec2> {
+    let ab = b - a;
+    if ab.length_squared() == 0.0 {
+        return None;
+    }
+    let t = ((p - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0);
+    Some(a + ab * t)
+}
+
+fn toggle_snapping(keys: Res<Input<KeyCode>>, mut settings: ResMut<SnapSettings>) {
+    if let Some(toggle_key) = settings.toggle_key {
+        if keys.just_pressed(toggle_key) {
+            settings.enabled = !settings.enabled;
+            info!("snapping enabled: {}", settings.enabled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec2;
+
+    use super::{Snap, SnapSettings};
+
+    #[test]
+    fn snap_priorities() {
+        let settings = SnapSettings {
+            grid: Some(1.0),
+            ..Default::default()
+        };
+        let positions = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(5.0, 5.0),
+        ];
+        let edges = [[0, 1]];
+        assert_eq!(
+            settings.snap(2, Vec2::new(1.9, 0.1), &positions, &edges),
+            (Vec2::new(2.0, 0.0), Snap::Vertex(1))
+        );
+        assert_eq!(
+            settings.snap(2, Vec2::new(1.0, 0.05), &positions, &edges),
+            (Vec2::new(1.0, 0.0), Snap::Edge([0, 1]))
+        );
+        assert_eq!(
+            settings.snap(2, Vec2::new(4.3, 4.6), &positions, &edges),
+            (Vec2::new(4.0, 5.0), Snap::Grid)
+        );
+        // Edges of the dragged vertex are ignored.
+        assert_eq!(
+            settings.snap(1, Vec2::new(1.0, 0.05), &positions, &edges),
+            (Vec2::new(1.0, 0.0), Snap::Grid)
+        );
+    }
+
+    #[test]
+    fn no_grid_snapping_without_positive_size() {
+        for grid in [0.0, -1.0] {
+            let settings = SnapSettings {
+                grid: Some(grid),
+                ..Default::default()
+            };
+            let position = Vec2::new(4.3, 4.6);
+            assert_eq!(settings.snap(0, position, &[], &[]), (position, Snap::None));
+        }
+    }
+}
@@ -0,0 +1,23 @@
+//! Meshes shared by the tests of several modules.
+
+use std::io::Read;
+
+use crate::{
+    mesh_data::{merge_triangles::ConvexPolygonsMeshData, IntoPAMesh},
+    navmesh::NavMesh,
+    trianglemerger::MeshMerger,
+};
+
+/// Reads a mesh 2 or mesh 3 file, `path` is relative to the crate root.
+pub(crate) fn load(path: &str) -> MeshMerger {
+    let mut file = std::fs::File::open(path).unwrap();
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).unwrap();
+    MeshMerger::from_bytes(&buffer)
+}
+
+/// Squares from (0, 0) to (1.5, 1.5) and from (4, 0) to (5, 1).
+pub(crate) fn two_quads() -> NavMesh {
+    let mesh_data = ConvexPolygonsMeshData::from(&load("assets/meshes/two_quads.mesh"));
+    NavMesh::new(mesh_data.to_pa_mesh())
+}
@@ -0,0 +1,190 @@
+//! LDtk `.ldtk` projects, one level at a time, with levels saved in the project file.
+//!
+//! - Non zero cells of IntGrid layers named after an obstacle tag are blocked.
+//! - Entities named after an obstacle tag are carved out along their bounds.
+
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde_json::Value;
+
+use crate::mesh_data::merge_triangles::ConvexPolygonsMeshData;
+
+use super::{rectangle, TileMap, TileMapError, TileMapImportOptions};
+
+/// Reads a project and builds the navmesh of the level selected in `options`.
+pub fn read_ldtk(
+    path: impl AsRef<Path>,
+    options: &TileMapImportOptions,
+) -> Result<ConvexPolygonsMeshData, TileMapError> {
+    let text = std::fs::read_to_string(path)?;
+    let tile_map = tile_map_from_ldtk(&text, options)?;
+    Ok(tile_map.to_mesh_data(options.merge))
+}
+
+/// The map covers the level with tiles of the project default grid size.
+pub fn tile_map_from_ldtk(
+    text: &str,
+    options: &TileMapImportOptions,
+) -> Result<TileMap, TileMapError> {
+    let project: Value = serde_json::from_str(text)?;
+    let levels = project["levels"]
+        .as_array()
+        .ok_or_else(|| format_error("no levels in the project"))?;
+    let level = match &options.level {
+        Some(identifier) => levels
+            .iter()
+            .find(|level| level["identifier"].as_str() == Some(identifier))
+            .ok_or_else(|| format_error(&format!("no level named {identifier}")))?,
+        None => levels
+            .first()
+            .ok_or_else(|| format_error("no levels in the project"))?,
+    };
+    let layers = level["layerInstances"].as_array().ok_or_else(|| {
+        format_error("the level is saved in a separate file, disable external levels")
+    })?;
+    let grid_size = number(&project, "defaultGridSize")? as u32;
+    if grid_size == 0 {
+        return Err(format_error("invalid defaultGridSize"));
+    }
+    let level_size = Vec2::new(number(level, "pxWid")?, number(level, "pxHei")?);
+    let mut tile_map = TileMap::new(
+        (level_size.x / grid_size as f32).ceil() as u32,
+        (level_size.y / grid_size as f32).ceil() as u32,
+        Vec2::splat(grid_size as f32),
+    );
+
+    for layer in layers {
+        let identifier = layer["__identifier"].as_str().unwrap_or_default();
+        if !options.is_obstacle(identifier) {
+            continue;
+        }
+        let offset = Vec2::new(
+            number(layer, "__pxTotalOffsetX").unwrap_or(0.0),
+            number(layer, "__pxTotalOffsetY").unwrap_or(0.0),
+        );
+        match layer["__type"].as_str() {
+            Some("IntGrid") => {
+                let columns = number(layer, "__cWid")? as u32;
+                let cell_size = number(layer, "__gridSize")?;
+                let cells = layer["intGridCsv"].as_array().into_iter().flatten();
+                // Cells matching the tiles of the map block them, others are carved out.
+                let aligned = cell_size == grid_size as f32 && offset == Vec2::ZERO;
+                for (i, cell) in cells.enumerate() {
+                    if cell.as_u64().unwrap_or(0) == 0 {
+                        continue;
+                    }
+                    let (column, row) = (i as u32 % columns, i as u32 / columns);
+                    if aligned {
+                        tile_map.block(column, row);
+                    } else {
+                        tile_map.obstacles.push(rectangle(
+                            offset + Vec2::new(column as f32, row as f32) * cell_size,
+                            Vec2::splat(cell_size),
+                        ));
+                    }
+                }
+            }
+            Some("Entities") => {
+                for entity in layer["entityInstances"].as_array().into_iter().flatten() {
+                    tile_map.obstacles.push(entity_bounds(entity, offset)?);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // Entities named as obstacles, in any layer.
+    for layer in layers {
+        if options.is_obstacle(layer["__identifier"].as_str().unwrap_or_default()) {
+            continue;
+        }
+        let offset = Vec2::new(
+            number(layer, "__pxTotalOffsetX").unwrap_or(0.0),
+            number(layer, "__pxTotalOffsetY").unwrap_or(0.0),
+        );
+        for entity in layer["entityInstances"].as_array().into_iter().flatten() {
+            if options.is_obstacle(entity["__identifier"].as_str().unwrap_or_default()) {
+                tile_map.obstacles.push(entity_bounds(entity, offset)?);
+            }
+        }
+    }
+    Ok(tile_map)
+}
+
+/// Bounds of an entity, whose position is its pivot.
+fn entity_bounds(entity: &Value, offset: Vec2) -> Result<Vec<Vec2>, TileMapError> {
+    let position =
+        vec2(&entity["px"]).ok_or_else(|| format_error("missing or invalid px on entity"))?;
+    let pivot = vec2(&entity["__pivot"]).unwrap_or(Vec2::ZERO);
+    let size = Vec2::new(number(entity, "width")?, number(entity, "height")?);
+    Ok(rectangle(offset + position - pivot * size, size))
+}
+
+fn vec2(value: &Value) -> Option<Vec2> {
+    let array = value.as_array()?;
+    Some(Vec2::new(
+        array.get(0)?.as_f64()? as f32,
+        array.get(1)?.as_f64()? as f32,
+    ))
+}
+
+fn number(value: &Value, name: &str) -> Result<f32, TileMapError> {
+    value[name]
+        .as_f64()
+        .map(|number| number as f32)
+        .ok_or_else(|| format_error(&format!("missing or invalid {name}")))
+}
+
+fn format_error(message: &str) -> TileMapError {
+    TileMapError::Format(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::{
+        tilemap::{area, is_walkable},
+        trianglemerger::MeshMerger,
+    };
+
+    use super::{read_ldtk, tile_map_from_ldtk, TileMapImportOptions};
+
+    #[test]
+    fn collision_grid_and_entity() {
+        let mesh_data =
+            read_ldtk("assets/maps/room.ldtk", &TileMapImportOptions::default()).unwrap();
+        // 4 x 3 cells of 16 pixels, minus 2 collision cells and a 8 x 8 obstacle entity.
+        assert!((area(&mesh_data) - (12.0 * 256.0 - 2.0 * 256.0 - 64.0)).abs() < 1e-2);
+        assert_eq!(MeshMerger::from(&mesh_data).validate(), vec![]);
+    }
+
+    #[test]
+    fn not_mirrored() {
+        let text = std::fs::read_to_string("assets/maps/room.ldtk").unwrap();
+        let options = TileMapImportOptions::default();
+        let tile_map = tile_map_from_ldtk(&text, &options).unwrap();
+        let mesh_data = tile_map.to_mesh_data(true);
+        // Collision cell in the bottom right corner, obstacle entity in the top row.
+        assert!(!is_walkable(&mesh_data, &tile_map, Vec2::new(56.0, 40.0)));
+        assert!(is_walkable(&mesh_data, &tile_map, Vec2::new(56.0, 8.0)));
+        assert!(!is_walkable(&mesh_data, &tile_map, Vec2::new(44.0, 12.0)));
+        assert!(is_walkable(&mesh_data, &tile_map, Vec2::new(44.0, 36.0)));
+    }
+
+    #[test]
+    fn level_by_identifier() {
+        let options = TileMapImportOptions {
+            level: Some("Level_1".to_string()),
+            ..Default::default()
+        };
+        let mesh_data = read_ldtk("assets/maps/room.ldtk", &options).unwrap();
+        assert!((area(&mesh_data) - 12.0 * 256.0).abs() < 1e-2);
+        let options = TileMapImportOptions {
+            level: Some("Missing".to_string()),
+            ..Default::default()
+        };
+        assert!(read_ldtk("assets/maps/room.ldtk", &options).is_err());
+    }
+}
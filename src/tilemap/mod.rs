@@ -0,0 +1,212 @@
+//! Navmeshes of 2D tile maps made with Tiled or LDtk.
+//!
+//! Maps are read in map coordinates: pixels, with y going down from the top left corner of the map.
+//! Navmeshes are flipped to have y going up from the bottom left corner, so they aren't mirrored.
+
+pub mod ldtk;
+pub mod tiled;
+
+use std::{collections::HashMap, fmt};
+
+use bevy::prelude::*;
+
+use crate::{
+    islands::polygon_area,
+    mesh_data::merge_triangles::ConvexPolygonsMeshData,
+    tools::{convex_hull, triangulate_simple_polygon},
+    trianglemerger::{MeshMerger, Polygon, Vertex},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileMapImportOptions {
+    /// Object layers, LDtk IntGrid layers and LDtk entities whose name starts with one of these,
+    /// ignoring case, are obstacles.
+    pub obstacle_tags: Vec<String>,
+    /// Level of LDtk projects to read, the first one if `None`.
+    pub level: Option<String>,
+    /// Merges the tiles into convex polygons with `MeshMerger`.
+    pub merge: bool,
+}
+
+impl Default for TileMapImportOptions {
+    fn default() -> Self {
+        TileMapImportOptions {
+            obstacle_tags: vec!["collision".to_string(), "obstacle".to_string()],
+            level: None,
+            merge: true,
+        }
+    }
+}
+
+impl TileMapImportOptions {
+    pub fn is_obstacle(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.obstacle_tags
+            .iter()
+            .any(|tag| name.starts_with(&tag.to_lowercase()))
+    }
+}
+
+#[derive(Debug)]
+pub enum TileMapError {
+    Io(std::io::Error),
+    Xml(roxmltree::Error),
+    Json(serde_json::Error),
+    /// Valid file, but not what the importer expects.
+    Format(String),
+}
+
+impl fmt::Display for TileMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TileMapError::Io(error) => write!(f, "{error}"),
+            TileMapError::Xml(error) => write!(f, "{error}"),
+            TileMapError::Json(error) => write!(f, "{error}"),
+            TileMapError::Format(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for TileMapError {}
+
+impl From<std::io::Error> for TileMapError {
+    fn from(error: std::io::Error) -> Self {
+        TileMapError::Io(error)
+    }
+}
+
+impl From<roxmltree::Error> for TileMapError {
+    fn from(error: roxmltree::Error) -> Self {
+        TileMapError::Xml(error)
+    }
+}
+
+impl From<serde_json::Error> for TileMapError {
+    fn from(error: serde_json::Error) -> Self {
+        TileMapError::Json(error)
+    }
+}
+
+/// Walkable grid of a map, with obstacle shapes cut out of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileMap {
+    pub columns: u32,
+    pub rows: u32,
+    pub tile_size: Vec2,
+    /// Tiles which are not walkable, row by row from the top.
+    pub blocked: Vec<bool>,
+    /// Simple polygons, in map coordinates.
+    pub obstacles: Vec<Vec<Vec2>>,
+}
+
+impl TileMap {
+    /// A map where all tiles are walkable.
+    pub fn new(columns: u32, rows: u32, tile_size: Vec2) -> Self {
+        TileMap {
+            columns,
+            rows,
+            tile_size,
+            blocked: vec![false; (columns * rows) as usize],
+            obstacles: Vec::new(),
+        }
+    }
+
+    /// Tiles outside of the map are ignored.
+    pub fn block(&mut self, column: u32, row: u32) {
+        if column < self.columns && row < self.rows {
+            self.blocked[(row * self.columns + column) as usize] = true;
+        }
+    }
+
+    /// A quad per walkable tile, with obstacles carved out, merged if `merge` is set.
+    ///
+    /// y is flipped: the top left corner of the map is at `(0, rows * tile_size.y)`.
+    pub fn to_mesh_data(&self, merge: bool) -> ConvexPolygonsMeshData {
+        let height = self.rows as f32 * self.tile_size.y;
+        let flip = |p: Vec2| Vec2::new(p.x, height - p.y);
+        let mut mesh_data = ConvexPolygonsMeshData::default();
+        let mut corners: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut corner = |mesh_data: &mut ConvexPolygonsMeshData, column: u32, row: u32| {
+            *corners.entry((column, row)).or_insert_with(|| {
+                mesh_data.mesh_vertices.push(Vertex {
+                    p: flip(Vec2::new(column as f32, row as f32) * self.tile_size),
+                    polygons: Vec::new(),
+                });
+                mesh_data.mesh_vertices.len() as u32 - 1
+            })
+        };
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                if self.blocked[(row * self.columns + column) as usize] {
+                    continue;
+                }
+                // Counter clockwise once flipped.
+                let vertices = vec![
+                    corner(&mut mesh_data, column, row + 1),
+                    corner(&mut mesh_data, column + 1, row + 1),
+                    corner(&mut mesh_data, column + 1, row),
+                    corner(&mut mesh_data, column, row),
+                ];
+                mesh_data.mesh_polygons.push(Polygon {
+                    vertices,
+                    ..default()
+                });
+            }
+        }
+        mesh_data.rebuild_neighbours();
+        for obstacle in self.obstacles.iter() {
+            // Reversed to keep its orientation once flipped.
+            let obstacle: Vec<Vec2> = obstacle.iter().rev().map(|p| flip(*p)).collect();
+            let hull = convex_hull(&obstacle);
+            let hull_area = polygon_area(&hull);
+            if hull_area - polygon_area(&obstacle).abs() <= hull_area * 1e-4 {
+                mesh_data.carve(&hull);
+            } else {
+                for triangle in triangulate_simple_polygon(&obstacle) {
+                    mesh_data.carve(&triangle.map(|i| obstacle[i]));
+                }
+            }
+        }
+        if merge {
+            let mut mesh_merger = MeshMerger::from(&mesh_data);
+            mesh_merger.my_merge();
+            mesh_merger.remove_unused();
+            mesh_data = ConvexPolygonsMeshData::from(&mesh_merger);
+        }
+        mesh_data
+    }
+}
+
+/// Rectangle from its top left corner, counter clockwise in map coordinates.
+pub(crate) fn rectangle(position: Vec2, size: Vec2) -> Vec<Vec2> {
+    vec![
+        position,
+        position + Vec2::new(size.x, 0.0),
+        position + size,
+        position + Vec2::new(0.0, size.y),
+    ]
+}
+
+/// Tests if a point of map coordinates is walkable on the navmesh of that map.
+#[cfg(test)]
+pub(crate) fn is_walkable(
+    mesh_data: &ConvexPolygonsMeshData,
+    tile_map: &TileMap,
+    point: Vec2,
+) -> bool {
+    use crate::{mesh_data::IntoPAMesh, navmesh::NavMesh};
+
+    let height = tile_map.rows as f32 * tile_map.tile_size.y;
+    NavMesh::new(mesh_data.to_pa_mesh())
+        .polygon_at(Vec2::new(point.x, height - point.y))
+        .is_some()
+}
+
+#[cfg(test)]
+pub(crate) fn area(mesh_data: &ConvexPolygonsMeshData) -> f32 {
+    mesh_data
+        .mesh_polygons
+        .iter()
+        .map(|p| MeshMerger::get_area(&mesh_data.mesh_vertices, &p.vertices) / 2.0)
+        .sum()
+}
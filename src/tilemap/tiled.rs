@@ -0,0 +1,249 @@
+//! Tiled `.tmx` maps, orthogonal and finite, with tile layers saved as CSV or XML.
+//!
+//! - Non empty tiles of a tile layer with a `walkable` property set to `false` are blocked,
+//!   as are tiles whose tileset tile has that property.
+//! - Objects of object layers named after an obstacle tag, or with an `obstacle` property set
+//!   to `true`, are carved out: rectangles, ellipses (as polygons), polygons and tile objects.
+
+use std::{collections::HashSet, path::Path};
+
+use bevy::prelude::*;
+use roxmltree::{Document, Node};
+
+use crate::mesh_data::merge_triangles::ConvexPolygonsMeshData;
+
+use super::{rectangle, TileMap, TileMapError, TileMapImportOptions};
+
+/// Tiled stores flip flags in the highest bits of tile ids.
+const GID_MASK: u32 = 0x0FFF_FFFF;
+const ELLIPSE_SEGMENTS: usize = 12;
+
+/// Reads a map and the external tilesets it uses, then builds its navmesh.
+pub fn read_tmx(
+    path: impl AsRef<Path>,
+    options: &TileMapImportOptions,
+) -> Result<ConvexPolygonsMeshData, TileMapError> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)?;
+    let tile_map = tile_map_from_tmx(&text, path.parent(), options)?;
+    Ok(tile_map.to_mesh_data(options.merge))
+}
+
+/// External tilesets are read relative to `base_dir`, they are ignored without it.
+pub fn tile_map_from_tmx(
+    text: &str,
+    base_dir: Option<&Path>,
+    options: &TileMapImportOptions,
+) -> Result<TileMap, TileMapError> {
+    let document = Document::parse(text)?;
+    let map = document.root_element();
+    if map.attribute("orientation").unwrap_or("orthogonal") != "orthogonal" {
+        return Err(TileMapError::Format(
+            "only orthogonal maps are supported".to_string(),
+        ));
+    }
+    if map.attribute("infinite") == Some("1") {
+        return Err(TileMapError::Format(
+            "infinite maps are not supported".to_string(),
+        ));
+    }
+    let mut tile_map = TileMap::new(
+        attribute(map, "width")?,
+        attribute(map, "height")?,
+        Vec2::new(attribute(map, "tilewidth")?, attribute(map, "tileheight")?),
+    );
+
+    let mut blocked_gids = HashSet::new();
+    for tileset in map.children().filter(|n| n.has_tag_name("tileset")) {
+        let first_gid: u32 = attribute(tileset, "firstgid")?;
+        let ids = match (tileset.attribute("source"), base_dir) {
+            (Some(source), Some(base_dir)) => {
+                let external = std::fs::read_to_string(base_dir.join(source))?;
+                blocked_tile_ids(Document::parse(&external)?.root_element())?
+            }
+            (Some(_), None) => Vec::new(),
+            (None, _) => blocked_tile_ids(tileset)?,
+        };
+        blocked_gids.extend(ids.into_iter().map(|id| first_gid + id));
+    }
+
+    for layer in map.descendants().filter(|n| n.has_tag_name("layer")) {
+        let layer_blocks = property(layer, "walkable") == Some("false");
+        let data = match layer.children().find(|n| n.has_tag_name("data")) {
+            Some(data) => data,
+            None => continue,
+        };
+        let gids: Vec<u32> = match data.attribute("encoding") {
+            Some("csv") => data
+                .text()
+                .unwrap_or_default()
+                .split(',')
+                .map(|gid| {
+                    gid.trim()
+                        .parse()
+                        .map_err(|_| TileMapError::Format(format!("invalid tile id {gid}")))
+                })
+                .collect::<Result<_, _>>()?,
+            None => data
+                .children()
+                .filter(|n| n.has_tag_name("tile"))
+                .map(|tile| {
+                    tile.attribute("gid")
+                        .map_or(Ok(0), |_| attribute(tile, "gid"))
+                })
+                .collect::<Result<_, _>>()?,
+            Some(encoding) => {
+                return Err(TileMapError::Format(format!(
+                    "unsupported layer encoding {encoding}, save the map with CSV layers"
+                )))
+            }
+        };
+        for (i, gid) in gids.into_iter().enumerate() {
+            let gid = gid & GID_MASK;
+            if gid != 0 && (layer_blocks || blocked_gids.contains(&gid)) {
+                let i = i as u32;
+                tile_map.block(i % tile_map.columns, i / tile_map.columns);
+            }
+        }
+    }
+
+    for group in map.descendants().filter(|n| n.has_tag_name("objectgroup")) {
+        let is_obstacle = options.is_obstacle(group.attribute("name").unwrap_or_default())
+            || property(group, "obstacle") == Some("true");
+        if !is_obstacle {
+            continue;
+        }
+        for object in group.children().filter(|n| n.has_tag_name("object")) {
+            if let Some(obstacle) = object_shape(object)? {
+                tile_map.obstacles.push(obstacle);
+            }
+        }
+    }
+    Ok(tile_map)
+}
+
+/// Outline of an object in map coordinates, `None` for points, polylines and texts.
+fn object_shape(object: Node) -> Result<Option<Vec<Vec2>>, TileMapError> {
+    let number = |name: &str| -> Result<f32, TileMapError> {
+        object
+            .attribute(name)
+            .map_or(Ok(0.0), |_| attribute(object, name))
+    };
+    let position = Vec2::new(number("x")?, number("y")?);
+    let size = Vec2::new(number("width")?, number("height")?);
+    let child = |name: &str| object.children().find(|n| n.has_tag_name(name));
+    let local = if let Some(polygon) = child("polygon") {
+        polygon
+            .attribute("points")
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|point| {
+                point
+                    .split_once(',')
+                    .and_then(|(x, y)| Some(Vec2::new(x.parse().ok()?, y.parse().ok()?)))
+                    .ok_or_else(|| TileMapError::Format(format!("invalid point {point}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    } else if child("point").is_some() || child("polyline").is_some() || child("text").is_some() {
+        return Ok(None);
+    } else if size.x <= 0.0 || size.y <= 0.0 {
+        return Ok(None);
+    } else if child("ellipse").is_some() {
+        (0..ELLIPSE_SEGMENTS)
+            .map(|i| {
+                let angle = i as f32 / ELLIPSE_SEGMENTS as f32 * std::f32::consts::TAU;
+                size / 2.0 + Vec2::new(angle.cos(), angle.sin()) * size / 2.0
+            })
+            .collect()
+    } else if object.attribute("gid").is_some() {
+        // Tile objects are anchored at their bottom left corner.
+        rectangle(Vec2::new(0.0, -size.y), size)
+    } else {
+        rectangle(Vec2::ZERO, size)
+    };
+    // Rotation is clockwise on screen, around the object position.
+    let rotation = Mat2::from_angle(number("rotation")?.to_radians());
+    Ok(Some(
+        local.into_iter().map(|p| position + rotation * p).collect(),
+    ))
+}
+
+fn attribute<T: std::str::FromStr>(node: Node, name: &str) -> Result<T, TileMapError> {
+    node.attribute(name)
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| {
+            TileMapError::Format(format!(
+                "missing or invalid {name} on {}",
+                node.tag_name().name()
+            ))
+        })
+}
+
+/// Ids of the tiles of a tileset which are not walkable.
+fn blocked_tile_ids(tileset: Node) -> Result<Vec<u32>, TileMapError> {
+    tileset
+        .children()
+        .filter(|n| n.has_tag_name("tile"))
+        .filter(|tile| property(*tile, "walkable") == Some("false"))
+        .map(|tile| attribute(tile, "id"))
+        .collect()
+}
+
+/// Value of a custom property.
+fn property<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|n| n.has_tag_name("properties"))?
+        .children()
+        .find(|n| n.has_tag_name("property") && n.attribute("name") == Some(name))?
+        .attribute("value")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use bevy::prelude::*;
+
+    use crate::{
+        tilemap::{area, is_walkable},
+        trianglemerger::MeshMerger,
+    };
+
+    use super::{read_tmx, tile_map_from_tmx, TileMapImportOptions};
+
+    #[test]
+    fn wall_tile_and_collision_object() {
+        let mesh_data = read_tmx("assets/maps/room.tmx", &TileMapImportOptions::default()).unwrap();
+        // 4 x 3 tiles of 16 pixels, minus a wall tile and a 8 x 8 crate.
+        assert!((area(&mesh_data) - (12.0 * 256.0 - 256.0 - 64.0)).abs() < 1e-2);
+        assert_eq!(MeshMerger::from(&mesh_data).validate(), vec![]);
+    }
+
+    #[test]
+    fn not_mirrored() {
+        let text = std::fs::read_to_string("assets/maps/room.tmx").unwrap();
+        let options = TileMapImportOptions::default();
+        let tile_map = tile_map_from_tmx(&text, Some(Path::new("assets/maps")), &options).unwrap();
+        let mesh_data = tile_map.to_mesh_data(true);
+        // The crate is in the top row of the map, the navmesh has y going up.
+        assert!(!is_walkable(&mesh_data, &tile_map, Vec2::new(44.0, 12.0)));
+        assert!(is_walkable(&mesh_data, &tile_map, Vec2::new(44.0, 36.0)));
+        let top = mesh_data
+            .mesh_vertices
+            .iter()
+            .map(|v| v.p.y)
+            .fold(f32::MIN, f32::max);
+        assert_eq!(top, 48.0);
+        assert!(mesh_data.mesh_vertices.iter().all(|v| v.p.y >= 0.0));
+    }
+
+    #[test]
+    fn external_tileset_needs_base_dir() {
+        let text = std::fs::read_to_string("assets/maps/room.tmx").unwrap();
+        let options = TileMapImportOptions::default();
+        let tile_map = tile_map_from_tmx(&text, Some(Path::new("assets/maps")), &options).unwrap();
+        assert_eq!(tile_map.blocked.iter().filter(|b| **b).count(), 1);
+        let tile_map = tile_map_from_tmx(&text, None, &options).unwrap();
+        assert_eq!(tile_map.blocked.iter().filter(|b| **b).count(), 0);
+    }
+}
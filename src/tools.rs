@@ -1,90 +1,30 @@
-use bevy::prelude::{Vec2, Vec3};
-use polyanya::{Mesh as PAMesh, Polygon, Vertex};
+//! Render helpers on top of the mesh tools of `meshquisse_core`.
 
-#[derive(Debug, PartialEq, Default)]
-pub struct TriangleMesh {
-    pub indices: Vec<u32>,
-    pub positions: Vec<Vec2>,
-}
+#[cfg(feature = "render")]
+use bevy::prelude::{Color, Mesh};
 
-/// Triangulates convex polygons, complexity is O(n)
-pub fn triangulate(navmesh: &PAMesh) -> Vec<u32> {
-    navmesh
-        .polygons
-        .iter()
-        .flat_map(|p| {
-            (2..p.vertices.len()).flat_map(|i| [p.vertices[0], p.vertices[i], p.vertices[i - 1]])
-        })
-        .map(|v| v as u32)
-        .collect()
-}
+#[cfg(feature = "render")]
+use crate::plane::PlaneMapping;
 
-pub fn create_grid_trimesh(width: u32, height: u32, spacing: f32) -> TriangleMesh {
-    let to_index = |x: u32, y: u32| y * width + x;
-    let positions: Vec<Vec2> = (0..height)
-        .flat_map(|y| {
-            (0..width).map(move |x| {
-                let position: Vec2 = [x as f32, y as f32].into();
-                position * spacing
-            })
-        })
-        .collect();
-    let indices: Vec<u32> = (1..height)
-        .flat_map(|y| {
-            (1..width).flat_map(move |x| {
-                let mut triangles = [
-                    // bottom left triangle
-                    to_index(x - 1, y - 1),
-                    to_index(x, y - 1),
-                    to_index(x - 1, y),
-                    // top right triangle
-                    to_index(x, y),
-                    to_index(x - 1, y),
-                    to_index(x, y - 1),
-                ];
-                triangles
-            })
-        })
-        .collect();
-    TriangleMesh { indices, positions }
-}
+pub use meshquisse_core::tools::*;
 
-/// Returns an polyanya::Mesh, without any complex transformations,
-/// polygons are kept as triangles.
-/// (not implemented) For a more optimal solution, consider calling trimesh_to_convex_polygon_mesh()
-pub fn navmesh_from_trimesh(triangles_mesh: &TriangleMesh) -> PAMesh {
-    let mut vertices: Vec<Vertex> = triangles_mesh
-        .positions
-        .iter()
-        .map(|position| Vertex::new(*position, vec![]))
-        .collect();
-    let polygons: Vec<_> = (0..triangles_mesh.indices.len() / 3)
-        .map(|i| {
-            let i = i * 3;
-            let indexes = vec![
-                triangles_mesh.indices[i],
-                triangles_mesh.indices[i + 1],
-                triangles_mesh.indices[i + 2],
-            ];
-            Polygon::new(indexes, false)
-        })
-        .collect();
-    for (vertex_index, mut vertex) in vertices.iter_mut().enumerate() {
-        vertex.polygons = polygons
-            .iter()
-            .enumerate()
-            .filter_map(|(polygon_index, p)| {
-                p.vertices
-                    .contains(&(vertex_index as u32))
-                    .then_some(polygon_index as isize)
-            })
-            .collect::<Vec<isize>>();
+/// Distinct looking color for the polygon `index` out of `nb_polygons`.
+#[cfg(feature = "render")]
+pub fn polygon_color(index: usize, nb_polygons: usize) -> Color {
+    fn lerp(v0: f32, v1: f32, t: f32) -> f32 {
+        ((1.0 - t) * v0) + (t * v1)
     }
-    PAMesh::new(vertices, polygons)
+    let saturation = lerp(0.3f32, 1f32, (index as f32 / nb_polygons as f32) % 1f32);
+    Color::hsl(
+        ((index as f32 / nb_polygons as f32) * 360f32 + index as f32 * 12313f32) % 360f32,
+        saturation,
+        0.5f32,
+    )
 }
 
-/// Returns an bevy::Mesh with triangles
-pub fn bevymesh_from_trimesh(triangles_mesh: &TriangleMesh) -> bevy::prelude::Mesh {
+/// Returns an bevy::Mesh with triangles, laid out by `mapping`.
+#[cfg(feature = "render")]
+pub fn bevymesh_from_trimesh(triangles_mesh: &TriangleMesh, mapping: &PlaneMapping) -> Mesh {
     use bevy::render::{mesh::Indices, prelude::*, render_resource::PrimitiveTopology};
 
     let mut new_mesh = Mesh::new(PrimitiveTopology::TriangleList);
@@ -93,18 +33,19 @@ pub fn bevymesh_from_trimesh(triangles_mesh: &TriangleMesh) -> bevy::prelude::Me
         triangles_mesh
             .positions
             .iter()
-            .map(|v| [v.x, 0.0, v.y])
+            .map(|v| mapping.to_world(*v).to_array())
             .collect::<Vec<[f32; 3]>>(),
     );
     new_mesh.set_indices(Some(Indices::U32(
-        triangles_mesh.indices.iter().rev().copied().collect(),
+        triangles_mesh
+            .indices
+            .chunks_exact(3)
+            .flat_map(|t| mapping.front_facing([t[0], t[1], t[2]]))
+            .collect(),
     )));
     new_mesh.insert_attribute(
         Mesh::ATTRIBUTE_NORMAL,
-        (0..triangles_mesh.positions.len())
-            .into_iter()
-            .map(|_| [0.0, 1.0, 0.0])
-            .collect::<Vec<[f32; 3]>>(),
+        vec![mapping.normal().to_array(); triangles_mesh.positions.len()],
     );
 
     new_mesh.insert_attribute(
@@ -117,71 +58,3 @@ pub fn bevymesh_from_trimesh(triangles_mesh: &TriangleMesh) -> bevy::prelude::Me
     );
     new_mesh
 }
-
-mod test {
-    use bevy::prelude::Vec2;
-    use polyanya::{Polygon, Vertex};
-
-    use super::{create_grid_trimesh, navmesh_from_trimesh, TriangleMesh};
-
-    fn trimesh_3_3_10() -> TriangleMesh {
-        TriangleMesh {
-            indices: vec![
-                0, 3, 1, 4, 1, 3, 1, 4, 2, 5, 2, 4, 3, 6, 4, 7, 4, 6, 4, 7, 5, 8, 5, 7,
-            ],
-            positions: vec![
-                Vec2 { x: 0.0, y: 0.0 },
-                Vec2 { x: 10.0, y: 0.0 },
-                Vec2 { x: 20.0, y: 0.0 },
-                Vec2 { x: 0.0, y: 10.0 },
-                Vec2 { x: 10.0, y: 10. },
-                Vec2 { x: 20.0, y: 10. },
-                Vec2 { x: 0.0, y: 20.0 },
-                Vec2 { x: 10.0, y: 20. },
-                Vec2 { x: 20.0, y: 20. },
-            ],
-        }
-    }
-
-    #[test]
-    fn test_trimesh_3_3_10() {
-        let trimesh = create_grid_trimesh(3, 3, 10f32);
-        assert_eq!(trimesh, trimesh_3_3_10())
-    }
-    #[test]
-    fn test_navmesh_from_trimesh_3_3_10() {
-        let trimesh = trimesh_3_3_10();
-        let navmesh = navmesh_from_trimesh(&trimesh);
-        assert_eq!(
-            navmesh.vertices,
-            vec![
-                Vertex::new(Vec2::new(0.0, 0.0,), vec![0,]),
-                Vertex::new(Vec2::new(10.0, 0.0,), vec![0, 1, 2,]),
-                Vertex::new(Vec2::new(20.0, 0.0,), vec![2, 3,],),
-                Vertex::new(Vec2::new(0.0, 10.0,), vec![0, 1, 4,],),
-                Vertex::new(Vec2::new(10.0, 10.0,), vec![1, 2, 3, 4, 5, 6,],),
-                Vertex::new(Vec2::new(20.0, 10.0,), vec![3, 6, 7,],),
-                Vertex::new(Vec2::new(0.0, 20.0,), vec![4, 5,],),
-                Vertex::new(Vec2::new(10.0, 20.0,), vec![5, 6, 7,],),
-                Vertex::new(Vec2::new(20.0, 20.0,), vec![7,],),
-            ]
-        );
-        assert_eq!(
-            navmesh
-                .polygons
-                .into_iter()
-                .map(|v| (v.vertices, v.is_one_way))
-                .collect::<Vec<_>>(),
-            [
-                (vec![0, 3, 1,], false,),
-                (vec![4, 1, 3,], false,),
-                (vec![1, 4, 2,], false,),
-                (vec![5, 2, 4,], false,),
-                (vec![3, 6, 4,], false,),
-                (vec![7, 4, 6,], false,),
-                (vec![4, 7, 5,], false,),
-                (vec![8, 5, 7,], false,),
-            ]
-        )
-    }
-}
@@ -0,0 +1,116 @@
+use bevy::{prelude::*, utils::HashMap};
+
+/// Uniform grid of the vertex positions of an `EditableMesh`,
+/// to pick vertices without spawning an entity per vertex.
+#[derive(Component, Debug, Clone, Default)]
+pub struct VertexIndex {
+    cell_size: f32,
+    positions: Vec<Vec2>,
+    cells: HashMap<(i32, i32), Vec<u32>>,
+}
+
+impl VertexIndex {
+    pub fn new(positions: Vec<Vec2>, cell_size: f32) -> Self {
+        let mut cells: HashMap<(i32, i32), Vec<u32>> = HashMap::new();
+        for (vertex_id, position) in positions.iter().enumerate() {
+            cells
+                .entry(cell_of(*position, cell_size))
+                .or_default()
+                .push(vertex_id as u32);
+        }
+        VertexIndex {
+            cell_size,
+            positions,
+            cells,
+        }
+    }
+
+    pub fn positions(&self) -> &[Vec2] {
+        &self.positions
+    }
+
+    /// Vertices whose position differs in `positions`, which must have as many vertices.
+    pub fn moved_vertices(&self, positions: &[Vec2]) -> Vec<u32> {
+        self.positions
+            .iter()
+            .zip(positions)
+            .enumerate()
+            .filter(|(_, (indexed, position))| indexed != position)
+            .map(|(vertex_id, _)| vertex_id as u32)
+            .collect()
+    }
+
+    /// Moves a single vertex, changing its cell if needed.
+    pub fn move_vertex(&mut self, vertex_id: u32, position: Vec2) {
+        let from = cell_of(self.positions[vertex_id as usize], self.cell_size);
+        let to = cell_of(position, self.cell_size);
+        if from != to {
+            if let Some(cell) = self.cells.get_mut(&from) {
+                cell.retain(|v| *v != vertex_id);
+                if cell.is_empty() {
+                    self.cells.remove(&from);
+                }
+            }
+            self.cells.entry(to).or_default().push(vertex_id);
+        }
+        self.positions[vertex_id as usize] = position;
+    }
+
+    /// Closest vertex to `position` within `radius`.
+    pub fn nearest(&self, position: Vec2, radius: f32) -> Option<u32> {
+        let (min_x, min_y) = cell_of(position - radius, self.cell_size);
+        let (max_x, max_y) = cell_of(position + radius, self.cell_size);
+        (min_x..=max_x)
+            .flat_map(|x| (min_y..=max_y).map(move |y| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .map(|vertex_id| {
+                (
+                    *vertex_id,
+                    self.positions[*vertex_id as usize].distance(position),
+                )
+            })
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(vertex_id, _)| vertex_id)
+    }
+}
+
+fn cell_of(position: Vec2, cell_size: f32) -> (i32, i32) {
+    let cell = (position / cell_size).floor();
+    (cell.x as i32, cell.y as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec2;
+
+    use super::VertexIndex;
+
+    #[test]
+    fn nearest_across_cells() {
+        let index = VertexIndex::new(
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(0.9, 0.0),
+                Vec2::new(-1.2, 5.0),
+            ],
+            1.0,
+        );
+        assert_eq!(index.nearest(Vec2::new(1.1, 0.1), 0.5), Some(1));
+        assert_eq!(index.nearest(Vec2::new(-0.1, -0.1), 0.5), Some(0));
+        assert_eq!(index.nearest(Vec2::new(-1.0, 4.5), 0.5), None);
+        assert_eq!(index.nearest(Vec2::new(-1.0, 4.8), 0.5), Some(2));
+    }
+
+    #[test]
+    fn moved_vertex_changes_cell() {
+        let mut index = VertexIndex::new(vec![Vec2::new(0.0, 0.0), Vec2::new(3.0, 0.0)], 1.0);
+        let positions = [Vec2::new(0.0, 0.0), Vec2::new(5.5, 5.5)];
+        assert_eq!(index.moved_vertices(&positions), vec![1]);
+        index.move_vertex(1, positions[1]);
+        assert!(index.moved_vertices(&positions).is_empty());
+        assert_eq!(index.nearest(Vec2::new(3.0, 0.0), 0.5), None);
+        assert_eq!(index.nearest(Vec2::new(5.4, 5.4), 0.5), Some(1));
+    }
+}